@@ -1,21 +1,26 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![cfg_attr(not(test), no_std)]
 /*!
 Allows inlining fields into another struct.
 This derive processes `#[flatten]` attributes and inlines the field definitions
 for those types, the names of flattened fields are ignored. Note that this
 macro generates a new macro with the same name as the derived type that is
-exported with `pub(crate)`. This generated macro can be used to inspect the
-field definitions of the derived type. This mechanism is what allows flattening
+exported with `pub(crate)` by default. This generated macro can be used to
+inspect the field definitions of the derived type. This mechanism is what allows flattening
 types so any types that will later be flattened needs to also use this derive
 macro.
-Due to declarative macro limitations any `#[flatten]` attributes need to be
-the first attribute for a field or it won't be found by the macro.
+The `#[flatten]` attribute can be placed anywhere among the attributes of a
+field. `#[cfg(...)]` attributes of a flattened field apply to all of its
+inlined fields, any other attributes of a flattened field are ignored.
 The code used in this library is originally from [here][macro_source].
 
 In most cases you should not need this macro. But if you have different structs, which
 share a lot of fields and you want to avoid duplicating their declaration and avoid an
 indirection this is useful.
 
+The crate and the generated code only depend on `core`, so it can be used in `no_std`
+crates as well.
+
 [macro_source]: <https://users.rust-lang.org/t/is-implementing-a-derive-macro-for-converting-nested-structs-to-flat-structs-possible/65839/3>
 
 ```rust
@@ -49,231 +54,13757 @@ let base_struct = BaseStruct {
 #[doc(hidden)]
 pub use paste::paste as __private_codegen_paste;
 
+#[cfg(feature = "serde_json")]
+extern crate alloc;
+
+#[cfg(feature = "serde_json")]
+#[doc(hidden)]
+pub use {
+    alloc::string::String as __PrivateString, serde as __private_serde_dep,
+    serde_json as __private_serde_json,
+};
+
+/// Counts how often a field name is declared, for `#[flatten(assert_unique)]`.
+#[doc(hidden)]
+pub const fn __private_count_field(field_names: &[&str], field_name: &str) -> usize {
+    let mut count = 0;
+    let mut i = 0;
+    while i < field_names.len() {
+        if __private_str_eq(field_names[i], field_name) {
+            count += 1;
+        }
+        i += 1;
+    }
+    count
+}
+
+/// Finds the first field name that is declared more than once and returns the
+/// parts of the compile error naming it and the types of its first two
+/// declarations, or only empty parts if all field names are unique. See
+/// [`__private_concat`] for joining the parts.
+#[doc(hidden)]
+pub const fn __private_duplicate(
+    field_names: &[&'static str],
+    field_types: &[&'static str],
+) -> [&'static str; 8] {
+    let mut j = 1;
+    while j < field_names.len() {
+        let mut i = 0;
+        while i < j {
+            if __private_str_eq(field_names[i], field_names[j]) {
+                return [
+                    "field `",
+                    field_names[j],
+                    "` is declared more than once, with the types `",
+                    field_types[i],
+                    "` and `",
+                    field_types[j],
+                    "`, use `#[flatten(prefix = \"...\")]` or `#[flatten(rename(...))]` \
+                     to disambiguate inlined fields or `#[flatten(on_conflict = \"...\")]` ",
+                    "to keep only one of them",
+                ];
+            }
+            i += 1;
+        }
+        j += 1;
+    }
+    [""; 8]
+}
+
+const fn __private_str_eq(left: &str, right: &str) -> bool {
+    let (left, right) = (left.as_bytes(), right.as_bytes());
+    let mut equal = left.len() == right.len();
+    let mut i = 0;
+    while equal && i < left.len() {
+        equal = left[i] == right[i];
+        i += 1;
+    }
+    equal
+}
+
+/// The length of the parts joined by [`__private_concat`].
+#[doc(hidden)]
+pub const fn __private_concat_len(parts: &[&str]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        len += parts[i].len();
+        i += 1;
+    }
+    len
+}
+
+/// Joins string parts computed in a const context, e.g. the error of
+/// [`__private_duplicate`].
+#[doc(hidden)]
+pub const fn __private_concat<const N: usize>(parts: &[&str]) -> [u8; N] {
+    let mut joined = [0; N];
+    let mut len = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        let part = parts[i].as_bytes();
+        let mut j = 0;
+        while j < part.len() {
+            joined[len] = part[j];
+            len += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    joined
+}
+
+/// Strips the `r#` of raw identifiers from a stringified field name.
+#[doc(hidden)]
+pub const fn __private_unraw(name: &'static str) -> &'static str {
+    match name.as_bytes() {
+        [b'r', b'#', rest @ ..] => match core::str::from_utf8(rest) {
+            Ok(name) => name,
+            Err(_) => name,
+        },
+        _ => name,
+    }
+}
+
+/// The length of a field name after applying a serde `rename_all` rule, see
+/// [`__private_serde_case`].
+#[cfg(feature = "serde_json")]
+#[doc(hidden)]
+pub const fn __private_serde_case_len(name: &str, rule: &str) -> usize {
+    let name = name.as_bytes();
+    match rule.as_bytes() {
+        b"PascalCase" | b"camelCase" => {
+            let mut len = 0;
+            let mut index = 0;
+            while index < name.len() {
+                if name[index] != b'_' {
+                    len += 1;
+                }
+                index += 1;
+            }
+            len
+        }
+        _ => name.len(),
+    }
+}
+
+/// Applies a serde `rename_all` rule to a field name the way serde renames
+/// fields, e.g. for the keys of `#[flatten_derive(try_from_map)]`. Unknown
+/// rules keep the name, serde rejects them already.
+#[cfg(feature = "serde_json")]
+#[doc(hidden)]
+pub const fn __private_serde_case<const N: usize>(name: &str, rule: &str) -> [u8; N] {
+    let name = name.as_bytes();
+    let rule = rule.as_bytes();
+    let mut key = [0; N];
+    let mut index = 0;
+    let mut len = 0;
+    let mut capitalize = true;
+    while index < name.len() {
+        let byte = name[index];
+        index += 1;
+        match rule {
+            b"UPPERCASE" | b"SCREAMING_SNAKE_CASE" => key[len] = byte.to_ascii_uppercase(),
+            b"kebab-case" if byte == b'_' => key[len] = b'-',
+            b"SCREAMING-KEBAB-CASE" if byte == b'_' => key[len] = b'-',
+            b"SCREAMING-KEBAB-CASE" => key[len] = byte.to_ascii_uppercase(),
+            b"PascalCase" | b"camelCase" if byte == b'_' => {
+                capitalize = true;
+                continue;
+            }
+            b"PascalCase" | b"camelCase" if capitalize => {
+                key[len] = if len == 0 && matches!(rule, b"camelCase") {
+                    byte.to_ascii_lowercase()
+                } else {
+                    byte.to_ascii_uppercase()
+                };
+                capitalize = false;
+            }
+            _ => key[len] = byte,
+        }
+        len += 1;
+    }
+    key
+}
+
+/// Turns the bytes of [`__private_serde_case`] back into a `str`.
+#[cfg(feature = "serde_json")]
+#[doc(hidden)]
+pub const fn __private_serde_key(key: &'static [u8]) -> &'static str {
+    match core::str::from_utf8(key) {
+        Ok(key) => key,
+        Err(_) => panic!("renamed field name is not UTF-8"),
+    }
+}
+
+/// A field and the value merged into it by `#[flatten_derive(merge)]`, see
+/// [`__PrivateMergeOption`] and [`__PrivateMergeValue`].
+#[doc(hidden)]
+pub struct __PrivateMergeField<'a, T> {
+    pub field: &'a mut T,
+    pub other: T,
+}
+
+/// Merges `Option` fields, only overwriting them if the other value is
+/// `Some`. It takes the field by value, so method resolution prefers it over
+/// [`__PrivateMergeValue`] for `Option` fields.
+#[doc(hidden)]
+pub trait __PrivateMergeOption {
+    fn __private_merge(self);
+}
+
+impl<T> __PrivateMergeOption for __PrivateMergeField<'_, Option<T>> {
+    fn __private_merge(self) {
+        if self.other.is_some() {
+            *self.field = self.other;
+        }
+    }
+}
+
+/// Merges any other field by overwriting it.
+#[doc(hidden)]
+pub trait __PrivateMergeValue {
+    fn __private_merge(&mut self);
+}
+
+impl<T> __PrivateMergeValue for __PrivateMergeField<'_, T> {
+    fn __private_merge(&mut self) {
+        core::mem::swap(self.field, &mut self.other);
+    }
+}
+
+/// Implemented for the structs declared with `flatten_structs!`, checked for
+/// the flattened types of structs with `#[flatten(strict)]`.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not declared with `flatten_structs!`",
+    label = "flattened here",
+    note = "`#[flatten(strict)]` only allows flattening structs declared with `flatten_structs!`"
+)]
+pub trait __PrivateFlattened {}
+
+/// Asserts that a flattened type is declared with `flatten_structs!`, see
+/// [`__PrivateFlattened`].
+#[doc(hidden)]
+pub const fn __private_assert_flattened<T: ?Sized + __PrivateFlattened>() {}
+
+/// The length of the fields listed by `dump_fields!`, see [`__private_dump`].
+#[doc(hidden)]
+pub const fn __private_dump_len(fields: &[&[&str]]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < fields.len() {
+        let mut words: usize = 0;
+        let mut j = 0;
+        while j < fields[i].len() {
+            if !fields[i][j].is_empty() {
+                len += fields[i][j].len();
+                words += 1;
+            }
+            j += 1;
+        }
+        // The spaces between the words, followed by `,\n`
+        len += words.saturating_sub(1) + 2;
+        i += 1;
+    }
+    len
+}
+
+/// Lists the fields for `dump_fields!`, every field is given by its
+/// stringified parts that are joined by spaces, leaving out empty parts like
+/// private visibilities.
+#[doc(hidden)]
+pub const fn __private_dump<const N: usize>(fields: &[&[&str]]) -> [u8; N] {
+    let mut dump = [0; N];
+    let mut len = 0;
+    let mut i = 0;
+    while i < fields.len() {
+        let mut first = true;
+        let mut j = 0;
+        while j < fields[i].len() {
+            let word = fields[i][j].as_bytes();
+            if !word.is_empty() {
+                if !first {
+                    dump[len] = b' ';
+                    len += 1;
+                }
+                first = false;
+                let mut k = 0;
+                while k < word.len() {
+                    dump[len] = word[k];
+                    len += 1;
+                    k += 1;
+                }
+            }
+            j += 1;
+        }
+        dump[len] = b',';
+        dump[len + 1] = b'\n';
+        len += 2;
+        i += 1;
+    }
+    dump
+}
+
+/// The error returned by builders generated with `#[flatten_derive(builder)]`
+/// when a field wasn't set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingField {
+    /// The name of the field that wasn't set.
+    pub field: &'static str,
+}
+
+impl core::fmt::Display for MissingField {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "missing field `{}`", self.field)
+    }
+}
+
+impl core::error::Error for MissingField {}
+
+/// The error returned when a struct with `#[flatten_derive(try_from_map)]`
+/// can't be created from a map, listing every offending key.
+#[cfg(feature = "serde_json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde_json")))]
+#[derive(Debug, Default)]
+pub struct MapError {
+    /// The keys of the fields without a default value that the map doesn't
+    /// have.
+    pub missing: alloc::vec::Vec<&'static str>,
+    /// The keys of the map that aren't fields of the struct.
+    pub unknown: alloc::vec::Vec<alloc::string::String>,
+    /// The keys of the fields whose values failed to deserialize, with their
+    /// errors.
+    pub invalid: alloc::vec::Vec<(&'static str, serde_json::Error)>,
+}
+
+#[cfg(feature = "serde_json")]
+impl MapError {
+    /// Whether there are no offending keys.
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unknown.is_empty() && self.invalid.is_empty()
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl core::fmt::Display for MapError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut separator = "";
+        for field in &self.missing {
+            write!(f, "{separator}missing field `{field}`")?;
+            separator = ", ";
+        }
+        for key in &self.unknown {
+            write!(f, "{separator}unknown field `{key}`")?;
+            separator = ", ";
+        }
+        for (field, error) in &self.invalid {
+            write!(f, "{separator}invalid field `{field}`: {error}")?;
+            separator = ", ";
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl core::error::Error for MapError {}
+
+/// The layout of a struct generated with `#[flatten_derive(layout)]`, e.g. to
+/// check that flattening didn't introduce unexpected padding. Its `Display`
+/// lists the byte ranges of the fields and the padding between them.
+#[cfg(feature = "layout")]
+#[cfg_attr(docsrs, doc(cfg(feature = "layout")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    /// The name of the struct.
+    pub name: &'static str,
+    /// The size of the struct in bytes.
+    pub size: usize,
+    /// The alignment of the struct in bytes.
+    pub align: usize,
+    /// The fields of the struct, including the inlined ones, in the order they
+    /// are declared in.
+    pub fields: &'static [FieldLayout],
+}
+
+/// The position of a field in a [`Layout`].
+#[cfg(feature = "layout")]
+#[cfg_attr(docsrs, doc(cfg(feature = "layout")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldLayout {
+    /// The name of the field.
+    pub name: &'static str,
+    /// The offset of the field from the start of the struct in bytes.
+    pub offset: usize,
+    /// The size of the field in bytes.
+    pub size: usize,
+}
+
+#[cfg(feature = "layout")]
+impl FieldLayout {
+    /// The byte range of the field in the struct.
+    pub const fn range(&self) -> core::ops::Range<usize> {
+        self.offset..self.offset + self.size
+    }
+}
+
+#[cfg(feature = "layout")]
+impl Layout {
+    /// The number of bytes of the struct that don't belong to any field.
+    pub const fn padding(&self) -> usize {
+        let mut padding = self.size;
+        let mut i = 0;
+        while i < self.fields.len() {
+            padding -= self.fields[i].size;
+            i += 1;
+        }
+        padding
+    }
+
+    /// The next field by offset after the given one, fields at the same offset
+    /// are ordered as declared.
+    fn next_field(&self, after: Option<(usize, usize)>) -> Option<(usize, usize)> {
+        self.fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| (field.offset, i))
+            .filter(|&key| after.is_none_or(|after| key > after))
+            .min()
+    }
+}
+
+#[cfg(feature = "layout")]
+impl core::fmt::Display for Layout {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "`{}`: {} bytes, aligned to {} bytes",
+            self.name, self.size, self.align
+        )?;
+        let mut end = 0;
+        let mut next = self.next_field(None);
+        while let Some((offset, i)) = next {
+            let field = &self.fields[i];
+            if offset > end {
+                write!(f, "\n  {end}..{offset} padding")?;
+            }
+            write!(f, "\n  {:?} `{}`", field.range(), field.name)?;
+            end = end.max(field.range().end);
+            next = self.next_field(Some((offset, i)));
+        }
+        if self.size > end {
+            write!(f, "\n  {end}..{} padding", self.size)?;
+        }
+        Ok(())
+    }
+}
+
+/// Declares a struct and inlines the fields of all its `#[flatten]` fields.
+///
+/// See the [crate level documentation](crate) for an overview. The declared
+/// struct can have generic parameters, lifetimes, const generic parameters like
+/// `const N: usize` and a `where` clause, they are kept as written. Fields can
+/// have any type, only the types of `#[flatten]` fields need to be paths to
+/// types declared with this macro. Generic types
+/// are flattened with their generic arguments, e.g. `#[flatten] range:
+/// Range<u32>`, the inlined fields then use the types for these arguments.
+/// The arguments can be parameters of the declared struct, e.g. `struct
+/// Flat<U> { #[flatten] inner: Inner<Vec<U>> }`, whatever the flattened type
+/// names its own parameters.
+/// Type aliases don't have an inspection macro, they can be flattened with
+/// `#[flatten(via = Type)]` naming the aliased type. The same works for
+/// associated types, e.g. `<Self as Schema>::Group`, the fields of the named
+/// type are inlined whatever the associated type resolves to.
+///
+/// One invocation can declare several structs after each other, each of them
+/// is declared as if it had its own invocation.
+/// Structs and groups without fields can be declared and flattened too, they
+/// don't inline any fields, e.g. as placeholders.
+///
+/// Enums can flatten types into their struct-like variants, e.g.
+/// `enum Event { Created { #[flatten] common: Common, id: u32 } }`, their
+/// tuple and unit variants are kept as they are. Inlined fields drop their
+/// visibility and default value, which variant fields can't have. Enums
+/// can't have generic parameters or be flattened themselves, nothing else is
+/// generated for them, and the flatten options generating code for the
+/// struct, e.g. `reconstruct` or `field_count`, are a compile error in their
+/// variants.
+///
+/// The attributes of fields, including doc comments, are kept in the order they
+/// are written, both for the fields of the struct and for the inlined fields.
+/// Attributes of other derive macros, e.g. `#[validate(range(min = 0))]`,
+/// follow the inlined fields, so they apply to the declared struct.
+/// Flatten options that add attributes to inlined fields put them in front.
+/// Deprecated fields stay deprecated, the generated code using them doesn't
+/// warn about it.
+/// Conditional attributes like `#[cfg_attr(feature = "serde", serde(...))]`
+/// are kept like any other attribute, on the fields as well as on the struct.
+/// The attributes configuring this macro, e.g. `#[flatten]` or
+/// `#[flatten_export(...)]`, can't be conditional though.
+///
+/// The inspection macro of a type is imported with a `use` like other items,
+/// so types can be flattened before they are declared, in the same module or
+/// in another one. It only exists once its own flattened fields have been
+/// inlined though, so types can't be flattened into themselves, directly or
+/// through other types. The compiler reports such cycles as a macro that can't
+/// be found instead of recursing, here "cannot find macro `Node` in this
+/// scope":
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Node {
+///         id: u32,
+///         #[flatten]
+///         next: Node,
+///     }
+/// );
+/// ```
+///
+/// Fields can be declared with a default value, e.g. `retries: u32 = 3`. The
+/// struct then implements `Default` with these values, fields without a
+/// default value use their own `Default`. Default values of flattened types are
+/// inlined with their fields, so they need to be nameable where the type is
+/// flattened, and flattening a type with default values implements `Default`
+/// for the struct as well.
+///
+/// The declared struct gets a `FIELD_NAMES` associated constant listing the
+/// names of all its fields in order, as they are named after flattening. Raw
+/// identifiers like `r#type` are listed without their `r#`. `FIELD_COUNT` is
+/// the number of these fields, and the `field_names()` method returns the same
+/// names for code that only has a value of the struct, e.g. in a trait
+/// implementation.
+///
+/// A flattened field disabled by its `#[cfg(...)]` attributes inlines no
+/// fields at all, so they are left out of `FIELD_NAMES`, the `Default`
+/// implementation and the `#[flatten_derive(...)]` code as well. Its type
+/// isn't inspected then and may be disabled by the same `cfg`.
+///
+/// The generated trait implementations are marked `#[automatically_derived]`
+/// like the output of derive macros, so tools such as coverage reports treat
+/// them as generated code. Lints about how the impl is written, like
+/// `single_use_lifetimes`, skip them, and the generated inherent
+/// implementations allow these lints as well.
+///
+/// Tuple structs without generic parameters are supported too, their
+/// `#[flatten]` fields inline the fields of the flattened type by position.
+/// The flattened type can be a tuple struct or a struct with named fields,
+/// whose fields are inlined in the order they are declared. Flatten options
+/// can't be used for tuple structs. Only newtypes, tuple structs with a single
+/// field like `struct Meters(pub f32);`, can be flattened into structs with
+/// named fields. As their field has no name, it's inlined under the name of
+/// the flattened field, e.g. `#[flatten] distance: Meters` declares
+/// `distance: f32` with the visibility of the newtype's field, and options
+/// like `prefix` or `rename(distance = meters)` apply to that name. The
+/// options that create the flattened type from its fields, `reconstruct`,
+/// `into_nested` and `serde_accept_nested`, are a compile error for newtypes,
+/// and `retain` doesn't support them either.
+///
+/// ```rust
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Packet(#[flatten] Header, u32, u32);
+/// );
+///
+/// flatten_structs!(
+///     struct Header(u8, bool);
+/// );
+///
+/// let packet = Packet(1, true, 2, 3);
+/// ```
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Route {
+///         #[flatten(reconstruct)]
+///         distance: Meters,
+///     }
+///
+///     struct Meters(pub f32);
+/// );
+/// ```
+///
+/// # Flatten options
+///
+/// `#[flatten(...)]` accepts a comma separated list of options that change how
+/// the fields of the flattened type are inlined:
+///
+/// - `serde`: doesn't inline the fields, the field is kept as it is declared
+///   and serde flattens it with `#[serde(flatten)]` instead. The type of the
+///   field doesn't need to be declared with this macro then. Can't be combined
+///   with other options. Needs the `serde` feature.
+/// - `prefix = "..."`: prepends the literal to the name of every inlined field.
+///   Fields of nested flattened types have their prefixes already, so the
+///   prefixes of each level are combined, e.g. `b_c_field`. Only the names of
+///   the fields change, so an inlined field with `#[serde(rename = "...")]` is
+///   still serialized under that name, while the other fields are serialized
+///   under their prefixed names unless `serde_keep_case` is used.
+/// - `prefix = auto`: prepends the name of the flattened field and `_` only to
+///   the inlined fields whose names are taken by the fields declared or
+///   inlined before, e.g. `created_id`, the other fields keep their names.
+/// - `suffix = "..."`: appends the literal to the name of every inlined field.
+///   Can be combined with `prefix`.
+/// - `vis(...)`: the visibility of every inlined field, e.g. `vis(pub(crate))`
+///   or `vis()` for private fields. They have the visibility of the flattened
+///   field by default.
+/// - `vis = inherit_source`: the inlined fields keep the visibility they are
+///   declared with in the flattened type, so private fields stay private even
+///   if the flattened field is `pub`. `vis = inherit_flatten` is the default
+///   of giving them the visibility of the flattened field.
+/// - `cfg(...)`: inlines the fields only if the predicate holds, e.g.
+///   `cfg(feature = "extended")`, like a `#[cfg(...)]` attribute of the
+///   flattened field, except that the flattened type is still inspected. Can
+///   be combined with such attributes, the fields are then inlined if all of
+///   them hold.
+/// - `rename(old = new, ...)`: renames single inlined fields, `old` is the name
+///   of the field in the flattened type. The renamed fields get exactly the
+///   given name, `prefix` and `suffix` are not applied to them. Naming a field
+///   the flattened type doesn't have is a compile error.
+/// - `keep(name, ...)`: the inlined fields with these names keep the name
+///   they are declared with in the flattened type, e.g. a shared `id` with
+///   `prefix = "left_", keep(id)`. Like `rename`, naming a field the flattened
+///   type doesn't have is a compile error. Serialized names set by
+///   `rename_all` are not kept, rename such a field with
+///   `#[serde(rename = "...")]` in the flattened type instead.
+/// - `skip(name, ...)`: doesn't inline the fields with these names in the
+///   flattened type. Naming a field the flattened type doesn't have is a
+///   compile error. Can't be combined with `reconstruct`, `into_nested` and
+///   `#[flatten_derive(split)]`, which need all fields of the flattened type,
+///   doing so is a compile error.
+/// - `only(name, ...)`: only inlines the fields with these names in the
+///   flattened type, the opposite of `skip`. Can't be combined with `skip`
+///   and the same restrictions apply.
+/// - `via = Type`: inlines the fields of the given type declared with this
+///   macro instead of looking up the type of the field, e.g. for type aliases
+///   and associated types which have no inspection macro of their own. The
+///   type of the field is not used otherwise, it should name the same type
+///   for readability.
+/// - `field_attr(...)`: adds the comma separated attributes to every inlined
+///   field in front of its own attributes, e.g. `field_attr(serde(default))`.
+/// - `serde_default`: deserializes every inlined field with serde from its
+///   `Default` if it's missing, the same as `field_attr(serde(default))`. The
+///   fields don't become `Option`s, unlike with an `Option` of the flattened
+///   type. Needs the `serde` feature.
+/// - `serde_accept_nested`: implements serde's `Deserialize` for the struct,
+///   accepting the inlined fields either under their own keys or nested in an
+///   object under the name of the flattened field, like before flattening,
+///   e.g. while migrating a schema. With several such fields, either all of
+///   them are nested or none. The struct can't derive `Deserialize` itself,
+///   usually it still derives `Serialize`, which its `serde` attributes need.
+///   Only the `serde` and `cfg` attributes of its fields and its
+///   `#[serde(rename_all = "...")]` are applied, and the flattened type needs
+///   to implement `Deserialize`. A flattened `Box` is accepted nested as well,
+///   a flattened `Option` is a compile error since its fields can only be
+///   deserialized flat. Needs the `serde` feature.
+/// - `doc_prefix = "..."`: adds the literal as the first line of the docs of
+///   every inlined field, e.g. to note where the field came from.
+/// - `serde_keep_case`: serializes every inlined field with serde under the
+///   same name as in the flattened type, following its
+///   `#[serde(rename_all = "...")]`. Only the cases that don't need to split
+///   words are supported, `"camelCase"` and the kebab cases are not. Needs
+///   the `serde` feature.
+/// - `rename_all = "..."`: serializes every inlined field with serde under its
+///   name in the given case, as named when the option is applied, e.g.
+///   `rename_all = "SCREAMING_SNAKE_CASE"` after `prefix = "left_"` gives
+///   `LEFT_VALUE`. Fields renamed with `#[serde(rename = "...")]` keep their
+///   name. Supports the same cases as `serde_keep_case` and needs the
+///   `serde` feature as well.
+/// - `on_conflict = "..."`: what to do when an inlined field has the same name
+///   as a field declared before it. `"error"` (the default) reports the
+///   duplicate, `"first"` keeps the earlier field and `"last"` replaces it
+///   with the inlined one. `"merge"` keeps the earlier field like `"first"`,
+///   but only for fields of the same type, conflicting fields whose types are
+///   written differently fail to compile with an error naming both types.
+///   Identical fields are dropped with `dedup`, which isn't a policy of
+///   `on_conflict`.
+/// - `dedup`: drops the inlined fields that are declared exactly like a field
+///   declared before them, with the same name, type and attributes, e.g. the
+///   fields of a type that is flattened into two of the flattened types.
+///   Conflicting fields that are declared differently fail to compile.
+/// - `reconstruct`: generates a `to_<field>()` method, named after the
+///   flattened field, that clones the inlined fields back into a value of the
+///   flattened type. All inlined fields need to implement `Clone`.
+/// - `into_nested`: implements `From<&Struct>` for the flattened type, which
+///   clones the inlined fields back like `reconstruct`, e.g. `let nested:
+///   Nested = (&flat).into()`.
+/// - `view`: generates a struct holding references to the inlined fields under
+///   their names in the flattened type and a method returning it, both named
+///   after the flattened field. The struct is prefixed with the name of the
+///   struct so views of several structs don't collide, e.g. `fn nested(&self)
+///   -> StructNestedRef<'_>`.
+/// - `view_mut`: like `view` with mutable references, the struct and method
+///   are named with `Mut` and `_mut`, e.g. `fn nested_mut(&mut self) ->
+///   StructNestedMut<'_>`, so the inlined fields can be changed as a group.
+/// - `field_count`: generates an associated constant with the number of
+///   inlined fields, named after the flattened field, e.g.
+///   `NESTED_FIELD_COUNT`.
+/// - `assert_unique`: checks the names of the inlined fields with a const
+///   assertion that fails to evaluate for a duplicated field, naming it and
+///   the flattened field. The assertion naming the types of duplicated fields
+///   is not generated for the struct then, the compiler still reports the
+///   duplicated field itself.
+/// - `retain`: keeps the flattened field itself in front of its inlined
+///   fields, e.g. while migrating code between both. Usually combined with
+///   `prefix` so the inlined fields don't clash with other fields.
+///
+/// ```rust
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Sensors {
+///         #[flatten(prefix = "left_")]
+///         left: Sensor,
+///         #[flatten(prefix = "right_")]
+///         right: Sensor,
+///     }
+/// );
+///
+/// flatten_structs!(
+///     struct Sensor {
+///         value: f32,
+///     }
+/// );
+///
+/// let sensors = Sensors {
+///     left_value: 0.0,
+///     right_value: 1.0,
+/// };
+/// ```
+///
+/// # Struct attributes
+///
+/// Attributes on the struct itself, e.g. `#[repr(C)]`, `#[non_exhaustive]` or
+/// `#[deprecated]`, are passed through to the generated struct in the order
+/// they are written. A `#[repr(...)]` applies to the inlined fields like to
+/// any other field, so `#[repr(C, packed)]` structs get the layout of their
+/// inlined fields without the padding a nested struct would add. These
+/// attributes configure the macro instead and are not passed through:
+///
+/// - `#[flatten_export(...)]`: the visibility of the generated inspection
+///   macro, `pub(crate)` by default. With `pub` the macro is also exported
+///   from the crate, so other crates can flatten the type. Field types need to
+///   be nameable from where the type is flattened, so spell out their full
+///   paths when exporting across crates. Flattened types can be named by any
+///   path, e.g. `#[flatten] header: crate::schema::Header`, which names the
+///   macro by the same path. A `use` of the struct imports the macro along
+///   with it, so re-exports like `pub use inner::Header` work as well, but
+///   only as far as the visibility of the macro allows. A macro can only
+///   declare items where it is called, so there is no attribute like
+///   `#[flatten_reexport(path)]` to re-export the inspection macro at another
+///   path. To flatten types from one central
+///   module, e.g. `crate::fields`, re-export them there with `pub(crate) use
+///   crate::net::Header;` and name them by that path.
+/// - `#[flatten_macro_name(name)]`: the name of the generated inspection macro,
+///   the name of the struct by default. Flattening the type then needs to name
+///   the macro instead, e.g. `#[flatten] field: name`. Useful when exported
+///   types with the same name would clash.
+/// - `#[flatten_as(Name, ...)]`: declares another struct named `Name` with the
+///   same visibility, generic parameters and fields, e.g. a variant for
+///   serialization with `#[flatten_as(FlatDto, derive(Serialize))]`. The
+///   struct only gets the attributes listed after the name, while the
+///   attributes of the fields are kept, so it needs the derives that use
+///   them. A `flatten_derive(...)` among them generates its code for the
+///   struct as well, named after it, e.g. `#[flatten_as(FlatDto,
+///   flatten_derive(builder))]` gives a `FlatDtoBuilder`. Nothing else is
+///   generated for it, it can't be flattened, and the attribute can be
+///   repeated for more structs.
+/// - `#[flatten_mod(name)]`: declares the generated inspection macro in a
+///   module with this name next to the struct, so its name can't clash with
+///   other macros. The struct itself is declared as usual, flattening it names
+///   the macro through the module, e.g. `#[flatten] field: name::Struct`, and
+///   the fields use the struct next to the module. The module has the
+///   visibility of the inspection macro and imports everything from its
+///   parent module.
+/// - `#[flatten(order(name, ...))]`: declares the fields in the listed order
+///   instead of the order they are declared and inlined in, e.g. to control
+///   the layout of a `#[repr(C)]` struct regardless of the flattened types.
+///   Every field, including the inlined ones, has to be listed exactly once,
+///   otherwise a const assertion naming the field fails to evaluate, and
+///   naming a field the struct doesn't have is a compile error. Only structs
+///   with named fields can be reordered.
+/// - `#[flatten(no_inspect)]`: doesn't declare the inspection macro, for
+///   structs that are never flattened. Their fields are still flattened as
+///   usual, and the name of the macro can't clash with other macros then.
+/// - `#[flatten(strict)]`: also checks that every flattened type is a struct
+///   declared with this macro. Flattening any other type fails with an error
+///   saying so next to the missing macro, even if a macro named like the type
+///   exists. Groups aren't types, flattening one into a strict struct is a
+///   compile error saying so. Only structs with named fields are checked.
+/// - `#[flatten(postprocess = macro_name)]`: calls the given macro with the
+///   gathered fields instead of declaring the struct, so it can declare the
+///   struct itself along with any other items. The macro is called with
+///   `attrs = { ... }, vis = { ... }, name = Struct, generics = { ... },
+///   where_clause = { ... }, fields = { ... },` where `generics` lacks the
+///   angle brackets and the fields, including the inlined ones, are listed
+///   like in the inspection macro, each followed by a comma and with its
+///   default value if there is one. Everything else generated for the struct
+///   still refers to it, so the macro needs to declare a struct with these
+///   fields. Only structs with named fields can be post-processed, the option
+///   is a compile error on tuple structs, groups and enums.
+/// - `#[flatten_derive(...)]`: a comma separated list of additional code to
+///   generate for the struct, including all inlined fields:
+///   - `builder`: a `<Struct>Builder` whose `default()` starts out with the
+///     default values of the fields, including the inlined ones, and leaves
+///     the other fields empty. It has a `with_<field>()` method for every field
+///     and a `build()` method that fails with [`MissingField`] if a field
+///     without a default value wasn't set.
+///   - `default`: implements `Default` with the `Default` of every field.
+///     Structs with default values, including the ones inlined from flattened
+///     types, implement it with these values already.
+///   - `accessors`: a `<field>()` getter returning a reference and a
+///     `set_<field>()` setter for every field, with the visibility of the
+///     struct.
+///   - `display`: implements `Display` listing every field with its value,
+///     e.g. `enable = true, value = 1`. All fields need to implement
+///     `Display`, generic parameters need to be bound by it.
+///   - `patch`: a `<Struct>Patch` with every field wrapped in an `Option`, all
+///     `None` with `default()`, and an `apply()` method on the struct that
+///     overwrites the fields that are set. `Option` fields become
+///     `Option<Option<_>>` so they can be patched to `None`. The fields of the
+///     patch have the visibility of the struct.
+///   - `split`: implements `From<Struct>` for a tuple of the flattened types,
+///     in the order of the flattened fields, moving the inlined fields into
+///     them. All fields of the struct need to be inlined from flattened
+///     fields, otherwise a const assertion naming the field fails to
+///     evaluate.
+///   - `from_parts`: a `from_parts()` function creating the struct from the
+///     values of its flattened fields, followed by the values of its other
+///     fields, moving the fields of the flattened types into it. Without other
+///     fields the struct also implements `From` for a tuple of the flattened
+///     types.
+///   - `const_new`: a `const fn new()` taking the value of every field,
+///     including the inlined ones, in the order they are declared, e.g. for
+///     `static` tables. Default values aren't used, every field is passed.
+///   - `eq_ignoring(name, ...)`: an `eq_ignoring_volatile()` method comparing
+///     all fields except the listed ones with `==`, e.g. to ignore timestamps.
+///     Naming a field the struct doesn't have makes a const assertion fail to
+///     evaluate.
+///   - `metadata`: a `field_metadata()` function returning the name and type
+///     of every field, e.g. `("value", "f32")`, to describe the struct
+///     without a reflection framework. The types are formatted by
+///     [`stringify!`], whose spacing isn't stable, e.g. the type of an
+///     inlined field can be `"Option < u32 >"`, so compare them without
+///     whitespace.
+///   - `try_from_map`: implements `TryFrom<serde_json::Map<String, Value>>`,
+///     deserializing every field from the value under its key, the name serde
+///     would use: its `#[serde(rename = "...")]`, including the ones added by
+///     flatten options, or its name in the case of the
+///     `#[serde(rename_all = "...")]` of the struct. Other serde attributes,
+///     e.g. `alias` or `default`, aren't used. Missing fields with a default
+///     value get it, all missing, unknown and invalid keys are reported
+///     together by [`MapError`]. Needs the `serde_json` feature.
+///   - `layout`: a `LAYOUT` constant with the size and alignment of the struct
+///     and the offset and size of every field, see [`Layout`], e.g. to check
+///     the padding of a `#[repr(C)]` struct. Needs the `layout` feature.
+///   - `doc_origins`: appends a paragraph listing the types of the flattened
+///     fields to the documentation of the struct, formatted by
+///     [`stringify!`].
+///   - `merge`: a `merge()` method taking another value of the struct whose
+///     fields overwrite the fields of `self`, e.g. to layer configurations.
+///     `Option` fields are only overwritten if the other value is `Some`, as
+///     long as the field is written as an `Option` and not as a generic
+///     parameter. `merge(skip(name, ...))` leaves the listed fields as they
+///     are, naming a field the struct doesn't have fails like for
+///     `eq_ignoring`.
+///
+/// Options that refer to inlined fields by name are checked against the
+/// fields of the flattened type:
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Sensors {
+///         #[flatten(rename(missing = other))]
+///         sensor: Sensor,
+///     }
+/// );
+///
+/// flatten_structs!(
+///     struct Sensor {
+///         value: f32,
+///     }
+/// );
+/// ```
+///
+/// With `vis = inherit_source` an inlined field that is private in the
+/// flattened type can't be accessed outside of the module of the struct, even
+/// though the flattened field is `pub`:
+///
+/// ```compile_fail,E0616
+/// mod account {
+///     use flatten_structs::flatten_structs;
+///
+///     flatten_structs!(
+///         pub struct User {
+///             #[flatten(vis = inherit_source)]
+///             pub account: Account,
+///         }
+///
+///         pub struct Account {
+///             pub name: String,
+///             secret: u64,
+///         }
+///     );
+///
+///     impl User {
+///         pub fn new() -> Self {
+///             Self {
+///                 name: String::new(),
+///                 secret: 0,
+///             }
+///         }
+///     }
+/// }
+///
+/// let user = account::User::new();
+/// let _ = user.secret;
+/// ```
+///
+/// Leaving out inlined fields with `skip` or `only` is a compile error for the
+/// options that need all fields of the flattened type:
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Sensors {
+///         #[flatten(skip(offset), reconstruct)]
+///         sensor: Sensor,
+///     }
+///
+///     struct Sensor {
+///         value: f32,
+///         offset: f32,
+///     }
+/// );
+/// ```
+///
+/// The options generating code for the struct fail in enum variants:
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     enum Event {
+///         Measured {
+///             #[flatten(field_count)]
+///             sensor: Sensor,
+///         },
+///     }
+///
+///     struct Sensor {
+///         value: f32,
+///     }
+/// );
+/// ```
+///
+/// Likewise `#[flatten(postprocess = macro_name)]` fails on anything but a
+/// struct with named fields:
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// macro_rules! declare {
+///     ($($item:tt)*) => {};
+/// }
+///
+/// flatten_structs!(
+///     #[flatten(postprocess = declare)]
+///     struct Reading(f32);
+/// );
+/// ```
+///
+/// Flattening an `Option` of a type, e.g. `#[flatten] maybe: Option<Nested>`,
+/// inlines the fields of the type wrapped in an `Option` each, so the whole
+/// group of fields can be absent. Fields that are `Option`s in the flattened
+/// type already become `Option<Option<_>>`. Default values of the inlined
+/// fields are not used, they are `None` by default. The generated code of
+/// `reconstruct`, `into_nested` and `view` needs the fields of the flattened
+/// type and doesn't support these fields.
+///
+/// Flattening a `Box` of a type, e.g. `#[flatten] boxed: Box<Nested>`, inlines
+/// the fields of the type like without the `Box`. The fields are stored in the
+/// struct directly then instead of behind the indirection of the `Box`, which
+/// changes its size and layout.
+///
+/// Fields that are only ever flattened can be declared as a group, e.g.
+/// `group CommonFields { id: u64, ts: u64 }`, which only declares the
+/// inspection macro and no struct. Groups are flattened like any other type,
+/// `#[flatten] common: CommonFields`, and take the struct attributes that
+/// configure the macro, e.g. `#[flatten_as(...)]` still declares a struct with
+/// the fields and the visibility of the group. Groups can't have generic
+/// parameters, and the options that need the flattened type itself, e.g.
+/// `reconstruct`, `view` or `retain`, don't support them.
+///
+/// Attributes can be placed in front of a struct, group or enum, in front of
+/// fields and, as inner attributes, at the start of the body of a struct or
+/// group, e.g. `#![derive(Debug)]` or `//!` doc comments. Inner attributes are
+/// handled as if they followed the attributes in front of the struct, so they
+/// can configure this macro as well. Inner attributes anywhere else and
+/// attributes at the end of the body that aren't followed by a field are
+/// compile errors:
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Sensor {
+///         value: f32,
+///         #![derive(Debug)]
+///         offset: f32,
+///     }
+/// );
+/// ```
+///
+/// Only types declared with this macro can be flattened, they are found
+/// through their inspection macro. Flattening any other type fails with an
+/// error that no macro named like the type can be found, the compiler notes
+/// that the type is in scope but not a macro:
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// struct Sensor {
+///     value: f32,
+/// }
+///
+/// flatten_structs!(
+///     struct Sensors {
+///         #[flatten]
+///         sensor: Sensor,
+///     }
+/// );
+/// ```
+///
+/// With `#[flatten(strict)]` the type is reported as well, with an error that
+/// it isn't declared with `flatten_structs!`:
+///
+/// ```compile_fail,E0277
+/// use flatten_structs::flatten_structs;
+///
+/// struct Sensor {
+///     value: f32,
+/// }
+///
+/// flatten_structs!(
+///     #[flatten(strict)]
+///     struct Sensors {
+///         #[flatten]
+///         sensor: Sensor,
+///     }
+/// );
+/// ```
+///
+/// Collections like `Vec` or `HashMap`, arrays and slices can't be inlined,
+/// flattening them is a compile error saying so, while `Option` and `Box`
+/// are supported as described above. This fails with "`#[flatten]` field
+/// `sensors` is a `Vec`, collections can't be flattened, ...":
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Sensors {
+///         #[flatten]
+///         sensors: Vec<Sensor>,
+///     }
+///
+///     struct Sensor {
+///         value: f32,
+///     }
+/// );
+/// ```
+///
+/// Collections are recognized by the last segment of their path, this fails
+/// with "`#[flatten]` field `sensors` is a `HashMap`, ...":
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Sensors {
+///         #[flatten]
+///         sensors: std::collections::HashMap<u32, Sensor>,
+///     }
+///
+///     struct Sensor {
+///         value: f32,
+///     }
+/// );
+/// ```
+///
+/// And this with "`#[flatten]` field `sensors` is an array or slice, ...":
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Sensors {
+///         #[flatten]
+///         sensors: [Sensor; 2],
+///     }
+///
+///     struct Sensor {
+///         value: f32,
+///     }
+/// );
+/// ```
+///
+/// Associated types need `via` to be flattened, without it this fails with
+/// "`#[flatten]` field `group` has a qualified path as its type, ...":
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// trait Schema {
+///     type Group;
+/// }
+///
+/// flatten_structs!(
+///     struct Reading {
+///         #[flatten]
+///         group: <Self as Schema>::Group,
+///     }
+///
+///     struct Sensor {
+///         value: f32,
+///     }
+/// );
+///
+/// impl Schema for Reading {
+///     type Group = Sensor;
+/// }
+/// ```
+///
+/// Inlining the same field name twice fails a const assertion that names the
+/// duplicated field and the types it is declared with:
+///
+/// ```compile_fail,E0080
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Sensors {
+///         #[flatten]
+///         left: Sensor,
+///         #[flatten]
+///         right: Sensor,
+///     }
+/// );
+///
+/// flatten_structs!(
+///     struct Sensor {
+///         value: f32,
+///     }
+/// );
+/// ```
+///
+/// `on_conflict = "merge"` only merges fields of the same type, this fails
+/// with "field `id` can't be merged, its types `u64` and `String` differ":
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Order {
+///         id: u64,
+///         #[flatten(on_conflict = "merge")]
+///         customer: Customer,
+///     }
+/// );
+///
+/// flatten_structs!(
+///     struct Customer {
+///         id: String,
+///     }
+/// );
+/// ```
+///
+/// `dedup` only drops fields declared exactly like the earlier field, this
+/// fails with "field `id` can't be deduplicated, it is declared as `u64` and
+/// as `String`":
+///
+/// ```compile_fail
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Order {
+///         id: u64,
+///         #[flatten(dedup)]
+///         customer: Customer,
+///     }
+/// );
+///
+/// flatten_structs!(
+///     struct Customer {
+///         id: String,
+///     }
+/// );
+/// ```
+///
+/// With `assert_unique` the duplicated field is reported by the assertion,
+/// here "field `value` inlined from `right` is declared more than once in
+/// `Sensors`":
+///
+/// ```compile_fail,E0080
+/// use flatten_structs::flatten_structs;
+///
+/// flatten_structs!(
+///     struct Sensors {
+///         #[flatten]
+///         left: Sensor,
+///         #[flatten(assert_unique)]
+///         right: Sensor,
+///     }
+///
+///     struct Sensor {
+///         value: f32,
+///     }
+/// );
+/// ```
 #[macro_export]
 macro_rules! flatten_structs {
+    // Every struct is declared on its own, this is reached once all structs
+    // of the invocation have been declared
+    () => {};
+    // Inner attributes at the start of the body of a group apply to the whole
+    // group like the attributes in front of it
+    (
+        $(#[$($struct_attr:tt)*])*
+        $vis:vis
+        group
+        $name:ident { #![$($inner_attr:tt)*] $($body:tt)* }
+        $($structs:tt)*
+    ) => {
+        $crate::flatten_structs!{
+            $(#[$($struct_attr)*])*
+            #[$($inner_attr)*]
+            $vis
+            group
+            $name { $($body)* }
+            $($structs)*
+        }
+    };
+    // Entry point for groups, these only declare the inspection macro so
+    // their fields can be flattened into other structs:
+    (
+        $(#[$($struct_attr:tt)*])*
+        $vis:vis
+        group
+        $name:ident { $($body:tt)* }
+        $($structs:tt)*
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $(#[$($struct_attr)*])* },
+            seen_attrs = {},
+            config = {
+                export = { pub(crate) },
+                export_attrs = {},
+                macro_name = { $name },
+                serde_rename_all = {},
+                derives = {},
+                copies = {},
+                module = {},
+                kind = { group },
+                inspect = { inspect },
+                order = {},
+                strict = {},
+                postprocess = {},
+            },
+            item = {
+                vis = { $vis },
+                name = $name,
+                generics = {},
+                params = {},
+                impl_generics = {},
+                where_clause = {},
+            },
+            queued_fields = { $($body)* },
+        }
+        $crate::flatten_structs!{$($structs)*}
+    };
     // Entry point:
     (
-        $(#[$struct_attr:meta])*
+        $(#[$($struct_attr:tt)*])*
         $vis:vis
         struct
-        $name:ident {$(
-            $(#[$($field_attr:tt)*])*
-            $field_vis:vis $field_name:ident: $field_type:path
-        ),* $(,)? }
+        $name:ident { $($body:tt)* }
+        $($structs:tt)*
     ) => {
-        // Start recursive macro calls:
-        $crate::flatten_structs!{@gather_fields
-            expanded_fields = {},
-            queued_fields = { $({
-                $(#[$($field_attr)*])*
-                $field_vis $field_name: $field_type
-            })* },
-            cx = {
-                definition = {
-                    $(#[$struct_attr])*
-                    $vis
-                    struct
-                    $name
+        $crate::flatten_structs!{@struct_body
+            attrs = { $(#[$($struct_attr)*])* },
+            item = {
+                vis = { $vis },
+                name = $name,
+                generics = {},
+                params = {},
+                impl_generics = {},
+            },
+            where_clause = {},
+            body = { $($body)* },
+        }
+        $crate::flatten_structs!{$($structs)*}
+    };
+    // Entry point for enums, the fields of every struct-like variant are
+    // gathered like the fields of a struct one variant after another:
+    (
+        $(#[$($enum_attr:tt)*])*
+        $vis:vis
+        enum
+        $name:ident { $($variants:tt)* }
+        $($structs:tt)*
+    ) => {
+        $crate::flatten_structs!{@enum_attrs
+            attrs = { $(#[$($enum_attr)*])* },
+            name = $name,
+        }
+        $crate::flatten_structs!{@enum_variants
+            attrs = { $(#[$($enum_attr)*])* },
+            vis = { $vis },
+            name = $name,
+            variants = {},
+            queued_variants = { $($variants)* },
+        }
+        $crate::flatten_structs!{$($structs)*}
+    };
+    // Entry point for tuple structs, the fields are kept in parentheses to
+    // gather them by position later:
+    (
+        $(#[$($struct_attr:tt)*])*
+        $vis:vis
+        struct
+        $name:ident ( $($body:tt)* );
+        $($structs:tt)*
+    ) => {
+        $crate::flatten_structs!{@struct_body
+            attrs = { $(#[$($struct_attr)*])* },
+            item = {
+                vis = { $vis },
+                name = $name,
+                generics = {},
+                params = {},
+                impl_generics = {},
+            },
+            where_clause = {},
+            body = { ( $($body)* ) },
+        }
+        $crate::flatten_structs!{$($structs)*}
+    };
+    (
+        $(#[$($struct_attr:tt)*])*
+        $vis:vis
+        struct
+        $name:ident where $($rest:tt)*
+    ) => {
+        $crate::flatten_structs!{@where_clause
+            where_clause = { where },
+            rest = { $($rest)* },
+            then = {
+                @struct_body
+                attrs = { $(#[$($struct_attr)*])* },
+                item = {
+                    vis = { $vis },
+                    name = $name,
+                    generics = {},
+                    params = {},
+                    impl_generics = {},
                 },
-                dollar = { $ },
             },
         }
     };
-    // Handle Queued field (with flatten attribute)
-    (@gather_fields
-        expanded_fields = $expanded_fields:tt,
-        queued_fields = { {
-            $(#[doc = $($field_docs:tt)*])*
-            #[flatten]
-            $(#[$($field_attr:tt)*])*
-            $field_vis:vis $field_name:ident: $field_type:path
-        } $($queued_fields:tt)* },
-        cx = $cx:tt,
+    // Entry point for generic structs, the generic parameters are collected
+    // up to the matching `>` first:
+    (
+        $(#[$($struct_attr:tt)*])*
+        $vis:vis
+        struct
+        $name:ident < $($rest:tt)*
     ) => {
-        $field_type!{
-            call = { $crate::flatten_structs },
-            prefix = { @callback },
-            cx = {
-                flattened_vis = { $field_vis },
-                expanded_fields = $expanded_fields,
-                queued_fields = { $($queued_fields)* },
-                cx = $cx,
+        $crate::flatten_structs!{@angle_brackets
+            depth = {},
+            inner = {},
+            rest = { $($rest)* },
+            then = {
+                @generics
+                attrs = { $(#[$($struct_attr)*])* },
+                vis = { $vis },
+                name = $name,
             },
         }
     };
-    // Callback from "inspection" macro when flattening type
-    (@callback
-        fields = {$(
-            $(#[$($field_attr:tt)*])*
-            $field_vis:vis $field_name:ident: $field_type:path,
-        )*},
-        cx = {
-            flattened_vis = { $flattened_vis:vis },
-            expanded_fields = { $($expanded_fields:tt)* },
-            queued_fields = $queued_fields:tt,
-            cx = $cx:tt,
-        },
+    (@generics
+        attrs = $attrs:tt,
+        vis = $vis:tt,
+        name = $name:ident,
+        inner = $generics:tt,
+        rest = $rest:tt,
     ) => {
-        $crate::flatten_structs!{@gather_fields
-            expanded_fields = { $($expanded_fields)* $({
-                $(#[$($field_attr)*])*
-                $flattened_vis $field_name: $field_type
-            })*},
-            queued_fields = $queued_fields,
-            cx = $cx,
+        $crate::flatten_structs!{@generic_params
+            expect = { param },
+            depth = {},
+            params = {},
+            impl_generics = {},
+            rest = $generics,
+            then = {
+                @generics_done
+                attrs = $attrs,
+                vis = $vis,
+                name = $name,
+                generics = $generics,
+                rest = $rest,
+            },
         }
     };
-    // Handle Queued field (without flatten attribute)
-    (@gather_fields
-        expanded_fields = { $($expanded_fields:tt)* },
-        queued_fields = { {
-            $(#[$($field_attr:tt)*])*
-            $field_vis:vis $field_name:ident: $field_type:path
-        } $($queued_fields:tt)* },
-        cx = $cx:tt,
+    (@generics_done
+        attrs = $attrs:tt,
+        vis = $vis:tt,
+        name = $name:ident,
+        generics = $generics:tt,
+        rest = { where $($rest:tt)* },
+        params = $params:tt,
+        impl_generics = $impl_generics:tt,
     ) => {
-        $crate::flatten_structs!{@gather_fields
-            expanded_fields = { $($expanded_fields)* {
-                $(#[$($field_attr)*])*
-                $field_vis $field_name: $field_type
-            }},
-            queued_fields = { $($queued_fields)* },
-            cx = $cx,
+        $crate::flatten_structs!{@where_clause
+            where_clause = { where },
+            rest = { $($rest)* },
+            then = {
+                @struct_body
+                attrs = $attrs,
+                item = {
+                    vis = $vis,
+                    name = $name,
+                    generics = $generics,
+                    params = $params,
+                    impl_generics = $impl_generics,
+                },
+            },
         }
     };
-    // Done, have gathered info about all fields:
-    (@gather_fields
-        expanded_fields = { $({
-            $(#[$field_attr:meta])*
-            $field_vis:vis $field_name:ident: $field_type:path
-        })* },
-        queued_fields = {},
-        cx = {
-            definition = {
-                $(#[$struct_attr:meta])*
-                $vis:vis
-                struct
-                $name:ident
+    (@generics_done
+        attrs = $attrs:tt,
+        vis = $vis:tt,
+        name = $name:ident,
+        generics = $generics:tt,
+        rest = { $body:tt $($structs:tt)* },
+        params = $params:tt,
+        impl_generics = $impl_generics:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_body
+            attrs = $attrs,
+            item = {
+                vis = $vis,
+                name = $name,
+                generics = $generics,
+                params = $params,
+                impl_generics = $impl_generics,
             },
-            dollar = { $dollar:tt },
-        },
+            where_clause = {},
+            body = $body,
+        }
+        $crate::flatten_structs!{$($structs)*}
+    };
+    // Collect the where clause up to the body of the struct, which is the
+    // last token or followed by the next struct, enum or group
+    (@where_clause
+        where_clause = $where_clause:tt,
+        rest = { { $($body:tt)* } },
+        then = { $($then:tt)* },
     ) => {
-        $(#[$struct_attr])*
-        $vis struct $name {$(
-            $(#[$field_attr])*
-            $field_vis $field_name: $field_type,
-        )*}
-        $crate::__private_codegen_paste!{
-            // Unique name for this macro:
-            // This macro allows another macro to query this types fields.
-            macro_rules! [<__private_field_inspect_for $name>] {
-                (
-                    call = { $dollarcall:path },
-                    prefix = { $dollar($dollarprefix:tt)* },
-                    cx = $dollarcx:tt,
-                ) => {
-                    $dollarcall! {$dollar($dollarprefix)*
-                        fields = {$(
-                            $(#[$field_attr])*
-                            $field_vis $field_name: $field_type,
-                        )*},
-                        cx = $dollarcx,
-                    }
-                };
-            }
-            // But expose the macro with the same name as the generated
-            // type. This works because types, macros and values all
-            // have different namespaces so they don't conflict.
-            #[allow(unused_imports)]
-            pub(crate) use [<__private_field_inspect_for $name>] as $name;
+        $crate::flatten_structs!{$($then)*
+            where_clause = $where_clause,
+            body = { $($body)* },
         }
     };
-}
-
-#[cfg(test)]
-mod test {
-    use serde::{Deserialize, Serialize};
-
-    #[test]
-    fn flatten_structs() {
-        flatten_structs!(
-            #[allow(unused)]
-            pub struct BaseStruct {
-                enable: bool,
-                #[flatten]
-                n1: NestedStruct1,
-                #[flatten]
-                n2: NestedStruct2,
-            }
-        );
-
-        flatten_structs!(
-            #[allow(unused)]
-            struct NestedStruct1 {
-                value: f32,
-            }
-        );
-
-        flatten_structs!(
-            #[allow(unused)]
-            struct NestedStruct2 {
+    (@where_clause
+        where_clause = $where_clause:tt,
+        rest = { { $($body:tt)* } # $($structs:tt)* },
+        then = { $($then:tt)* },
+    ) => {
+        $crate::flatten_structs!{$($then)*
+            where_clause = $where_clause,
+            body = { $($body)* },
+        }
+        $crate::flatten_structs!{# $($structs)*}
+    };
+    (@where_clause
+        where_clause = $where_clause:tt,
+        rest = { { $($body:tt)* } pub $($structs:tt)* },
+        then = { $($then:tt)* },
+    ) => {
+        $crate::flatten_structs!{$($then)*
+            where_clause = $where_clause,
+            body = { $($body)* },
+        }
+        $crate::flatten_structs!{pub $($structs)*}
+    };
+    (@where_clause
+        where_clause = $where_clause:tt,
+        rest = { { $($body:tt)* } struct $($structs:tt)* },
+        then = { $($then:tt)* },
+    ) => {
+        $crate::flatten_structs!{$($then)*
+            where_clause = $where_clause,
+            body = { $($body)* },
+        }
+        $crate::flatten_structs!{struct $($structs)*}
+    };
+    (@where_clause
+        where_clause = $where_clause:tt,
+        rest = { { $($body:tt)* } enum $($structs:tt)* },
+        then = { $($then:tt)* },
+    ) => {
+        $crate::flatten_structs!{$($then)*
+            where_clause = $where_clause,
+            body = { $($body)* },
+        }
+        $crate::flatten_structs!{enum $($structs)*}
+    };
+    (@where_clause
+        where_clause = $where_clause:tt,
+        rest = { { $($body:tt)* } group $($structs:tt)* },
+        then = { $($then:tt)* },
+    ) => {
+        $crate::flatten_structs!{$($then)*
+            where_clause = $where_clause,
+            body = { $($body)* },
+        }
+        $crate::flatten_structs!{group $($structs)*}
+    };
+    (@where_clause
+        where_clause = { $($where_clause:tt)* },
+        rest = { $next:tt $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@where_clause
+            where_clause = { $($where_clause)* $next },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    // Collect the tokens up to the matching `>`, then continue with `then`
+    (@angle_brackets
+        depth = {},
+        inner = $inner:tt,
+        rest = { > $($rest:tt)* },
+        then = { $($then:tt)* },
+    ) => {
+        $crate::flatten_structs!{$($then)*
+            inner = $inner,
+            rest = { $($rest)* },
+        }
+    };
+    (@angle_brackets
+        depth = { < },
+        inner = { $($inner:tt)* },
+        rest = { >> $($rest:tt)* },
+        then = { $($then:tt)* },
+    ) => {
+        $crate::flatten_structs!{$($then)*
+            inner = { $($inner)* > },
+            rest = { $($rest)* },
+        }
+    };
+    (@angle_brackets
+        depth = { < < $($depth:tt)* },
+        inner = { $($inner:tt)* },
+        rest = { >> $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@angle_brackets
+            depth = { $($depth)* },
+            inner = { $($inner)* >> },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@angle_brackets
+        depth = { < $($depth:tt)* },
+        inner = { $($inner:tt)* },
+        rest = { > $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@angle_brackets
+            depth = { $($depth)* },
+            inner = { $($inner)* > },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@angle_brackets
+        depth = { $($depth:tt)* },
+        inner = { $($inner:tt)* },
+        rest = { < $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@angle_brackets
+            depth = { < $($depth)* },
+            inner = { $($inner)* < },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@angle_brackets
+        depth = $depth:tt,
+        inner = { $($inner:tt)* },
+        rest = { $next:tt $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@angle_brackets
+            depth = $depth,
+            inner = { $($inner)* $next },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    // Split generic parameters into the parameters without their bounds and
+    // defaults to name the type, e.g. `'a, T: Clone = u8, const N: usize`
+    // becomes `'a, T, N`, and the parameters without defaults for impls.
+    (@generic_params
+        expect = { param },
+        depth = {},
+        params = { $($params:tt)* },
+        impl_generics = { $($impl_generics:tt)* },
+        rest = { const $param:ident $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { bounds },
+            depth = {},
+            params = { $($params)* $param, },
+            impl_generics = { $($impl_generics)* const $param },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@generic_params
+        expect = { param },
+        depth = {},
+        params = { $($params:tt)* },
+        impl_generics = { $($impl_generics:tt)* },
+        rest = { $param:tt $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { bounds },
+            depth = {},
+            params = { $($params)* $param, },
+            impl_generics = { $($impl_generics)* $param },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@generic_params
+        expect = $expect:tt,
+        depth = {},
+        params = $params:tt,
+        impl_generics = $impl_generics:tt,
+        rest = {},
+        then = { $($then:tt)* },
+    ) => {
+        $crate::flatten_structs!{$($then)*
+            params = $params,
+            impl_generics = $impl_generics,
+        }
+    };
+    (@generic_params
+        expect = $expect:tt,
+        depth = {},
+        params = $params:tt,
+        impl_generics = { $($impl_generics:tt)* },
+        rest = { , $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { param },
+            depth = {},
+            params = $params,
+            impl_generics = { $($impl_generics)* , },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@generic_params
+        expect = { bounds },
+        depth = {},
+        params = $params:tt,
+        impl_generics = $impl_generics:tt,
+        rest = { = $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { default },
+            depth = {},
+            params = $params,
+            impl_generics = $impl_generics,
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@generic_params
+        expect = { bounds },
+        depth = { $($depth:tt)* },
+        params = $params:tt,
+        impl_generics = { $($impl_generics:tt)* },
+        rest = { < $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { bounds },
+            depth = { < $($depth)* },
+            params = $params,
+            impl_generics = { $($impl_generics)* < },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@generic_params
+        expect = { bounds },
+        depth = { < < $($depth:tt)* },
+        params = $params:tt,
+        impl_generics = { $($impl_generics:tt)* },
+        rest = { >> $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { bounds },
+            depth = { $($depth)* },
+            params = $params,
+            impl_generics = { $($impl_generics)* >> },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@generic_params
+        expect = { bounds },
+        depth = { < $($depth:tt)* },
+        params = $params:tt,
+        impl_generics = { $($impl_generics:tt)* },
+        rest = { > $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { bounds },
+            depth = { $($depth)* },
+            params = $params,
+            impl_generics = { $($impl_generics)* > },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@generic_params
+        expect = { bounds },
+        depth = $depth:tt,
+        params = $params:tt,
+        impl_generics = { $($impl_generics:tt)* },
+        rest = { $next:tt $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { bounds },
+            depth = $depth,
+            params = $params,
+            impl_generics = { $($impl_generics)* $next },
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@generic_params
+        expect = { default },
+        depth = { $($depth:tt)* },
+        params = $params:tt,
+        impl_generics = $impl_generics:tt,
+        rest = { < $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { default },
+            depth = { < $($depth)* },
+            params = $params,
+            impl_generics = $impl_generics,
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@generic_params
+        expect = { default },
+        depth = { < < $($depth:tt)* },
+        params = $params:tt,
+        impl_generics = $impl_generics:tt,
+        rest = { >> $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { default },
+            depth = { $($depth)* },
+            params = $params,
+            impl_generics = $impl_generics,
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@generic_params
+        expect = { default },
+        depth = { < $($depth:tt)* },
+        params = $params:tt,
+        impl_generics = $impl_generics:tt,
+        rest = { > $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { default },
+            depth = { $($depth)* },
+            params = $params,
+            impl_generics = $impl_generics,
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    (@generic_params
+        expect = { default },
+        depth = $depth:tt,
+        params = $params:tt,
+        impl_generics = $impl_generics:tt,
+        rest = { $_skip:tt $($rest:tt)* },
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@generic_params
+            expect = { default },
+            depth = $depth,
+            params = $params,
+            impl_generics = $impl_generics,
+            rest = { $($rest)* },
+            then = $then,
+        }
+    };
+    // Inner attributes at the start of the body, e.g. `#![derive(Debug)]` or
+    // `//!` doc comments, are moved after the attributes of the struct, so
+    // they are picked out or kept like any other struct attribute
+    (@struct_body
+        attrs = { $($attrs:tt)* },
+        item = $item:tt,
+        where_clause = $where_clause:tt,
+        body = { #![$($inner_attr:tt)*] $($body:tt)* },
+    ) => {
+        $crate::flatten_structs!{@struct_body
+            attrs = { $($attrs)* #[$($inner_attr)*] },
+            item = $item,
+            where_clause = $where_clause,
+            body = { $($body)* },
+        }
+    };
+    (@struct_body
+        attrs = { $($attrs:tt)* },
+        item = $item:tt,
+        where_clause = $where_clause:tt,
+        body = { ( #![$($inner_attr:tt)*] $($body:tt)* ) },
+    ) => {
+        $crate::flatten_structs!{@struct_body
+            attrs = { $($attrs)* #[$($inner_attr)*] },
+            item = $item,
+            where_clause = $where_clause,
+            body = { ( $($body)* ) },
+        }
+    };
+    (@struct_body
+        attrs = $attrs:tt,
+        item = {
+            vis = $vis:tt,
+            name = $name:ident,
+            generics = $generics:tt,
+            params = $params:tt,
+            impl_generics = $impl_generics:tt,
+        },
+        where_clause = $where_clause:tt,
+        body = { $($body:tt)* },
+    ) => {
+        // Pick out the attributes that configure this macro first:
+        $crate::flatten_structs!{@struct_attrs
+            attrs = $attrs,
+            seen_attrs = {},
+            config = {
+                export = { pub(crate) },
+                export_attrs = {},
+                macro_name = { $name },
+                serde_rename_all = {},
+                derives = {},
+                copies = {},
+                module = {},
+                kind = { struct },
+                inspect = { inspect },
+                order = {},
+                strict = {},
+                postprocess = {},
+            },
+            item = {
+                vis = $vis,
+                name = $name,
+                generics = $generics,
+                params = $params,
+                impl_generics = $impl_generics,
+                where_clause = $where_clause,
+            },
+            queued_fields = { $($body)* },
+        }
+    };
+    // `#[flatten_export(pub)]` needs the inspection macro to be exported from
+    // the crate to be usable by other crates
+    (@struct_attrs
+        attrs = { #[flatten_export(pub)] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = {
+            export = $_export:tt,
+            export_attrs = $_export_attrs:tt,
+            macro_name = $macro_name:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            derives = $derives:tt,
+            copies = $copies:tt,
+            module = $module:tt,
+            kind = $kind:tt,
+            inspect = $inspect:tt,
+            order = $order:tt,
+            strict = $strict:tt,
+            postprocess = $postprocess:tt,
+        },
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = {
+                export = { pub },
+                export_attrs = { #[doc(hidden)] #[macro_export] },
+                macro_name = $macro_name,
+                serde_rename_all = $serde_rename_all,
+                derives = $derives,
+                copies = $copies,
+                module = $module,
+                kind = $kind,
+                inspect = $inspect,
+                order = $order,
+                strict = $strict,
+                postprocess = $postprocess,
+            },
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    (@struct_attrs
+        attrs = { #[flatten_export($export:vis)] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = {
+            export = $_export:tt,
+            export_attrs = $_export_attrs:tt,
+            macro_name = $macro_name:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            derives = $derives:tt,
+            copies = $copies:tt,
+            module = $module:tt,
+            kind = $kind:tt,
+            inspect = $inspect:tt,
+            order = $order:tt,
+            strict = $strict:tt,
+            postprocess = $postprocess:tt,
+        },
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = {
+                export = { $export },
+                export_attrs = {},
+                macro_name = $macro_name,
+                serde_rename_all = $serde_rename_all,
+                derives = $derives,
+                copies = $copies,
+                module = $module,
+                kind = $kind,
+                inspect = $inspect,
+                order = $order,
+                strict = $strict,
+                postprocess = $postprocess,
+            },
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    // `#[flatten_macro_name(...)]` replaces the name of the inspection macro
+    (@struct_attrs
+        attrs = { #[flatten_macro_name($macro_name:ident)] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = {
+            export = $export:tt,
+            export_attrs = $export_attrs:tt,
+            macro_name = $_macro_name:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            derives = $derives:tt,
+            copies = $copies:tt,
+            module = $module:tt,
+            kind = $kind:tt,
+            inspect = $inspect:tt,
+            order = $order:tt,
+            strict = $strict:tt,
+            postprocess = $postprocess:tt,
+        },
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = {
+                export = $export,
+                export_attrs = $export_attrs,
+                macro_name = { $macro_name },
+                serde_rename_all = $serde_rename_all,
+                derives = $derives,
+                copies = $copies,
+                module = $module,
+                kind = $kind,
+                inspect = $inspect,
+                order = $order,
+                strict = $strict,
+                postprocess = $postprocess,
+            },
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    // `#[flatten_as(Name, ...)]` declares another struct with the same fields,
+    // the arguments are split into its attributes and its `flatten_derive`s
+    (@struct_attrs
+        attrs = { #[flatten_as($copy_name:ident $(, $($copy_args:tt)*)?)] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = $config:tt,
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { #[flatten_as(@copy
+                name = $copy_name,
+                attrs = {},
+                rename_all = {},
+                derives = {},
+                args = { $($($copy_args)*)? },
+            )] $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = $config,
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    (@struct_attrs
+        attrs = { #[flatten_as(@copy
+            name = $copy_name:ident,
+            attrs = $copy_attrs:tt,
+            rename_all = $copy_rename_all:tt,
+            derives = { $($copy_derives:tt)* },
+            args = {
+                flatten_derive($($derive:ident $(($($derive_args:tt)*))?),* $(,)?)
+                $(, $($copy_args:tt)*)?
+            },
+        )] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = $config:tt,
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { #[flatten_as(@copy
+                name = $copy_name,
+                attrs = $copy_attrs,
+                rename_all = $copy_rename_all,
+                derives = { $($copy_derives)* $($derive $(($($derive_args)*))?)* },
+                args = { $($($copy_args)*)? },
+            )] $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = $config,
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    // The `rename_all` of the copy renames the keys of its `try_from_map`
+    (@struct_attrs
+        attrs = { #[flatten_as(@copy
+            name = $copy_name:ident,
+            attrs = { $($copy_attrs:tt)* },
+            rename_all = $_copy_rename_all:tt,
+            derives = $copy_derives:tt,
+            args = {
+                serde(rename_all = $rename_all:literal $($serde:tt)*)
+                $(, $($copy_args:tt)*)?
+            },
+        )] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = $config:tt,
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { #[flatten_as(@copy
+                name = $copy_name,
+                attrs = { $($copy_attrs)* #[serde(rename_all = $rename_all $($serde)*)] },
+                rename_all = { $rename_all },
+                derives = $copy_derives,
+                args = { $($($copy_args)*)? },
+            )] $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = $config,
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    (@struct_attrs
+        attrs = { #[flatten_as(@copy
+            name = $copy_name:ident,
+            attrs = { $($copy_attrs:tt)* },
+            rename_all = $copy_rename_all:tt,
+            derives = $copy_derives:tt,
+            args = { $copy_attr:meta $(, $($copy_args:tt)*)? },
+        )] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = $config:tt,
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { #[flatten_as(@copy
+                name = $copy_name,
+                attrs = { $($copy_attrs)* #[$copy_attr] },
+                rename_all = $copy_rename_all,
+                derives = $copy_derives,
+                args = { $($($copy_args)*)? },
+            )] $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = $config,
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    (@struct_attrs
+        attrs = { #[flatten_as(@copy
+            name = $copy_name:ident,
+            attrs = $copy_attrs:tt,
+            rename_all = $copy_rename_all:tt,
+            derives = $copy_derives:tt,
+            args = {},
+        )] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = {
+            export = $export:tt,
+            export_attrs = $export_attrs:tt,
+            macro_name = $macro_name:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            derives = $derives:tt,
+            copies = { $($copies:tt)* },
+            module = $module:tt,
+            kind = $kind:tt,
+            inspect = $inspect:tt,
+            order = $order:tt,
+            strict = $strict:tt,
+            postprocess = $postprocess:tt,
+        },
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = {
+                export = $export,
+                export_attrs = $export_attrs,
+                macro_name = $macro_name,
+                serde_rename_all = $serde_rename_all,
+                derives = $derives,
+                copies = { $($copies)* {
+                    name = $copy_name,
+                    attrs = $copy_attrs,
+                    rename_all = $copy_rename_all,
+                    derives = $copy_derives,
+                } },
+                module = $module,
+                kind = $kind,
+                inspect = $inspect,
+                order = $order,
+                strict = $strict,
+                postprocess = $postprocess,
+            },
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    // `#[flatten_mod(...)]` declares the inspection macro in a module
+    (@struct_attrs
+        attrs = { #[flatten_mod($module:ident)] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = {
+            export = $export:tt,
+            export_attrs = $export_attrs:tt,
+            macro_name = $macro_name:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            derives = $derives:tt,
+            copies = $copies:tt,
+            module = $_module:tt,
+            kind = $kind:tt,
+            inspect = $inspect:tt,
+            order = $order:tt,
+            strict = $strict:tt,
+            postprocess = $postprocess:tt,
+        },
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = {
+                export = $export,
+                export_attrs = $export_attrs,
+                macro_name = $macro_name,
+                serde_rename_all = $serde_rename_all,
+                derives = $derives,
+                copies = $copies,
+                module = { $module },
+                kind = $kind,
+                inspect = $inspect,
+                order = $order,
+                strict = $strict,
+                postprocess = $postprocess,
+            },
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    // `#[flatten(no_inspect)]` doesn't declare the inspection macro
+    (@struct_attrs
+        attrs = { #[flatten(no_inspect)] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = {
+            export = $export:tt,
+            export_attrs = $export_attrs:tt,
+            macro_name = $macro_name:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            derives = $derives:tt,
+            copies = $copies:tt,
+            module = $module:tt,
+            kind = $kind:tt,
+            inspect = $_inspect:tt,
+            order = $order:tt,
+            strict = $strict:tt,
+            postprocess = $postprocess:tt,
+        },
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = {
+                export = $export,
+                export_attrs = $export_attrs,
+                macro_name = $macro_name,
+                serde_rename_all = $serde_rename_all,
+                derives = $derives,
+                copies = $copies,
+                module = $module,
+                kind = $kind,
+                inspect = {},
+                order = $order,
+                strict = $strict,
+                postprocess = $postprocess,
+            },
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    // `#[flatten(strict)]` checks that the flattened types are declared with
+    // this macro
+    (@struct_attrs
+        attrs = { #[flatten(strict)] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = {
+            export = $export:tt,
+            export_attrs = $export_attrs:tt,
+            macro_name = $macro_name:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            derives = $derives:tt,
+            copies = $copies:tt,
+            module = $module:tt,
+            kind = $kind:tt,
+            inspect = $inspect:tt,
+            order = $order:tt,
+            strict = $_strict:tt,
+            postprocess = $postprocess:tt,
+        },
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = {
+                export = $export,
+                export_attrs = $export_attrs,
+                macro_name = $macro_name,
+                serde_rename_all = $serde_rename_all,
+                derives = $derives,
+                copies = $copies,
+                module = $module,
+                kind = $kind,
+                inspect = $inspect,
+                order = $order,
+                strict = { strict },
+                postprocess = $postprocess,
+            },
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    // `#[flatten(postprocess = macro_name)]` lets another macro declare the
+    // struct from the gathered fields, groups don't declare a struct
+    (@struct_attrs
+        attrs = { #[flatten(postprocess = $($postprocess:ident)::+)] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = {
+            export = $export:tt,
+            export_attrs = $export_attrs:tt,
+            macro_name = $macro_name:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            derives = $derives:tt,
+            copies = $copies:tt,
+            module = $module:tt,
+            kind = { group },
+            inspect = $inspect:tt,
+            order = $order:tt,
+            strict = $strict:tt,
+            postprocess = $_postprocess:tt,
+        },
+        item = {
+            vis = $vis:tt,
+            name = $name:ident,
+            $($item:tt)*
+        },
+        queued_fields = $queued_fields:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten(postprocess = ...)]` needs a struct with named fields, `",
+            ::core::stringify!($name),
+            "` is a group",
+        ));
+    };
+    (@struct_attrs
+        attrs = { #[flatten(postprocess = $($postprocess:ident)::+)] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = {
+            export = $export:tt,
+            export_attrs = $export_attrs:tt,
+            macro_name = $macro_name:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            derives = $derives:tt,
+            copies = $copies:tt,
+            module = $module:tt,
+            kind = $kind:tt,
+            inspect = $inspect:tt,
+            order = $order:tt,
+            strict = $strict:tt,
+            postprocess = $_postprocess:tt,
+        },
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = {
+                export = $export,
+                export_attrs = $export_attrs,
+                macro_name = $macro_name,
+                serde_rename_all = $serde_rename_all,
+                derives = $derives,
+                copies = $copies,
+                module = $module,
+                kind = $kind,
+                inspect = $inspect,
+                order = $order,
+                strict = $strict,
+                postprocess = { $($postprocess)::+ },
+            },
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    // `#[flatten(order(...))]` sets the order of the fields
+    (@struct_attrs
+        attrs = { #[flatten(order($($order:ident),+ $(,)?))] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = {
+            export = $export:tt,
+            export_attrs = $export_attrs:tt,
+            macro_name = $macro_name:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            derives = $derives:tt,
+            copies = $copies:tt,
+            module = $module:tt,
+            kind = $kind:tt,
+            inspect = $inspect:tt,
+            order = $_order:tt,
+            strict = $strict:tt,
+            postprocess = $postprocess:tt,
+        },
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = {
+                export = $export,
+                export_attrs = $export_attrs,
+                macro_name = $macro_name,
+                serde_rename_all = $serde_rename_all,
+                derives = $derives,
+                copies = $copies,
+                module = $module,
+                kind = $kind,
+                inspect = $inspect,
+                order = { $($order)+ },
+                strict = $strict,
+                postprocess = $postprocess,
+            },
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    // `#[flatten_derive(...)]` generates additional code for the struct
+    (@struct_attrs
+        attrs = { #[flatten_derive($($derive:ident $(($($derive_args:tt)*))?),* $(,)?)] $($attrs:tt)* },
+        seen_attrs = $seen_attrs:tt,
+        config = {
+            export = $export:tt,
+            export_attrs = $export_attrs:tt,
+            macro_name = $macro_name:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            derives = { $($derives:tt)* },
+            copies = $copies:tt,
+            module = $module:tt,
+            kind = $kind:tt,
+            inspect = $inspect:tt,
+            order = $order:tt,
+            strict = $strict:tt,
+            postprocess = $postprocess:tt,
+        },
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = $seen_attrs,
+            config = {
+                export = $export,
+                export_attrs = $export_attrs,
+                macro_name = $macro_name,
+                serde_rename_all = $serde_rename_all,
+                derives = { $($derives)* $($derive $(($($derive_args)*))?)* },
+                copies = $copies,
+                module = $module,
+                kind = $kind,
+                inspect = $inspect,
+                order = $order,
+                strict = $strict,
+                postprocess = $postprocess,
+            },
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    // Remember `#[serde(rename_all = "...")]` so the case of the field names
+    // can be kept when the type is flattened, the attribute itself is kept
+    (@struct_attrs
+        attrs = { #[serde(rename_all = $serde_rename_all:tt $($serde:tt)*)] $($attrs:tt)* },
+        seen_attrs = { $($seen_attrs:tt)* },
+        config = {
+            export = $export:tt,
+            export_attrs = $export_attrs:tt,
+            macro_name = $macro_name:tt,
+            serde_rename_all = $_serde_rename_all:tt,
+            derives = $derives:tt,
+            copies = $copies:tt,
+            module = $module:tt,
+            kind = $kind:tt,
+            inspect = $inspect:tt,
+            order = $order:tt,
+            strict = $strict:tt,
+            postprocess = $postprocess:tt,
+        },
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = { $($seen_attrs)* #[serde(rename_all = $serde_rename_all $($serde)*)] },
+            config = {
+                export = $export,
+                export_attrs = $export_attrs,
+                macro_name = $macro_name,
+                serde_rename_all = { $serde_rename_all },
+                derives = $derives,
+                copies = $copies,
+                module = $module,
+                kind = $kind,
+                inspect = $inspect,
+                order = $order,
+                strict = $strict,
+                postprocess = $postprocess,
+            },
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    // Keep any other attribute for the generated struct
+    (@struct_attrs
+        attrs = { #[$($attr:tt)*] $($attrs:tt)* },
+        seen_attrs = { $($seen_attrs:tt)* },
+        config = $config:tt,
+        item = $item:tt,
+        queued_fields = $queued_fields:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_attrs
+            attrs = { $($attrs)* },
+            seen_attrs = { $($seen_attrs)* #[$($attr)*] },
+            config = $config,
+            item = $item,
+            queued_fields = $queued_fields,
+        }
+    };
+    (@struct_attrs
+        attrs = {},
+        seen_attrs = { $($seen_attrs:tt)* },
+        config = $config:tt,
+        item = { $($item:tt)* },
+        queued_fields = { ( $($queued_fields:tt)* ) },
+    ) => {
+        $crate::flatten_structs!{@gather_tuple_fields
+            expanded_fields = {},
+            field = { attrs = {}, flatten = {} },
+            queued_fields = { $($queued_fields)* },
+            cx = {
+                definition = {
+                    config = $config,
+                    attrs = { $($seen_attrs)* },
+                    $($item)*
+                },
+                dollar = { $ },
+            },
+        }
+    };
+    (@struct_attrs
+        attrs = {},
+        seen_attrs = { $($seen_attrs:tt)* },
+        config = $config:tt,
+        item = { $($item:tt)* },
+        queued_fields = $queued_fields:tt,
+    ) => {
+        // Start recursive macro calls:
+        $crate::flatten_structs!{@gather_fields
+            expanded_fields = {},
+            queued_fields = $queued_fields,
+            cx = {
+                definition = {
+                    config = $config,
+                    attrs = { $($seen_attrs)* },
+                    $($item)*
+                },
+                groups = {},
+                own_fields = {},
+                duplicates = { error },
+                dollar = { $ },
+            },
+        }
+    };
+    // Handle queued fields, the common cases are handled without recursing
+    // for every field or attribute to keep the recursion depth low. Fields
+    // without attributes can't be flattened, keep all of them at once
+    (@gather_fields
+        expanded_fields = { $($expanded_fields:tt)* },
+        queued_fields = {
+            $($field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?),+ $(,)?
+        },
+        cx = {
+            definition = $definition:tt,
+            groups = $groups:tt,
+            own_fields = { $($own_fields:tt)* },
+            duplicates = $duplicates:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@gather_fields
+            expanded_fields = { $($expanded_fields)* $({
+                $field_vis $field_name: $field_type $(= $field_default)?
+            })+ },
+            queued_fields = {},
+            cx = {
+                definition = $definition,
+                groups = $groups,
+                own_fields = { $($own_fields)* $({ $field_name: $field_type })+ },
+                duplicates = $duplicates,
+                dollar = $dollar,
+            },
+        }
+    };
+    (@gather_fields
+        expanded_fields = { $($expanded_fields:tt)* },
+        queued_fields = {
+            $($field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?),+,
+            #[$($next_attr:tt)*] $($queued_fields:tt)*
+        },
+        cx = {
+            definition = $definition:tt,
+            groups = $groups:tt,
+            own_fields = { $($own_fields:tt)* },
+            duplicates = $duplicates:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@gather_fields
+            expanded_fields = { $($expanded_fields)* $({
+                $field_vis $field_name: $field_type $(= $field_default)?
+            })+ },
+            queued_fields = { #[$($next_attr)*] $($queued_fields)* },
+            cx = {
+                definition = $definition,
+                groups = $groups,
+                own_fields = { $($own_fields)* $({ $field_name: $field_type })+ },
+                duplicates = $duplicates,
+                dollar = $dollar,
+            },
+        }
+    };
+    // Plain `#[flatten]` fields whose type is a single identifier have no
+    // options to apply except for maybe a `prefix`, the inspection macro is
+    // called right away. Its callback continues with the next such field
+    // itself, which keeps the recursion depth for structs with many
+    // flattened fields low.
+    (@gather_fields
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = {
+            #[flatten $((prefix = $prefix:literal))?]
+            $field_vis:vis $field_name:ident: $type_name:ident $(, $($queued_fields:tt)*)?
+        },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@strict
+            type = { $type_name },
+            cx = $cx,
+        }
+        $type_name!{
+            call = { $crate::flatten_structs },
+            prefix = { @callback },
+            cx = {
+                flattened_vis = { $field_vis },
+                flattened_type = {
+                    prefix = {},
+                    type_prefix = {},
+                    path = { $type_name },
+                    args = {},
+                },
+                flattened_name = $field_name,
+                flatten_opts = { $(prefix = $prefix)? },
+                expanded_fields = $expanded_fields,
+                queued_fields = { $($($queued_fields)*)? },
+                cx = $cx,
+            },
+        }
+    };
+    // The `#[flatten]` attribute is one of the first two attributes
+    (@gather_fields
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = {
+            #[flatten $(($($flatten_opts:tt)*))?]
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $($queued_fields:tt)*
+        },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_cfgs
+            cfgs = {},
+            attrs = { $(#[$($field_attr)*])* },
+            field = { $field_vis $field_name },
+            flatten_opts = { $($($flatten_opts)*)? },
+            expanded_fields = $expanded_fields,
+            queued_fields = { $($queued_fields)* },
+            cx = $cx,
+        }
+    };
+    (@gather_fields
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = {
+            #[$($first_attr:tt)*]
+            #[flatten $(($($flatten_opts:tt)*))?]
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $($queued_fields:tt)*
+        },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_cfgs
+            cfgs = {},
+            attrs = { #[$($first_attr)*] $(#[$($field_attr)*])* },
+            field = { $field_vis $field_name },
+            flatten_opts = { $($($flatten_opts)*)? },
+            expanded_fields = $expanded_fields,
+            queued_fields = { $($queued_fields)* },
+            cx = $cx,
+        }
+    };
+    // More attributes, look for a `#[flatten]` attribute among the others
+    (@gather_fields
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = {
+            #[$($first_attr:tt)*]
+            #[$($second_attr:tt)*]
+            $(#[$($field_attr:tt)*])+
+            $field_vis:vis $field_name:ident: $($queued_fields:tt)*
+        },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@find_flatten
+            seen_attrs = { #[$($first_attr)*] #[$($second_attr)*] },
+            attrs = { $(#[$($field_attr)*])+ },
+            field = { $field_vis $field_name },
+            expanded_fields = $expanded_fields,
+            queued_fields = { $($queued_fields)* },
+            cx = $cx,
+        }
+    };
+    // No flatten attribute among the attributes, keep the field as is
+    (@gather_fields
+        expanded_fields = { $($expanded_fields:tt)* },
+        queued_fields = {
+            $(#[$($field_attr:tt)*])+
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+            $(, $($queued_fields:tt)*)?
+        },
+        cx = {
+            definition = $definition:tt,
+            groups = $groups:tt,
+            own_fields = { $($own_fields:tt)* },
+            duplicates = $duplicates:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@gather_fields
+            expanded_fields = { $($expanded_fields)* {
+                $(#[$($field_attr)*])+
+                $field_vis $field_name: $field_type $(= $field_default)?
+            }},
+            queued_fields = { $($($queued_fields)*)? },
+            cx = {
+                definition = $definition,
+                groups = $groups,
+                own_fields = { $($own_fields)* { $field_name: $field_type } },
+                duplicates = $duplicates,
+                dollar = $dollar,
+            },
+        }
+    };
+    // Inner attributes after the first field and attributes without a field
+    // would fail to parse with an error pointing somewhere into this macro
+    (@gather_fields
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = {
+            $($field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?),+,
+            #![$($inner_attr:tt)*] $($queued_fields:tt)*
+        },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@gather_fields
+            expanded_fields = $expanded_fields,
+            queued_fields = { #![$($inner_attr)*] },
+            cx = $cx,
+        }
+    };
+    (@gather_fields
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { $(#[$($field_attr:tt)*])* #![$($inner_attr:tt)*] $($queued_fields:tt)* },
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "inner attribute `#![",
+            ::core::stringify!($($inner_attr)*),
+            "]` needs to be at the start of the struct body",
+        ));
+    };
+    (@gather_fields
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { #[$($field_attr:tt)*] $(#[$($attrs:tt)*])* },
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "attribute `#[",
+            ::core::stringify!($($field_attr)*),
+            "]` at the end of the struct body needs to be followed by a field",
+        ));
+    };
+    // Found the flatten attribute, inline the fields of the type instead
+    (@find_flatten
+        seen_attrs = { $($seen_attrs:tt)* },
+        attrs = { #[flatten $(($($flatten_opts:tt)*))?] $($attrs:tt)* },
+        field = $field:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_cfgs
+            cfgs = {},
+            attrs = { $($seen_attrs)* $($attrs)* },
+            field = $field,
+            flatten_opts = { $($($flatten_opts)*)? },
+            expanded_fields = $expanded_fields,
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+    // Skip over any other attribute, keeping it for the field
+    (@find_flatten
+        seen_attrs = { $($seen_attrs:tt)* },
+        attrs = { #[$($attr:tt)*] $($attrs:tt)* },
+        field = $field:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@find_flatten
+            seen_attrs = { $($seen_attrs)* #[$($attr)*] },
+            attrs = { $($attrs)* },
+            field = $field,
+            expanded_fields = $expanded_fields,
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+    // No flatten attribute, keep the field as is
+    (@find_flatten
+        seen_attrs = { $($seen_attrs:tt)* },
+        attrs = {},
+        field = { $field_vis:vis $field_name:ident },
+        expanded_fields = { $($expanded_fields:tt)* },
+        queued_fields = { $field_type:ty $(= $field_default:expr)? $(, $($queued_fields:tt)*)? },
+        cx = {
+            definition = $definition:tt,
+            groups = $groups:tt,
+            own_fields = { $($own_fields:tt)* },
+            duplicates = $duplicates:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@gather_fields
+            expanded_fields = { $($expanded_fields)* {
+                $($seen_attrs)*
+                $field_vis $field_name: $field_type $(= $field_default)?
+            }},
+            queued_fields = { $($($queued_fields)*)? },
+            cx = {
+                definition = $definition,
+                groups = $groups,
+                own_fields = { $($own_fields)* { $field_name: $field_type } },
+                duplicates = $duplicates,
+                dollar = $dollar,
+            },
+        }
+    };
+    // `#[flatten(serde)]` keeps the field as is with all of its attributes,
+    // serde flattens it when (de)serializing instead
+    (@flatten_cfgs
+        cfgs = {},
+        attrs = { $($attrs:tt)* },
+        field = { $field_vis:vis $field_name:ident },
+        flatten_opts = { serde },
+        expanded_fields = { $($expanded_fields:tt)* },
+        queued_fields = { $field_type:ty $(= $field_default:expr)? $(, $($queued_fields:tt)*)? },
+        cx = {
+            definition = $definition:tt,
+            groups = $groups:tt,
+            own_fields = { $($own_fields:tt)* },
+            duplicates = $duplicates:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::__private_serde!{
+            option = serde,
+            then = {
+                @gather_fields
+                expanded_fields = { $($expanded_fields)* {
+                    $($attrs)*
+                    #[serde(flatten)]
+                    $field_vis $field_name: $field_type $(= $field_default)?
+                }},
+                queued_fields = { $($($queued_fields)*)? },
+                cx = {
+                    definition = $definition,
+                    groups = $groups,
+                    own_fields = { $($own_fields)* { $field_name: $field_type } },
+                    duplicates = $duplicates,
+                    dollar = $dollar,
+                },
+            },
+        }
+    };
+    // The `#[cfg(...)]` attributes of a flattened field apply to all of its
+    // inlined fields, its other attributes are dropped
+    (@flatten_cfgs
+        cfgs = { $($cfgs:tt)* },
+        attrs = { #[cfg($($cfg:tt)*)] $($attrs:tt)* },
+        field = $field:tt,
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_cfgs
+            cfgs = { $($cfgs)* $($cfg)*, },
+            attrs = { $($attrs)* },
+            field = $field,
+            flatten_opts = $flatten_opts,
+            expanded_fields = $expanded_fields,
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_cfgs
+        cfgs = $cfgs:tt,
+        attrs = { #[$($attr:tt)*] $($attrs:tt)* },
+        field = $field:tt,
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_cfgs
+            cfgs = $cfgs,
+            attrs = { $($attrs)* },
+            field = $field,
+            flatten_opts = $flatten_opts,
+            expanded_fields = $expanded_fields,
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_cfgs
+        cfgs = {},
+        attrs = {},
+        field = $field:tt,
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_via
+            seen_opts = {},
+            flatten_opts = $flatten_opts,
+            field = $field,
+            expanded_fields = $expanded_fields,
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+    // The field is only flattened if all `#[cfg(...)]` attributes hold,
+    // otherwise gathering continues without it. The flattened type isn't
+    // inspected then and may be disabled by the same `cfg` itself.
+    (@flatten_cfgs
+        cfgs = { $($cfgs:tt)* },
+        attrs = {},
+        field = $field:tt,
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        #[cfg(all($($cfgs)*))]
+        $crate::flatten_structs!{@flatten_via
+            seen_opts = {},
+            flatten_opts = $flatten_opts,
+            field = $field,
+            expanded_fields = $expanded_fields,
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+        #[cfg(not(all($($cfgs)*)))]
+        $crate::flatten_structs!{@flatten_disabled
+            expanded_fields = $expanded_fields,
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_disabled
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { $_field_type:ty $(= $_field_default:expr)? $(, $($queued_fields:tt)*)? },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@gather_fields
+            expanded_fields = $expanded_fields,
+            queued_fields = { $($($queued_fields)*)? },
+            cx = $cx,
+        }
+    };
+    // `#[flatten(via = Type)]` flattens the given type instead of the type of
+    // the field, e.g. for type aliases that don't have an inspection macro
+    (@flatten_via
+        seen_opts = $seen_opts:tt,
+        flatten_opts = { via = $($via_path:ident)::+ < $($flatten_opts:tt)* },
+        field = $field:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@angle_brackets
+            depth = {},
+            inner = {},
+            rest = { $($flatten_opts)* },
+            then = {
+                @flatten_via_args
+                via_path = { $($via_path)::+ },
+                seen_opts = $seen_opts,
+                field = $field,
+                expanded_fields = $expanded_fields,
+                queued_fields = $queued_fields,
+                cx = $cx,
+            },
+        }
+    };
+    (@flatten_via
+        seen_opts = { $($seen_opts:tt)* },
+        flatten_opts = { via = $($via_path:ident)::+ $(, $($flatten_opts:tt)*)? },
+        field = $field:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { $_field_type:ty $(, $($queued_fields:tt)*)? },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_type
+            field = $field,
+            flatten_opts = { $($seen_opts)* $($($flatten_opts)*)? },
+            expanded_fields = $expanded_fields,
+            queued_fields = { $($via_path)::+ $(, $($queued_fields)*)? },
+            cx = $cx,
+        }
+    };
+    // Skip over the other options, a whole option at a time if it has no or
+    // a single token as its value
+    (@flatten_via
+        seen_opts = { $($seen_opts:tt)* },
+        flatten_opts = {
+            $opt:ident $(= $value:tt)? $(($($args:tt)*))? $(, $($flatten_opts:tt)*)?
+        },
+        field = $field:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_via
+            seen_opts = { $($seen_opts)* $opt $(= $value)? $(($($args)*))?, },
+            flatten_opts = { $($($flatten_opts)*)? },
+            field = $field,
+            expanded_fields = $expanded_fields,
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_via
+        seen_opts = { $($seen_opts:tt)* },
+        flatten_opts = { $opt:tt $($flatten_opts:tt)* },
+        field = $field:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_via
+            seen_opts = { $($seen_opts)* $opt },
+            flatten_opts = { $($flatten_opts)* },
+            field = $field,
+            expanded_fields = $expanded_fields,
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_via
+        seen_opts = $seen_opts:tt,
+        flatten_opts = {},
+        field = $field:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_type
+            field = $field,
+            flatten_opts = $seen_opts,
+            expanded_fields = $expanded_fields,
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_via_args
+        via_path = { $($via_path:tt)* },
+        seen_opts = { $($seen_opts:tt)* },
+        field = $field:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { $_field_type:ty $(, $($queued_fields:tt)*)? },
+        cx = $cx:tt,
+        inner = { $($via_args:tt)* },
+        rest = { $(, $($flatten_opts:tt)*)? },
+    ) => {
+        $crate::flatten_structs!{@flatten_type
+            field = $field,
+            flatten_opts = { $($seen_opts)* $($($flatten_opts)*)? },
+            expanded_fields = $expanded_fields,
+            queued_fields = { $($via_path)* < $($via_args)* > $(, $($queued_fields)*)? },
+            cx = $cx,
+        }
+    };
+    // Flattened `Box`es, e.g. `Box<Nested>`, inline the fields of the type in
+    // the `Box` as if it wasn't boxed
+    (@flatten_type
+        field = $field:tt,
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { Box < $($queued_fields:tt)* },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@angle_brackets
+            depth = {},
+            inner = {},
+            rest = { $($queued_fields)* },
+            then = {
+                @flatten_inner_type
+                field = $field,
+                flatten_opts = $flatten_opts,
+                expanded_fields = $expanded_fields,
+                cx = $cx,
+            },
+        }
+    };
+    // Flattened `Option`s, e.g. `Option<Nested>`, inline the fields of the
+    // type in the `Option` and wrap them in an `Option` each
+    (@flatten_type
+        field = $field:tt,
+        flatten_opts = { $($flatten_opts:tt)* },
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { Option < $($queued_fields:tt)* },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@angle_brackets
+            depth = {},
+            inner = {},
+            rest = { $($queued_fields)* },
+            then = {
+                @flatten_inner_type
+                field = $field,
+                flatten_opts = { @optional, $($flatten_opts)* },
+                expanded_fields = $expanded_fields,
+                cx = $cx,
+            },
+        }
+    };
+    // Flattened generic types, e.g. `Nested<u32>`, the generic arguments are
+    // split off to call the inspection macro of `Nested`. Declarative macros
+    // can't check whether a macro exists, types that weren't declared with
+    // this macro are reported by the compiler as a missing macro named like the
+    // type, pointing at the type of the field.
+    (@flatten_type
+        field = $field:tt,
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { $($type_path:ident)::+ < $($queued_fields:tt)* },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_collection
+            path = { $($type_path)::+ },
+            field = $field,
+            then = {
+                type_path = { $($type_path)::+ },
+                field = $field,
+                flatten_opts = $flatten_opts,
+                expanded_fields = $expanded_fields,
+                queued_fields = { $($queued_fields)* },
+                cx = $cx,
+            },
+        }
+    };
+    (@flatten_generic
+        type_path = { $($type_path:ident)::+ },
+        field = $field:tt,
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { $($queued_fields:tt)* },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@angle_brackets
+            depth = {},
+            inner = {},
+            rest = { $($queued_fields)* },
+            then = {
+                @flatten_generic_type
+                type_path = { $($type_path)::+ },
+                field = $field,
+                flatten_opts = $flatten_opts,
+                expanded_fields = $expanded_fields,
+                cx = $cx,
+            },
+        }
+    };
+    (@flatten_type
+        field = { $field_vis:vis $field_name:ident },
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { $($type_path:ident)::+ $(, $($queued_fields:tt)*)? },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@strict
+            type = { $($type_path)::+ },
+            cx = $cx,
+        }
+        $($type_path)::+!{
+            call = { $crate::flatten_structs },
+            prefix = { @callback },
+            cx = {
+                flattened_vis = { $field_vis },
+                flattened_type = {
+                    prefix = {},
+                    type_prefix = {},
+                    path = { $($type_path)::+ },
+                    args = {},
+                },
+                flattened_name = $field_name,
+                flatten_opts = $flatten_opts,
+                expanded_fields = $expanded_fields,
+                queued_fields = { $($($queued_fields)*)? },
+                cx = $cx,
+            },
+        }
+    };
+    (@flatten_type
+        field = { $field_vis:vis $field_name:ident },
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { [$($array:tt)*] $($queued_fields:tt)* },
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten]` field `",
+            ::core::stringify!($field_name),
+            "` is an array or slice, these can't be flattened, only a type declared ",
+            "with `flatten_structs!` can be, optionally in an `Option` or `Box`",
+        ));
+    };
+    // Qualified paths, e.g. `<Self as Schema>::Group`, have no inspection
+    // macro to call
+    (@flatten_type
+        field = { $field_vis:vis $field_name:ident },
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { < $($queued_fields:tt)* },
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten]` field `",
+            ::core::stringify!($field_name),
+            "` has a qualified path as its type, name the type declared with ",
+            "`flatten_structs!` it resolves to with `#[flatten(via = Type)]`",
+        ));
+    };
+    (@flatten_type
+        field = { $field_vis:vis $field_name:ident },
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = { $($queued_fields:tt)* },
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten]` field `",
+            ::core::stringify!($field_name),
+            "` needs to be a type declared with `flatten_structs!`",
+        ));
+    };
+    (@flatten_generic_type
+        type_path = { $($type_path:tt)* },
+        field = { $field_vis:vis $field_name:ident },
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        cx = $cx:tt,
+        inner = { $($type_args:tt)* },
+        rest = { $(, $($queued_fields:tt)*)? },
+    ) => {
+        $crate::flatten_structs!{@strict
+            type = { $($type_path)* < $($type_args)* > },
+            cx = $cx,
+        }
+        $($type_path)*!{
+            call = { $crate::flatten_structs },
+            prefix = { @callback },
+            cx = {
+                flattened_vis = { $field_vis },
+                flattened_type = {
+                    prefix = {},
+                    type_prefix = {},
+                    path = { $($type_path)* },
+                    args = { $($type_args)* },
+                },
+                flattened_name = $field_name,
+                flatten_opts = $flatten_opts,
+                expanded_fields = $expanded_fields,
+                queued_fields = { $($($queued_fields)*)? },
+                cx = $cx,
+            },
+        }
+    };
+    // With `#[flatten(strict)]` each flattened type is checked to implement
+    // `__PrivateFlattened`, so groups and items of other macros named like
+    // the type are rejected. The check is a function using the generics of the
+    // struct, the flattened type may use its generic parameters.
+    (@strict
+        type = { $($type:tt)* },
+        cx = {
+            definition = {
+                config = {
+                    export = $export:tt,
+                    export_attrs = $export_attrs:tt,
+                    macro_name = $macro_name:tt,
+                    serde_rename_all = $serde_rename_all:tt,
+                    derives = $derives:tt,
+                    copies = $copies:tt,
+                    module = $module:tt,
+                    kind = $kind:tt,
+                    inspect = $inspect:tt,
+                    order = $order:tt,
+                    strict = { strict },
+                    postprocess = $postprocess:tt,
+                },
+                attrs = $attrs:tt,
+                vis = $vis:tt,
+                name = $name:ident,
+                generics = $generics:tt,
+                params = $params:tt,
+                impl_generics = { $($impl_generics:tt)* },
+                where_clause = { $($where_clause:tt)* },
+            },
+            $($cx:tt)*
+        },
+    ) => {
+        const _: () = {
+            #[allow(dead_code, deprecated, clippy::extra_unused_type_parameters)]
+            fn strict<$($impl_generics)*>() $($where_clause)* {
+                $crate::__private_assert_flattened::<$($type)*>();
+            }
+        };
+    };
+    (@strict
+        type = $type:tt,
+        cx = $cx:tt,
+    ) => {};
+    // Collections can't be inlined, they are recognized by the last segment
+    // of their path to report them instead of a missing macro named like them
+    (@flatten_collection
+        path = { $segment:ident :: $($path:tt)+ },
+        field = $field:tt,
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_collection
+            path = { $($path)+ },
+            field = $field,
+            then = $then,
+        }
+    };
+    (@flatten_collection path = { Vec }, field = $field:tt, then = $then:tt,) => {
+        $crate::flatten_structs!{@flatten_collection_error collection = Vec, field = $field}
+    };
+    (@flatten_collection path = { VecDeque }, field = $field:tt, then = $then:tt,) => {
+        $crate::flatten_structs!{@flatten_collection_error collection = VecDeque, field = $field}
+    };
+    (@flatten_collection path = { LinkedList }, field = $field:tt, then = $then:tt,) => {
+        $crate::flatten_structs!{@flatten_collection_error collection = LinkedList, field = $field}
+    };
+    (@flatten_collection path = { BinaryHeap }, field = $field:tt, then = $then:tt,) => {
+        $crate::flatten_structs!{@flatten_collection_error collection = BinaryHeap, field = $field}
+    };
+    (@flatten_collection path = { HashMap }, field = $field:tt, then = $then:tt,) => {
+        $crate::flatten_structs!{@flatten_collection_error collection = HashMap, field = $field}
+    };
+    (@flatten_collection path = { BTreeMap }, field = $field:tt, then = $then:tt,) => {
+        $crate::flatten_structs!{@flatten_collection_error collection = BTreeMap, field = $field}
+    };
+    (@flatten_collection path = { HashSet }, field = $field:tt, then = $then:tt,) => {
+        $crate::flatten_structs!{@flatten_collection_error collection = HashSet, field = $field}
+    };
+    (@flatten_collection path = { BTreeSet }, field = $field:tt, then = $then:tt,) => {
+        $crate::flatten_structs!{@flatten_collection_error collection = BTreeSet, field = $field}
+    };
+    (@flatten_collection
+        path = { $type_name:ident },
+        field = $field:tt,
+        then = { $($then:tt)* },
+    ) => {
+        $crate::flatten_structs!{@flatten_generic $($then)*}
+    };
+    (@flatten_collection_error
+        collection = $collection:ident,
+        field = { $field_vis:vis $field_name:ident }
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten]` field `",
+            ::core::stringify!($field_name),
+            "` is a `",
+            ::core::stringify!($collection),
+            "`, collections can't be flattened, only a type declared with ",
+            "`flatten_structs!` can be, optionally in an `Option` or `Box`",
+        ));
+    };
+    (@flatten_inner_type
+        field = $field:tt,
+        flatten_opts = $flatten_opts:tt,
+        expanded_fields = $expanded_fields:tt,
+        cx = $cx:tt,
+        inner = { $($inner:tt)* },
+        rest = { $($queued_fields:tt)* },
+    ) => {
+        $crate::flatten_structs!{@flatten_type
+            field = $field,
+            flatten_opts = $flatten_opts,
+            expanded_fields = $expanded_fields,
+            queued_fields = { $($inner)* $($queued_fields)* },
+            cx = $cx,
+        }
+    };
+    // Callback from the inspection macro for `inspect!`, the fields are passed
+    // on to the macro of the user without the details used for flattening
+    (@inspect
+        fields = $fields:tt,
+        serde_rename_all = $serde_rename_all:tt,
+        field_types = $field_types:tt,
+        cx = {
+            callback = { $($callback:tt)* },
+            args = { $($args:tt)* },
+        },
+    ) => {
+        $($callback)*! { $($args)* fields = $fields }
+    };
+    (@inspect
+        tuple_fields = $tuple_fields:tt,
+        cx = {
+            callback = { $($callback:tt)* },
+            args = { $($args:tt)* },
+        },
+    ) => {
+        $($callback)*! { $($args)* tuple_fields = $tuple_fields }
+    };
+    (@dump_fields
+        fields = { $(
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?,
+        )* },
+        serde_rename_all = $serde_rename_all:tt,
+        field_types = $field_types:tt,
+        cx = {},
+    ) => {{
+        const FIELDS: &[&[&str]] = &[$(&[
+            $(::core::stringify!(#[$($field_attr)*]),)*
+            ::core::stringify!($field_vis),
+            ::core::concat!(::core::stringify!($field_name), ":"),
+            ::core::stringify!($field_type),
+            $("=", ::core::stringify!($field_default),)?
+        ]),*];
+        $crate::flatten_structs!{@dump FIELDS}
+    }};
+    (@dump_fields
+        tuple_fields = { $(
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_type:ty,
+        )* },
+        cx = {},
+    ) => {{
+        const FIELDS: &[&[&str]] = &[$(&[
+            $(::core::stringify!(#[$($field_attr)*]),)*
+            ::core::stringify!($field_vis),
+            ::core::stringify!($field_type),
+        ]),*];
+        $crate::flatten_structs!{@dump FIELDS}
+    }};
+    (@dump $fields:ident) => {{
+        const DUMP: [u8; $crate::__private_dump_len($fields)] = $crate::__private_dump($fields);
+        const DUMP_STR: &str = match ::core::str::from_utf8(&DUMP) {
+            ::core::result::Result::Ok(dump) => dump,
+            ::core::result::Result::Err(_) => ::core::unreachable!(),
+        };
+        DUMP_STR
+    }};
+    // Newtypes are inlined as their only field named like the flattened
+    // field, which the options can rename like any other inlined field
+    (@callback
+        tuple_fields = {
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_type:ty,
+        },
+        cx = {
+            flattened_vis = $flattened_vis:tt,
+            flattened_type = {
+                prefix = $prefix:tt,
+                type_prefix = $type_prefix:tt,
+                path = { $($path:tt)* },
+                args = $args:tt,
+            },
+            flattened_name = $flattened_name:ident,
+            flatten_opts = { $($flatten_opts:tt)* },
+            $($cx:tt)*
+        },
+    ) => {
+        $crate::flatten_structs!{@newtype_opts
+            opts = { $($flatten_opts)* },
+            name = $flattened_name,
+            then = {
+                @callback
+                fields = {
+                    $(#[$($field_attr)*])*
+                    $field_vis $flattened_name: $field_type,
+                },
+                serde_rename_all = {},
+                field_types = {},
+                cx = {
+                    flattened_vis = $flattened_vis,
+                    flattened_type = { $($path)* },
+                    flattened_name = $flattened_name,
+                    flatten_opts = { $($flatten_opts)* },
+                    $($cx)*
+                },
+            },
+        }
+    };
+    // The options that create the flattened type from its fields by name
+    // can't create newtypes
+    (@newtype_opts
+        opts = { reconstruct $($opts:tt)* },
+        name = $flattened_name:ident,
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@newtype_opts_error option = reconstruct, name = $flattened_name}
+    };
+    (@newtype_opts
+        opts = { into_nested $($opts:tt)* },
+        name = $flattened_name:ident,
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@newtype_opts_error option = into_nested, name = $flattened_name}
+    };
+    (@newtype_opts
+        opts = { serde_accept_nested $($opts:tt)* },
+        name = $flattened_name:ident,
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@newtype_opts_error
+            option = serde_accept_nested,
+            name = $flattened_name
+        }
+    };
+    (@newtype_opts
+        opts = { $_opt:tt $($opts:tt)* },
+        name = $flattened_name:ident,
+        then = $then:tt,
+    ) => {
+        $crate::flatten_structs!{@newtype_opts
+            opts = { $($opts)* },
+            name = $flattened_name,
+            then = $then,
+        }
+    };
+    (@newtype_opts
+        opts = {},
+        name = $flattened_name:ident,
+        then = { $($then:tt)* },
+    ) => {
+        $crate::flatten_structs!{$($then)*}
+    };
+    (@newtype_opts_error option = $option:ident, name = $flattened_name:ident) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten(",
+            ::core::stringify!($option),
+            ")]` can't be used on `",
+            ::core::stringify!($flattened_name),
+            "`, newtypes have no field names to create them from",
+        ));
+    };
+    // Other tuple structs have no field names to inline into a struct with
+    // named fields
+    (@callback
+        tuple_fields = $tuple_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(
+            "only tuple structs with one field can be flattened into structs with named fields"
+        );
+    };
+    // Groups have no type that `#[flatten(strict)]` could check
+    (@callback
+        fields = $fields:tt,
+        serde_rename_all = $serde_rename_all:tt,
+        field_types = {
+            type_name = $type_name:ident,
+            types_trait = $types_trait:ident,
+            type_module = $type_module:tt,
+            kind = { group },
+        },
+        cx = {
+            flattened_vis = $flattened_vis:tt,
+            flattened_type = $flattened_type:tt,
+            flattened_name = $flattened_name:ident,
+            flatten_opts = $flatten_opts:tt,
+            expanded_fields = $expanded_fields:tt,
+            queued_fields = $queued_fields:tt,
+            cx = {
+                definition = {
+                    config = {
+                        export = $export:tt,
+                        export_attrs = $export_attrs:tt,
+                        macro_name = $macro_name:tt,
+                        serde_rename_all = $struct_serde_rename_all:tt,
+                        derives = $derives:tt,
+                        copies = $copies:tt,
+                        module = $module:tt,
+                        kind = $struct_kind:tt,
+                        inspect = $inspect:tt,
+                        order = $order:tt,
+                        strict = { strict },
+                        postprocess = $postprocess:tt,
+                    },
+                    attrs = $attrs:tt,
+                    vis = $vis:tt,
+                    name = $name:ident,
+                    $($definition:tt)*
+                },
+                $($cx:tt)*
+            },
+        },
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten(strict)]` of `",
+            ::core::stringify!($name),
+            "` only allows flattening structs declared with `flatten_structs!`, `",
+            ::core::stringify!($type_name),
+            "` is a group",
+        ));
+    };
+    // Callback for the plain `#[flatten]` fields of `@gather_fields`, the
+    // inlined fields get the visibility of the flattened field and are
+    // appended like with `on_conflict = "error"`. If the next field is
+    // another plain `#[flatten]` or `#[flatten(prefix = "...")]` field its
+    // inspection macro is called right away, otherwise gathering continues.
+    (@callback
+        fields = {$(
+            $(#[$($field_attr:tt)*])*
+            $_field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?,
+        )*},
+        serde_rename_all = $_serde_rename_all:tt,
+        field_types = {
+            type_name = $type_name:ident,
+            types_trait = $_types_trait:ident,
+            type_module = $_type_module:tt,
+            kind = $_kind:tt,
+        },
+        cx = {
+            flattened_vis = { $flattened_vis:vis },
+            flattened_type = {
+                prefix = {},
+                type_prefix = {},
+                path = { $_macro_name:ident },
+                args = {},
+            },
+            flattened_name = $flattened_name:ident,
+            flatten_opts = {},
+            expanded_fields = { $($expanded_fields:tt)* },
+            queued_fields = {
+                #[flatten $((prefix = $next_prefix:literal))?]
+                $next_vis:vis $next_name:ident: $next_type:ident $(, $($queued_fields:tt)*)?
+            },
+            cx = {
+                definition = $definition:tt,
+                groups = { $($groups:tt)* },
+                own_fields = $own_fields:tt,
+                duplicates = $duplicates:tt,
+                dollar = $dollar:tt,
+            },
+        },
+    ) => {
+        $crate::flatten_structs!{@strict
+            type = { $next_type },
+            cx = {
+                definition = $definition,
+            },
+        }
+        $next_type!{
+            call = { $crate::flatten_structs },
+            prefix = { @callback },
+            cx = {
+                flattened_vis = { $next_vis },
+                flattened_type = {
+                    prefix = {},
+                    type_prefix = {},
+                    path = { $next_type },
+                    args = {},
+                },
+                flattened_name = $next_name,
+                flatten_opts = { $(prefix = $next_prefix)? },
+                expanded_fields = { $($expanded_fields)* $({
+                    $(#[$($field_attr)*])*
+                    $flattened_vis $field_name: $field_type $(= $field_default)?
+                })* },
+                queued_fields = { $($($queued_fields)*)? },
+                cx = {
+                    definition = $definition,
+                    groups = { $($groups)* {
+                        generate = {},
+                        flattened = {
+                            attrs = {},
+                            vis = { $flattened_vis },
+                            name = $flattened_name,
+                            type = { $type_name },
+                        },
+                        fields = { $({ $field_name => $field_name: $field_type })* },
+                    } },
+                    own_fields = $own_fields,
+                    duplicates = $duplicates,
+                    dollar = $dollar,
+                },
+            },
+        }
+    };
+    (@callback
+        fields = {$(
+            $(#[$($field_attr:tt)*])*
+            $_field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?,
+        )*},
+        serde_rename_all = $_serde_rename_all:tt,
+        field_types = {
+            type_name = $type_name:ident,
+            types_trait = $_types_trait:ident,
+            type_module = $_type_module:tt,
+            kind = $_kind:tt,
+        },
+        cx = {
+            flattened_vis = { $flattened_vis:vis },
+            flattened_type = {
+                prefix = {},
+                type_prefix = {},
+                path = { $_macro_name:ident },
+                args = {},
+            },
+            flattened_name = $flattened_name:ident,
+            flatten_opts = {},
+            expanded_fields = { $($expanded_fields:tt)* },
+            queued_fields = $queued_fields:tt,
+            cx = {
+                definition = $definition:tt,
+                groups = { $($groups:tt)* },
+                own_fields = $own_fields:tt,
+                duplicates = $duplicates:tt,
+                dollar = $dollar:tt,
+            },
+        },
+    ) => {
+        $crate::flatten_structs!{@gather_fields
+            expanded_fields = { $($expanded_fields)* $({
+                $(#[$($field_attr)*])*
+                $flattened_vis $field_name: $field_type $(= $field_default)?
+            })* },
+            queued_fields = $queued_fields,
+            cx = {
+                definition = $definition,
+                groups = { $($groups)* {
+                    generate = {},
+                    flattened = {
+                        attrs = {},
+                        vis = { $flattened_vis },
+                        name = $flattened_name,
+                        type = { $type_name },
+                    },
+                    fields = { $({ $field_name => $field_name: $field_type })* },
+                } },
+                own_fields = $own_fields,
+                duplicates = $duplicates,
+                dollar = $dollar,
+            },
+        }
+    };
+    // Same for plain `#[flatten(prefix = "...")]` fields, the prefix is pasted
+    // to the names of the inlined fields
+    (@callback
+        fields = {$(
+            $(#[$($field_attr:tt)*])*
+            $_field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?,
+        )*},
+        serde_rename_all = $_serde_rename_all:tt,
+        field_types = {
+            type_name = $type_name:ident,
+            types_trait = $_types_trait:ident,
+            type_module = $_type_module:tt,
+            kind = $_kind:tt,
+        },
+        cx = {
+            flattened_vis = { $flattened_vis:vis },
+            flattened_type = {
+                prefix = {},
+                type_prefix = {},
+                path = { $_macro_name:ident },
+                args = {},
+            },
+            flattened_name = $flattened_name:ident,
+            flatten_opts = { prefix = $prefix:literal },
+            expanded_fields = { $($expanded_fields:tt)* },
+            queued_fields = {
+                #[flatten $((prefix = $next_prefix:literal))?]
+                $next_vis:vis $next_name:ident: $next_type:ident $(, $($queued_fields:tt)*)?
+            },
+            cx = {
+                definition = $definition:tt,
+                groups = { $($groups:tt)* },
+                own_fields = $own_fields:tt,
+                duplicates = $duplicates:tt,
+                dollar = $dollar:tt,
+            },
+        },
+    ) => {
+        $crate::__private_codegen_paste!{
+            $crate::flatten_structs!{@strict
+                type = { $next_type },
+                cx = {
+                    definition = $definition,
+                },
+            }
+            $next_type!{
+                call = { $crate::flatten_structs },
+                prefix = { @callback },
+                cx = {
+                    flattened_vis = { $next_vis },
+                    flattened_type = {
+                        prefix = {},
+                        type_prefix = {},
+                        path = { $next_type },
+                        args = {},
+                    },
+                    flattened_name = $next_name,
+                    flatten_opts = { $(prefix = $next_prefix)? },
+                    expanded_fields = { $($expanded_fields)* $({
+                        $(#[$($field_attr)*])*
+                        $flattened_vis [<$prefix $field_name>]: $field_type $(= $field_default)?
+                    })* },
+                    queued_fields = { $($($queued_fields)*)? },
+                    cx = {
+                        definition = $definition,
+                        groups = { $($groups)* {
+                            generate = {},
+                            flattened = {
+                                attrs = {},
+                                vis = { $flattened_vis },
+                                name = $flattened_name,
+                                type = { $type_name },
+                            },
+                            fields = { $({ $field_name => [<$prefix $field_name>]: $field_type })* },
+                        } },
+                        own_fields = $own_fields,
+                        duplicates = $duplicates,
+                        dollar = $dollar,
+                    },
+                },
+            }
+        }
+    };
+    (@callback
+        fields = {$(
+            $(#[$($field_attr:tt)*])*
+            $_field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?,
+        )*},
+        serde_rename_all = $_serde_rename_all:tt,
+        field_types = {
+            type_name = $type_name:ident,
+            types_trait = $_types_trait:ident,
+            type_module = $_type_module:tt,
+            kind = $_kind:tt,
+        },
+        cx = {
+            flattened_vis = { $flattened_vis:vis },
+            flattened_type = {
+                prefix = {},
+                type_prefix = {},
+                path = { $_macro_name:ident },
+                args = {},
+            },
+            flattened_name = $flattened_name:ident,
+            flatten_opts = { prefix = $prefix:literal },
+            expanded_fields = { $($expanded_fields:tt)* },
+            queued_fields = $queued_fields:tt,
+            cx = {
+                definition = $definition:tt,
+                groups = { $($groups:tt)* },
+                own_fields = $own_fields:tt,
+                duplicates = $duplicates:tt,
+                dollar = $dollar:tt,
+            },
+        },
+    ) => {
+        $crate::__private_codegen_paste!{
+            $crate::flatten_structs!{@gather_fields
+                expanded_fields = { $($expanded_fields)* $({
+                    $(#[$($field_attr)*])*
+                    $flattened_vis [<$prefix $field_name>]: $field_type $(= $field_default)?
+                })* },
+                queued_fields = $queued_fields,
+                cx = {
+                    definition = $definition,
+                    groups = { $($groups)* {
+                        generate = {},
+                        flattened = {
+                            attrs = {},
+                            vis = { $flattened_vis },
+                            name = $flattened_name,
+                            type = { $type_name },
+                        },
+                        fields = { $({ $field_name => [<$prefix $field_name>]: $field_type })* },
+                    } },
+                    own_fields = $own_fields,
+                    duplicates = $duplicates,
+                    dollar = $dollar,
+                },
+            }
+        }
+    };
+    // The inspection macro is found through the path of the flattened type,
+    // split off its module path to name the type and its items next to it.
+    // Types with `#[flatten_mod(...)]` are declared next to the module of
+    // their inspection macro instead.
+    (@callback
+        fields = $fields:tt,
+        serde_rename_all = $serde_rename_all:tt,
+        field_types = {
+            type_name = $type_name:ident,
+            types_trait = $types_trait:ident,
+            type_module = { $type_module:ident },
+            kind = $kind:tt,
+        },
+        cx = {
+            flattened_vis = $flattened_vis:tt,
+            flattened_type = {
+                prefix = { $($prefix:tt)* },
+                type_prefix = $type_prefix:tt,
+                path = { $segment:ident :: $macro_name:ident },
+                args = $args:tt,
+            },
+            $($cx:tt)*
+        },
+    ) => {
+        $crate::flatten_structs!{@callback
+            fields = $fields,
+            serde_rename_all = $serde_rename_all,
+            field_types = {
+                type_name = $type_name,
+                types_trait = $types_trait,
+                type_module = {},
+                kind = $kind,
+            },
+            cx = {
+                flattened_vis = $flattened_vis,
+                flattened_type = {
+                    prefix = { $($prefix)* $segment :: },
+                    type_prefix = $type_prefix,
+                    path = { $macro_name },
+                    args = $args,
+                },
+                $($cx)*
+            },
+        }
+    };
+    (@callback
+        fields = $fields:tt,
+        serde_rename_all = $serde_rename_all:tt,
+        field_types = $field_types:tt,
+        cx = {
+            flattened_vis = $flattened_vis:tt,
+            flattened_type = {
+                prefix = { $($prefix:tt)* },
+                type_prefix = { $($type_prefix:tt)* },
+                path = { $segment:ident :: $($path:tt)+ },
+                args = $args:tt,
+            },
+            $($cx:tt)*
+        },
+    ) => {
+        $crate::flatten_structs!{@callback
+            fields = $fields,
+            serde_rename_all = $serde_rename_all,
+            field_types = $field_types,
+            cx = {
+                flattened_vis = $flattened_vis,
+                flattened_type = {
+                    prefix = { $($prefix)* $segment :: },
+                    type_prefix = { $($type_prefix)* $segment :: },
+                    path = { $($path)+ },
+                    args = $args,
+                },
+                $($cx)*
+            },
+        }
+    };
+    (@callback
+        fields = $fields:tt,
+        serde_rename_all = $serde_rename_all:tt,
+        field_types = {
+            type_name = $type_name:ident,
+            types_trait = $types_trait:ident,
+            type_module = $_type_module:tt,
+            kind = $_kind:tt,
+        },
+        cx = {
+            flattened_vis = $flattened_vis:tt,
+            flattened_type = {
+                prefix = $prefix:tt,
+                type_prefix = { $($type_prefix:tt)* },
+                path = { $_macro_name:ident },
+                args = {},
+            },
+            $($cx:tt)*
+        },
+    ) => {
+        $crate::flatten_structs!{@callback
+            fields = $fields,
+            serde_rename_all = $serde_rename_all,
+            field_types = {},
+            cx = {
+                flattened_vis = $flattened_vis,
+                flattened_type = { $($type_prefix)* $type_name },
+                $($cx)*
+            },
+        }
+    };
+    // The field types of generic types can refer to their generic
+    // parameters, so they are named through the associated types of the
+    // `field_types` trait of the type with its generic arguments instead,
+    // which lets the compiler substitute them.
+    (@callback
+        fields = $fields:tt,
+        serde_rename_all = $serde_rename_all:tt,
+        field_types = {
+            type_name = $type_name:ident,
+            types_trait = $types_trait:ident,
+            type_module = $_type_module:tt,
+            kind = $_kind:tt,
+        },
+        cx = {
+            flattened_vis = $flattened_vis:tt,
+            flattened_type = {
+                prefix = { $($prefix:tt)* },
+                type_prefix = { $($type_prefix:tt)* },
+                path = { $_macro_name:ident },
+                args = { $($args:tt)* },
+            },
+            $($cx:tt)*
+        },
+    ) => {
+        $crate::flatten_structs!{@callback
+            fields = $fields,
+            serde_rename_all = $serde_rename_all,
+            field_types = {
+                flattened_type = { $($type_prefix)* $type_name<$($args)*> },
+                types_trait = { $($prefix)* $types_trait },
+            },
+            cx = {
+                flattened_vis = $flattened_vis,
+                flattened_type = { $($type_prefix)* $type_name::<$($args)*> },
+                $($cx)*
+            },
+        }
+    };
+    (@callback
+        fields = {$(
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?,
+        )*},
+        serde_rename_all = $serde_rename_all:tt,
+        field_types = {
+            flattened_type = { $flattened_type:ty },
+            types_trait = { $types_trait:path },
+        },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@callback
+            fields = {$(
+                $(#[$($field_attr)*])*
+                $field_vis $field_name: <$flattened_type as $types_trait>::$field_name
+                $(= $field_default)?,
+            )*},
+            serde_rename_all = $serde_rename_all,
+            field_types = {},
+            cx = $cx,
+        }
+    };
+    // Callback from "inspection" macro when flattening type
+    (@callback
+        fields = {$(
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?,
+        )*},
+        serde_rename_all = $serde_rename_all:tt,
+        field_types = {},
+        cx = {
+            flattened_vis = { $flattened_vis:vis },
+            flattened_type = { $($flattened_type:tt)* },
+            flattened_name = $flattened_name:ident,
+            flatten_opts = $flatten_opts:tt,
+            expanded_fields = $expanded_fields:tt,
+            queued_fields = $queued_fields:tt,
+            cx = {
+                definition = $definition:tt,
+                groups = $groups:tt,
+                own_fields = $own_fields:tt,
+                duplicates = $duplicates:tt,
+                dollar = $dollar:tt,
+            },
+        },
+    ) => {
+        // Every inlined field remembers the name it was declared with so
+        // options can refer to it after it has been renamed.
+        $crate::flatten_structs!{@flatten_vis
+            scan = $flatten_opts,
+            vis = { $flattened_vis },
+            fields = { $({
+                $field_name =>
+                $(#[$($field_attr)*])*
+                $field_vis $field_name: $field_type $(= $field_default)?
+            })* },
+            opts = $flatten_opts,
+            deferred = {
+                renames = {},
+                on_conflict = { "error" },
+                serde_rename_all = $serde_rename_all,
+                generate = {},
+                flattened = {
+                    attrs = {},
+                    vis = { $flattened_vis },
+                    name = $flattened_name,
+                    type = { $($flattened_type)* },
+                },
+                filter = {},
+            },
+            cx = {
+                dollar = $dollar,
+                expanded_fields = $expanded_fields,
+                queued_fields = $queued_fields,
+                cx = {
+                    definition = $definition,
+                    groups = $groups,
+                    own_fields = $own_fields,
+                    duplicates = $duplicates,
+                    dollar = $dollar,
+                },
+            },
+        }
+    };
+    // Inlined fields get the visibility of the flattened field unless the
+    // options contain `vis = inherit_source`, which keeps the visibility they
+    // are declared with in the flattened type.
+    (@flatten_vis
+        scan = { vis = inherit_source $($scan:tt)* },
+        vis = $vis:tt,
+        fields = $fields:tt,
+        opts = $opts:tt,
+        deferred = $deferred:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = $opts,
+            deferred = $deferred,
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_vis
+        scan = { $_opt:ident $(= $_value:tt)? $(($($_args:tt)*))? $(, $($scan:tt)*)? },
+        vis = $vis:tt,
+        fields = $fields:tt,
+        opts = $opts:tt,
+        deferred = $deferred:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_vis
+            scan = { $($($scan)*)? },
+            vis = $vis,
+            fields = $fields,
+            opts = $opts,
+            deferred = $deferred,
+            cx = $cx,
+        }
+    };
+    (@flatten_vis
+        scan = { $_opt:tt $($scan:tt)* },
+        vis = $vis:tt,
+        fields = $fields:tt,
+        opts = $opts:tt,
+        deferred = $deferred:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_vis
+            scan = { $($scan)* },
+            vis = $vis,
+            fields = $fields,
+            opts = $opts,
+            deferred = $deferred,
+            cx = $cx,
+        }
+    };
+    (@flatten_vis
+        scan = {},
+        vis = { $flattened_vis:vis },
+        fields = { $({
+            $source:ident =>
+            $(#[$($field_attr:tt)*])*
+            $_field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        opts = $opts:tt,
+        deferred = $deferred:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = $opts,
+            deferred = $deferred,
+            fields = { $({
+                $source =>
+                $(#[$($field_attr)*])*
+                $flattened_vis $field_name: $field_type $(= $field_default)?
+            })* },
+            cx = $cx,
+        }
+    };
+    // `#[flatten(cfg(...))]` applies like a `#[cfg(...)]` of the flattened
+    // field, without the fields gathering continues as if it wasn't flattened
+    (@flatten_opts
+        opts = { cfg($($cfg:tt)*) $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = $fields:tt,
+        cx = {
+            dollar = $dollar:tt,
+            expanded_fields = $expanded_fields:tt,
+            queued_fields = $queued_fields:tt,
+            cx = $cx:tt,
+        },
+    ) => {
+        #[cfg($($cfg)*)]
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = $deferred,
+            fields = $fields,
+            cx = {
+                dollar = $dollar,
+                expanded_fields = $expanded_fields,
+                queued_fields = $queued_fields,
+                cx = $cx,
+            },
+        }
+        #[cfg(not($($cfg)*))]
+        $crate::flatten_structs!{@gather_fields
+            expanded_fields = $expanded_fields,
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+    // Wrap every field inlined from an `Option` in an `Option`, their default
+    // values are dropped since the fields are `None` by default
+    (@flatten_opts
+        opts = { @optional $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = $generate:tt,
+            flattened = {
+                attrs = $flattened_attrs:tt,
+                vis = $flattened_vis:tt,
+                name = $flattened_name:ident,
+                type = { $($flattened_type:tt)* },
+            },
+            filter = $filter:tt,
+        },
+        fields = { $({
+            $source:ident =>
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = $on_conflict,
+                serde_rename_all = $serde_rename_all,
+                generate = $generate,
+                flattened = {
+                    attrs = $flattened_attrs,
+                    vis = $flattened_vis,
+                    name = $flattened_name,
+                    type = { ::core::option::Option<$($flattened_type)*> },
+                },
+                filter = $filter,
+            },
+            fields = { $({
+                $source =>
+                $(#[$($field_attr)*])*
+                $field_vis $field_name: ::core::option::Option<$field_type>
+            })* },
+            cx = $cx,
+        }
+    };
+    // Apply `#[flatten(prefix = "...")]` to every inlined field
+    (@flatten_opts
+        opts = { prefix = $prefix:literal $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = { $({
+            $source:ident =>
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        cx = $cx:tt,
+    ) => {
+        $crate::__private_codegen_paste!{
+            $crate::flatten_structs!{@flatten_opts
+                opts = { $($($opts)*)? },
+                deferred = $deferred,
+                fields = { $({
+                    $source =>
+                    $(#[$($field_attr)*])*
+                    $field_vis [<$prefix $field_name>]: $field_type $(= $field_default)?
+                })* },
+                cx = $cx,
+            }
+        }
+    };
+    // Apply `#[flatten(prefix = auto)]` to the inlined fields conflicting with
+    // the fields expanded so far
+    (@flatten_opts
+        opts = { prefix = auto $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = $generate:tt,
+            flattened = {
+                attrs = $flattened_attrs:tt,
+                vis = $flattened_vis:tt,
+                name = $flattened_name:ident,
+                type = $flattened_type:tt,
+            },
+            filter = $filter:tt,
+        },
+        fields = $fields:tt,
+        cx = {
+            dollar = { $dollar:tt },
+            expanded_fields = { $({
+                $(#[$($expanded_attr:tt)*])*
+                $expanded_vis:vis $expanded_name:ident: $expanded_type:ty $(= $expanded_default:expr)?
+            })* },
+            queued_fields = $queued_fields:tt,
+            cx = $cx:tt,
+        },
+    ) => {
+        macro_rules! __flatten_structs_auto_prefix {
+            $((
+                prefixed = { $dollar($dollar prefixed:tt)* },
+                fields = { {
+                    $dollar source:ident =>
+                    $dollar(#[$dollar($dollar field_attr:tt)*])*
+                    $dollar field_vis:vis $expanded_name: $dollar field_type:ty $dollar(= $dollar field_default:expr)?
+                } $dollar($dollar fields:tt)* },
+                then = $dollar then:tt,
+            ) => {
+                $crate::__private_codegen_paste!{
+                    __flatten_structs_auto_prefix!{
+                        prefixed = { $dollar($dollar prefixed)* {
+                            $dollar source =>
+                            $dollar(#[$dollar($dollar field_attr)*])*
+                            $dollar field_vis [<$flattened_name _ $expanded_name>]: $dollar field_type $dollar(= $dollar field_default)?
+                        } },
+                        fields = { $dollar($dollar fields)* },
+                        then = $dollar then,
+                    }
+                }
+            };)*
+            (
+                prefixed = { $dollar($dollar prefixed:tt)* },
+                fields = { $dollar field:tt $dollar($dollar fields:tt)* },
+                then = $dollar then:tt,
+            ) => {
+                __flatten_structs_auto_prefix!{
+                    prefixed = { $dollar($dollar prefixed)* $dollar field },
+                    fields = { $dollar($dollar fields)* },
+                    then = $dollar then,
+                }
+            };
+            (
+                prefixed = $dollar prefixed:tt,
+                fields = {},
+                then = {
+                    opts = $dollar opts:tt,
+                    deferred = $dollar deferred:tt,
+                    cx = $dollar cx:tt,
+                },
+            ) => {
+                $crate::flatten_structs!{@flatten_opts
+                    opts = $dollar opts,
+                    deferred = $dollar deferred,
+                    fields = $dollar prefixed,
+                    cx = $dollar cx,
+                }
+            };
+        }
+        __flatten_structs_auto_prefix!{
+            prefixed = {},
+            fields = $fields,
+            then = {
+                opts = { $($($opts)*)? },
+                deferred = {
+                    renames = $renames,
+                    on_conflict = $on_conflict,
+                    serde_rename_all = $serde_rename_all,
+                    generate = $generate,
+                    flattened = {
+                        attrs = $flattened_attrs,
+                        vis = $flattened_vis,
+                        name = $flattened_name,
+                        type = $flattened_type,
+                    },
+                    filter = $filter,
+                },
+                cx = {
+                    dollar = { $dollar },
+                    expanded_fields = { $({
+                        $(#[$($expanded_attr)*])*
+                        $expanded_vis $expanded_name: $expanded_type $(= $expanded_default)?
+                    })* },
+                    queued_fields = $queued_fields,
+                    cx = $cx,
+                },
+            },
+        }
+    };
+    // Apply `#[flatten(suffix = "...")]` to every inlined field
+    (@flatten_opts
+        opts = { suffix = $suffix:literal $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = { $({
+            $source:ident =>
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        cx = $cx:tt,
+    ) => {
+        $crate::__private_codegen_paste!{
+            $crate::flatten_structs!{@flatten_opts
+                opts = { $($($opts)*)? },
+                deferred = $deferred,
+                fields = { $({
+                    $source =>
+                    $(#[$($field_attr)*])*
+                    $field_vis [<$field_name $suffix>]: $field_type $(= $field_default)?
+                })* },
+                cx = $cx,
+            }
+        }
+    };
+    // `#[flatten(vis = ...)]` chose the visibility the inlined fields start
+    // with before the callback applied the options
+    (@flatten_opts
+        opts = { vis = $policy:ident $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_vis_policy $policy}
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = $deferred,
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    // Apply `#[flatten(vis(...))]` to every inlined field, `vis()` makes them
+    // private
+    (@flatten_opts
+        opts = { vis() $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = { $({
+            $source:ident =>
+            $(#[$($field_attr:tt)*])*
+            $_field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = $deferred,
+            fields = { $({
+                $source =>
+                $(#[$($field_attr)*])*
+                $field_name: $field_type $(= $field_default)?
+            })* },
+            cx = $cx,
+        }
+    };
+    (@flatten_opts
+        opts = { vis($field_vis:vis) $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = { $({
+            $source:ident =>
+            $(#[$($field_attr:tt)*])*
+            $_field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = $deferred,
+            fields = { $({
+                $source =>
+                $(#[$($field_attr)*])*
+                $field_vis $field_name: $field_type $(= $field_default)?
+            })* },
+            cx = $cx,
+        }
+    };
+    // Apply `#[flatten(field_attr(...))]` to every inlined field, the
+    // attributes are combined into an always enabled `cfg_attr`, so they can
+    // be added to each field in one go
+    (@flatten_opts
+        opts = { field_attr($($field_attrs:tt)+) $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { @field_attrs (all(), $($field_attrs)+) $(, $($opts)*)? },
+            deferred = $deferred,
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_opts
+        opts = { @field_attrs $field_attrs:tt $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = { $({ $source:ident => $($field:tt)* })* },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = $deferred,
+            fields = { $({
+                $source =>
+                #[cfg_attr $field_attrs]
+                $($field)*
+            })* },
+            cx = $cx,
+        }
+    };
+    // `#[flatten(serde_default)]` adds `#[serde(default)]` to every inlined
+    // field like `field_attr(...)`
+    (@flatten_opts
+        opts = { serde_default $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::__private_serde!{
+            option = serde_default,
+            then = {
+                @flatten_opts
+                opts = { @field_attrs (all(), serde(default)) $(, $($opts)*)? },
+                deferred = $deferred,
+                fields = $fields,
+                cx = $cx,
+            },
+        }
+    };
+    // The fields inlined from an `Option` are each optional, a nested `None`
+    // can't be told apart from missing fields
+    (@flatten_opts
+        opts = { serde_accept_nested $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = $generate:tt,
+            flattened = {
+                attrs = $flattened_attrs:tt,
+                vis = $flattened_vis:tt,
+                name = $flattened_name:ident,
+                type = { ::core::option::Option $($flattened_type:tt)* },
+            },
+            filter = $filter:tt,
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten(serde_accept_nested)]` can't be used on `",
+            ::core::stringify!($flattened_name),
+            "`, the fields of a flattened `Option` can only be deserialized flat",
+        ));
+    };
+    // Collect `#[flatten(serde_accept_nested)]`, the `Deserialize` impl is
+    // generated along with the struct for all such fields together. It is
+    // kept in front of the other options so `@accept_nested` finds it first.
+    (@flatten_opts
+        opts = { serde_accept_nested $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = { $($generate:ident)* },
+            flattened = $flattened:tt,
+            filter = $filter:tt,
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::__private_serde!{
+            option = serde_accept_nested,
+            then = {
+                @flatten_opts
+                opts = { $($($opts)*)? },
+                deferred = {
+                    renames = $renames,
+                    on_conflict = $on_conflict,
+                    serde_rename_all = $serde_rename_all,
+                    generate = { serde_accept_nested $($generate)* },
+                    flattened = $flattened,
+                    filter = $filter,
+                },
+                fields = $fields,
+                cx = $cx,
+            },
+        }
+    };
+    // Apply `#[flatten(doc_prefix = "...")]` to every inlined field
+    (@flatten_opts
+        opts = { doc_prefix = $doc_prefix:literal $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = { $({ $source:ident => $($field:tt)* })* },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = $deferred,
+            fields = { $({
+                $source =>
+                #[doc = $doc_prefix]
+                $($field)*
+            })* },
+            cx = $cx,
+        }
+    };
+    // Apply `#[flatten(serde_keep_case)]`, every inlined field is serialized
+    // with the same name as in the flattened type
+    (@flatten_opts
+        opts = { serde_keep_case $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            $($deferred:tt)*
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::__private_serde!{
+            option = serde_keep_case,
+            then = {
+                @serde_keep_case
+                key = { source },
+                rename_all = $serde_rename_all,
+                fields = $fields,
+                then = {
+                    opts = { $($($opts)*)? },
+                    deferred = {
+                        renames = $renames,
+                        on_conflict = $on_conflict,
+                        serde_rename_all = $serde_rename_all,
+                        $($deferred)*
+                    },
+                },
+                cx = $cx,
+            },
+        }
+    };
+    // Apply `#[flatten(rename_all = "...")]`, every inlined field is
+    // serialized under its name in the given case
+    (@flatten_opts
+        opts = { rename_all = $rename_all:tt $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::__private_serde!{
+            option = rename_all,
+            then = {
+                @serde_keep_case
+                key = { field },
+                rename_all = { $rename_all },
+                fields = $fields,
+                then = {
+                    opts = { $($($opts)*)? },
+                    deferred = $deferred,
+                },
+                cx = $cx,
+            },
+        }
+    };
+    // Apply `#[flatten(skip(...))]` and `#[flatten(only(...))]`, these select
+    // the inlined fields by the name they were declared with
+    (@flatten_opts
+        opts = { skip($($names:ident),* $(,)?) $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = { $($generate:ident)* },
+            flattened = $flattened:tt,
+            filter = {},
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@select_fields
+            names = { $($names)* },
+            keep = { others },
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = $on_conflict,
+                serde_rename_all = $serde_rename_all,
+                generate = { $($generate)* filtered },
+                flattened = $flattened,
+                filter = { skip },
+            },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_opts
+        opts = { only($($names:ident),* $(,)?) $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = { $($generate:ident)* },
+            flattened = $flattened:tt,
+            filter = {},
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@select_fields
+            names = { $($names)* },
+            keep = { listed },
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = $on_conflict,
+                serde_rename_all = $serde_rename_all,
+                generate = { $($generate)* filtered },
+                flattened = $flattened,
+                filter = { only },
+            },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_opts
+        opts = { skip($($names:tt)*) $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = $generate:tt,
+            flattened = $flattened:tt,
+            filter = { $_filter:ident },
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(
+            "only one of `#[flatten(skip(...))]` and `#[flatten(only(...))]` can be used"
+        );
+    };
+    (@flatten_opts
+        opts = { only($($names:tt)*) $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = $generate:tt,
+            flattened = $flattened:tt,
+            filter = { $_filter:ident },
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(
+            "only one of `#[flatten(skip(...))]` and `#[flatten(only(...))]` can be used"
+        );
+    };
+    // Sort the inlined fields into the listed fields and the other fields,
+    // this generates helper macros like renaming the fields
+    (@select_fields
+        names = { $($names:ident)* },
+        keep = $keep:tt,
+        opts = $opts:tt,
+        deferred = $deferred:tt,
+        fields = { $({ $source:ident => $($field:tt)* })* },
+        cx = {
+            dollar = { $dollar:tt },
+            $($cx:tt)*
+        },
+    ) => {
+        macro_rules! __flatten_structs_check_field {
+            $(($source) => {};)*
+            ($dollar unknown:ident) => {
+                ::core::compile_error!(::core::concat!(
+                    "`",
+                    ::core::stringify!($dollar unknown),
+                    "` is not a field of the flattened type",
+                ));
+            };
+        }
+        $(__flatten_structs_check_field!{$names})*
+        macro_rules! __flatten_structs_select {
+            $((
+                listed = { $dollar($dollar listed:tt)* },
+                others = $dollar others:tt,
+                fields = { { $names => $dollar($dollar field:tt)* } $dollar($dollar fields:tt)* },
+                state = $dollar state:tt,
+            ) => {
+                __flatten_structs_select!{
+                    listed = { $dollar($dollar listed)* { $names => $dollar($dollar field)* } },
+                    others = $dollar others,
+                    fields = { $dollar($dollar fields)* },
+                    state = $dollar state,
+                }
+            };)*
+            (
+                listed = $dollar listed:tt,
+                others = { $dollar($dollar others:tt)* },
+                fields = { $dollar field:tt $dollar($dollar fields:tt)* },
+                state = $dollar state:tt,
+            ) => {
+                __flatten_structs_select!{
+                    listed = $dollar listed,
+                    others = { $dollar($dollar others)* $dollar field },
+                    fields = { $dollar($dollar fields)* },
+                    state = $dollar state,
+                }
+            };
+            (
+                listed = $dollar listed:tt,
+                others = $dollar others:tt,
+                fields = {},
+                state = {
+                    keep = { listed },
+                    opts = $dollar opts:tt,
+                    deferred = $dollar deferred:tt,
+                    cx = $dollar cx:tt,
+                },
+            ) => {
+                $crate::flatten_structs!{@flatten_opts
+                    opts = $dollar opts,
+                    deferred = $dollar deferred,
+                    fields = $dollar listed,
+                    cx = $dollar cx,
+                }
+            };
+            (
+                listed = $dollar listed:tt,
+                others = $dollar others:tt,
+                fields = {},
+                state = {
+                    keep = { others },
+                    opts = $dollar opts:tt,
+                    deferred = $dollar deferred:tt,
+                    cx = $dollar cx:tt,
+                },
+            ) => {
+                $crate::flatten_structs!{@flatten_opts
+                    opts = $dollar opts,
+                    deferred = $dollar deferred,
+                    fields = $dollar others,
+                    cx = $dollar cx,
+                }
+            };
+        }
+        __flatten_structs_select!{
+            listed = {},
+            others = {},
+            fields = { $({ $source => $($field)* })* },
+            state = {
+                keep = $keep,
+                opts = $opts,
+                deferred = $deferred,
+                cx = {
+                    dollar = { $dollar },
+                    $($cx)*
+                },
+            },
+        }
+    };
+    // Collect `#[flatten(rename(...))]`, these are applied once all other
+    // options have been applied so they always produce the exact name given.
+    (@flatten_opts
+        opts = { rename($($rename_from:ident = $rename_to:ident),* $(,)?) $(, $($opts:tt)*)? },
+        deferred = {
+            renames = { $($renames:tt)* },
+            $($deferred:tt)*
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = { $($renames)* $($rename_from = $rename_to)* },
+                $($deferred)*
+            },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    // `#[flatten(keep(...))]` renames the fields to the name they are declared
+    // with, after all other options
+    (@flatten_opts
+        opts = { keep($($keep:ident),* $(,)?) $(, $($opts:tt)*)? },
+        deferred = {
+            renames = { $($renames:tt)* },
+            $($deferred:tt)*
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = { $($renames)* $($keep = $keep)* },
+                $($deferred)*
+            },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_vis_policy inherit_flatten) => {};
+    (@flatten_vis_policy inherit_source) => {};
+    (@flatten_vis_policy $policy:ident) => {
+        ::core::compile_error!(::core::concat!(
+            "unsupported visibility policy `",
+            ::core::stringify!($policy),
+            "`, expected `inherit_flatten` or `inherit_source`",
+        ));
+    };
+    // Collect `#[flatten(on_conflict = "...")]`, applied when merging the
+    // inlined fields with the fields declared before them.
+    (@flatten_opts
+        opts = { on_conflict = $on_conflict:ident $(, $($opts:tt)*)? },
+        deferred = $deferred:tt,
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "unsupported `on_conflict` policy `",
+            ::core::stringify!($on_conflict),
+            "`, expected \"error\", \"first\", \"last\" or \"merge\", ",
+            "identical fields are dropped with `#[flatten(dedup)]` instead",
+        ));
+    };
+    (@flatten_opts
+        opts = { on_conflict = $on_conflict:tt $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $_on_conflict:tt,
+            $($deferred:tt)*
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = { $on_conflict },
+                $($deferred)*
+            },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    // `#[flatten(dedup)]` is the `dedup` policy for conflicting fields, it
+    // can't be written as `on_conflict = ...` as the policies are strings
+    (@flatten_opts
+        opts = { dedup $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $_on_conflict:tt,
+            $($deferred:tt)*
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = { dedup },
+                $($deferred)*
+            },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    // Collect `#[flatten(reconstruct)]`, `#[flatten(into_nested)]` and
+    // `#[flatten(view)]`, the code for them is generated along with the struct
+    // once the final names of the inlined fields are known.
+    (@flatten_opts
+        opts = { reconstruct $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = { $($generate:ident)* },
+            flattened = $flattened:tt,
+            filter = $filter:tt,
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = $on_conflict,
+                serde_rename_all = $serde_rename_all,
+                generate = { $($generate)* reconstruct },
+                flattened = $flattened,
+                filter = $filter,
+            },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_opts
+        opts = { into_nested $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = { $($generate:ident)* },
+            flattened = $flattened:tt,
+            filter = $filter:tt,
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = $on_conflict,
+                serde_rename_all = $serde_rename_all,
+                generate = { $($generate)* into_nested },
+                flattened = $flattened,
+                filter = $filter,
+            },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_opts
+        opts = { view $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = { $($generate:ident)* },
+            flattened = $flattened:tt,
+            filter = $filter:tt,
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = $on_conflict,
+                serde_rename_all = $serde_rename_all,
+                generate = { $($generate)* view },
+                flattened = $flattened,
+                filter = $filter,
+            },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_opts
+        opts = { view_mut $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = { $($generate:ident)* },
+            flattened = $flattened:tt,
+            filter = $filter:tt,
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = $on_conflict,
+                serde_rename_all = $serde_rename_all,
+                generate = { $($generate)* view_mut },
+                flattened = $flattened,
+                filter = $filter,
+            },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    (@flatten_opts
+        opts = { field_count $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = { $($generate:ident)* },
+            flattened = $flattened:tt,
+            filter = $filter:tt,
+        },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = $on_conflict,
+                serde_rename_all = $serde_rename_all,
+                generate = { $($generate)* field_count },
+                flattened = $flattened,
+                filter = $filter,
+            },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    // Collect `#[flatten(assert_unique)]`, the inlined fields are checked by a
+    // const assertion instead of the compile error for duplicated fields
+    (@flatten_opts
+        opts = { assert_unique $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = { $($generate:ident)* },
+            flattened = $flattened:tt,
+            filter = $filter:tt,
+        },
+        fields = $fields:tt,
+        cx = {
+            dollar = $dollar:tt,
+            expanded_fields = $expanded_fields:tt,
+            queued_fields = $queued_fields:tt,
+            cx = {
+                definition = $definition:tt,
+                groups = $groups:tt,
+                own_fields = $own_fields:tt,
+                duplicates = $_duplicates:tt,
+                dollar = $_dollar:tt,
+            },
+        },
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = $on_conflict,
+                serde_rename_all = $serde_rename_all,
+                generate = { $($generate)* assert_unique },
+                flattened = $flattened,
+                filter = $filter,
+            },
+            fields = $fields,
+            cx = {
+                dollar = $dollar,
+                expanded_fields = $expanded_fields,
+                queued_fields = $queued_fields,
+                cx = {
+                    definition = $definition,
+                    groups = $groups,
+                    own_fields = $own_fields,
+                    duplicates = { assert },
+                    dollar = $dollar,
+                },
+            },
+        }
+    };
+    // Apply `#[flatten(retain)]`, the flattened field is kept as it was
+    // declared before its inlined fields
+    (@flatten_opts
+        opts = { retain $(, $($opts:tt)*)? },
+        deferred = {
+            renames = $renames:tt,
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = $generate:tt,
+            flattened = {
+                attrs = { $($flattened_attrs:tt)* },
+                vis = { $flattened_vis:vis },
+                name = $flattened_name:ident,
+                type = { $($flattened_type:tt)* },
+            },
+            filter = $filter:tt,
+        },
+        fields = $fields:tt,
+        cx = {
+            dollar = $dollar:tt,
+            expanded_fields = { $($expanded_fields:tt)* },
+            $($cx:tt)*
+        },
+    ) => {
+        $crate::flatten_structs!{@flatten_opts
+            opts = { $($($opts)*)? },
+            deferred = {
+                renames = $renames,
+                on_conflict = $on_conflict,
+                serde_rename_all = $serde_rename_all,
+                generate = $generate,
+                flattened = {
+                    attrs = { $($flattened_attrs)* },
+                    vis = { $flattened_vis },
+                    name = $flattened_name,
+                    type = { $($flattened_type)* },
+                },
+                filter = $filter,
+            },
+            fields = $fields,
+            cx = {
+                dollar = $dollar,
+                expanded_fields = { $($expanded_fields)* {
+                    $($flattened_attrs)*
+                    $flattened_vis $flattened_name: $($flattened_type)*
+                }},
+                $($cx)*
+            },
+        }
+    };
+    // Rename the inlined fields by the name they were declared with, this
+    // generates a helper macro since declarative macros can't compare
+    // identifiers otherwise.
+    (@flatten_opts
+        opts = {},
+        deferred = {
+            renames = { $($rename_from:ident = $rename_to:ident)+ },
+            $($deferred:tt)*
+        },
+        fields = { $({ $source:ident => $($field:tt)* })* },
+        cx = {
+            dollar = { $dollar:tt },
+            $($cx:tt)*
+        },
+    ) => {
+        macro_rules! __flatten_structs_check_field {
+            $(($source) => {};)*
+            ($dollar unknown:ident) => {
+                ::core::compile_error!(::core::concat!(
+                    "`",
+                    ::core::stringify!($dollar unknown),
+                    "` is not a field of the flattened type",
+                ));
+            };
+        }
+        $(__flatten_structs_check_field!{$rename_from})+
+        macro_rules! __flatten_structs_rename {
+            $((
+                renamed = { $dollar($dollar renamed:tt)* },
+                fields = { {
+                    $rename_from =>
+                    $dollar(#[$dollar($dollar field_attr:tt)*])*
+                    $dollar field_vis:vis $dollar field_name:ident: $dollar field_type:ty $dollar(= $dollar field_default:expr)?
+                } $dollar($dollar fields:tt)* },
+                state = $dollar state:tt,
+            ) => {
+                __flatten_structs_rename!{
+                    renamed = { $dollar($dollar renamed)* {
+                        $rename_from =>
+                        $dollar(#[$dollar($dollar field_attr)*])*
+                        $dollar field_vis $rename_to: $dollar field_type $dollar(= $dollar field_default)?
+                    } },
+                    fields = { $dollar($dollar fields)* },
+                    state = $dollar state,
+                }
+            };)+
+            (
+                renamed = { $dollar($dollar renamed:tt)* },
+                fields = { $dollar field:tt $dollar($dollar fields:tt)* },
+                state = $dollar state:tt,
+            ) => {
+                __flatten_structs_rename!{
+                    renamed = { $dollar($dollar renamed)* $dollar field },
+                    fields = { $dollar($dollar fields)* },
+                    state = $dollar state,
+                }
+            };
+            (
+                renamed = $dollar renamed:tt,
+                fields = {},
+                state = {
+                    deferred = $dollar deferred:tt,
+                    cx = $dollar cx:tt,
+                },
+            ) => {
+                $crate::flatten_structs!{@flatten_opts
+                    opts = {},
+                    deferred = $dollar deferred,
+                    fields = $dollar renamed,
+                    cx = $dollar cx,
+                }
+            };
+        }
+        __flatten_structs_rename!{
+            renamed = {},
+            fields = { $({ $source => $($field)* })* },
+            state = {
+                deferred = {
+                    renames = {},
+                    $($deferred)*
+                },
+                cx = {
+                    dollar = { $dollar },
+                    $($cx)*
+                },
+            },
+        }
+    };
+    // Done, all flatten options applied to the inlined fields. Remember which
+    // inlined fields belong to the flattened field, to generate the code for
+    // the collected options and `#[flatten_derive(split)]` along with the
+    // struct
+    (@flatten_opts
+        opts = {},
+        deferred = {
+            renames = {},
+            on_conflict = $on_conflict:tt,
+            serde_rename_all = $serde_rename_all:tt,
+            generate = $generate:tt,
+            flattened = $flattened:tt,
+            filter = $filter:tt,
+        },
+        fields = { $({
+            $source:ident =>
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        cx = {
+            dollar = $dollar:tt,
+            expanded_fields = $expanded_fields:tt,
+            queued_fields = $queued_fields:tt,
+            cx = {
+                definition = $definition:tt,
+                groups = { $($groups:tt)* },
+                own_fields = $own_fields:tt,
+                duplicates = $duplicates:tt,
+                dollar = $_dollar:tt,
+            },
+        },
+    ) => {
+        $crate::flatten_structs!{@filtered
+            generate = $generate,
+            filtered = {},
+            needs_all = {},
+        }
+        $crate::flatten_structs!{@merge_fields
+            on_conflict = $on_conflict,
+            fields = { $({
+                $(#[$($field_attr)*])*
+                $field_vis $field_name: $field_type $(= $field_default)?
+            })* },
+            cx = {
+                dollar = $dollar,
+                expanded_fields = $expanded_fields,
+                queued_fields = $queued_fields,
+                cx = {
+                    definition = $definition,
+                    groups = { $($groups)* {
+                        generate = $generate,
+                        flattened = $flattened,
+                        fields = { $({ $source => $field_name: $field_type })* },
+                    } },
+                    own_fields = $own_fields,
+                    duplicates = $duplicates,
+                    dollar = $dollar,
+                },
+            },
+        }
+    };
+    // `skip` and `only` leave out fields that `reconstruct`, `into_nested` and
+    // `#[flatten_derive(split)]` need to create the flattened type, they mark
+    // the group as `filtered`
+    (@filtered
+        generate = { filtered $($generate:ident)* },
+        filtered = $_filtered:tt,
+        needs_all = $needs_all:tt,
+    ) => {
+        $crate::flatten_structs!{@filtered
+            generate = { $($generate)* },
+            filtered = { filtered },
+            needs_all = $needs_all,
+        }
+    };
+    (@filtered
+        generate = { reconstruct $($generate:ident)* },
+        filtered = $filtered:tt,
+        needs_all = {},
+    ) => {
+        $crate::flatten_structs!{@filtered
+            generate = { $($generate)* },
+            filtered = $filtered,
+            needs_all = { reconstruct },
+        }
+    };
+    (@filtered
+        generate = { into_nested $($generate:ident)* },
+        filtered = $filtered:tt,
+        needs_all = {},
+    ) => {
+        $crate::flatten_structs!{@filtered
+            generate = { $($generate)* },
+            filtered = $filtered,
+            needs_all = { into_nested },
+        }
+    };
+    (@filtered
+        generate = { $_other:ident $($generate:ident)* },
+        filtered = $filtered:tt,
+        needs_all = $needs_all:tt,
+    ) => {
+        $crate::flatten_structs!{@filtered
+            generate = { $($generate)* },
+            filtered = $filtered,
+            needs_all = $needs_all,
+        }
+    };
+    (@filtered
+        generate = {},
+        filtered = { filtered },
+        needs_all = { $needs_all:ident },
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten(skip(...))]` and `#[flatten(only(...))]` can't be combined with `",
+            ::core::stringify!($needs_all),
+            "`, which needs all fields of the flattened type",
+        ));
+    };
+    (@filtered
+        generate = {},
+        filtered = $filtered:tt,
+        needs_all = $needs_all:tt,
+    ) => {};
+    (@flatten_opts
+        opts = { $($opts:tt)* },
+        deferred = $deferred:tt,
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "unsupported `#[flatten]` option: ",
+            ::core::stringify!($($opts)*),
+        ));
+    };
+    // Translate the `rename_all` of the flattened type into the paste modifier
+    // that converts a field name into the same case
+    (@serde_keep_case
+        key = $key:tt,
+        rename_all = {},
+        fields = $fields:tt,
+        then = $then:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@serde_keep_case
+            key = $key,
+            modifier = {},
+            fields = $fields,
+            kept = {},
+            then = $then,
+            cx = $cx,
+        }
+    };
+    (@serde_keep_case
+        key = $key:tt,
+        rename_all = { "lowercase" },
+        fields = $fields:tt,
+        then = $then:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@serde_keep_case
+            key = $key,
+            modifier = { :lower },
+            fields = $fields,
+            kept = {},
+            then = $then,
+            cx = $cx,
+        }
+    };
+    (@serde_keep_case
+        key = $key:tt,
+        rename_all = { "UPPERCASE" },
+        fields = $fields:tt,
+        then = $then:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@serde_keep_case
+            key = $key,
+            modifier = { :upper },
+            fields = $fields,
+            kept = {},
+            then = $then,
+            cx = $cx,
+        }
+    };
+    (@serde_keep_case
+        key = $key:tt,
+        rename_all = { "PascalCase" },
+        fields = $fields:tt,
+        then = $then:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@serde_keep_case
+            key = $key,
+            modifier = { :camel },
+            fields = $fields,
+            kept = {},
+            then = $then,
+            cx = $cx,
+        }
+    };
+    (@serde_keep_case
+        key = $key:tt,
+        rename_all = { "snake_case" },
+        fields = $fields:tt,
+        then = $then:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@serde_keep_case
+            key = $key,
+            modifier = {},
+            fields = $fields,
+            kept = {},
+            then = $then,
+            cx = $cx,
+        }
+    };
+    (@serde_keep_case
+        key = $key:tt,
+        rename_all = { "SCREAMING_SNAKE_CASE" },
+        fields = $fields:tt,
+        then = $then:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@serde_keep_case
+            key = $key,
+            modifier = { :upper },
+            fields = $fields,
+            kept = {},
+            then = $then,
+            cx = $cx,
+        }
+    };
+    (@serde_keep_case
+        key = { source },
+        rename_all = { $rename_all:tt },
+        fields = $fields:tt,
+        then = $then:tt,
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`serde_keep_case` can't keep the case of `rename_all = ",
+            ::core::stringify!($rename_all),
+            "`, rename the fields with `#[serde(rename = \"...\")]` instead",
+        ));
+    };
+    (@serde_keep_case
+        key = { field },
+        rename_all = { $rename_all:tt },
+        fields = $fields:tt,
+        then = $then:tt,
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "unsupported `#[flatten(rename_all = ",
+            ::core::stringify!($rename_all),
+            ")]`, expected \"lowercase\", \"UPPERCASE\", \"PascalCase\", ",
+            "\"snake_case\" or \"SCREAMING_SNAKE_CASE\"",
+        ));
+    };
+    (@serde_keep_case
+        key = $key:tt,
+        modifier = { $($modifier:tt)* },
+        fields = { {
+            $source:ident =>
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        } $($fields:tt)* },
+        kept = $kept:tt,
+        then = $then:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@serde_find_rename
+            serde = {},
+            attrs = { $(#[$($field_attr)*])* },
+            field = {
+                $source =>
+                $(#[$($field_attr)*])*
+                $field_vis $field_name: $field_type $(= $field_default)?
+            },
+            state = {
+                key = $key,
+                modifier = { $($modifier)* },
+                fields = { $($fields)* },
+                kept = $kept,
+                then = $then,
+                cx = $cx,
+            },
+        }
+    };
+    (@serde_keep_case
+        key = $key:tt,
+        modifier = $modifier:tt,
+        fields = {},
+        kept = $kept:tt,
+        then = { $($then:tt)* },
+        cx = $cx:tt,
+    ) => {
+        $crate::__private_codegen_paste!{
+            $crate::flatten_structs!{@flatten_opts
+                $($then)*
+                fields = $kept,
+                cx = $cx,
+            }
+        }
+    };
+    // Fields that are already renamed keep their name
+    (@serde_find_rename
+        serde = { rename $($serde:tt)* },
+        attrs = $attrs:tt,
+        field = $field:tt,
+        state = {
+            key = $key:tt,
+            modifier = $modifier:tt,
+            fields = $fields:tt,
+            kept = { $($kept:tt)* },
+            then = $then:tt,
+            cx = $cx:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@serde_keep_case
+            key = $key,
+            modifier = $modifier,
+            fields = $fields,
+            kept = { $($kept)* $field },
+            then = $then,
+            cx = $cx,
+        }
+    };
+    (@serde_find_rename
+        serde = { $_serde:tt $($serde:tt)* },
+        attrs = $attrs:tt,
+        field = $field:tt,
+        state = $state:tt,
+    ) => {
+        $crate::flatten_structs!{@serde_find_rename
+            serde = { $($serde)* },
+            attrs = $attrs,
+            field = $field,
+            state = $state,
+        }
+    };
+    (@serde_find_rename
+        serde = {},
+        attrs = { #[serde($($serde:tt)*)] $($attrs:tt)* },
+        field = $field:tt,
+        state = $state:tt,
+    ) => {
+        $crate::flatten_structs!{@serde_find_rename
+            serde = { $($serde)* },
+            attrs = { $($attrs)* },
+            field = $field,
+            state = $state,
+        }
+    };
+    (@serde_find_rename
+        serde = {},
+        attrs = { #[$($attr:tt)*] $($attrs:tt)* },
+        field = $field:tt,
+        state = $state:tt,
+    ) => {
+        $crate::flatten_structs!{@serde_find_rename
+            serde = {},
+            attrs = { $($attrs)* },
+            field = $field,
+            state = $state,
+        }
+    };
+    (@serde_find_rename
+        serde = {},
+        attrs = {},
+        field = { $source:ident => $($field:tt)* },
+        state = {
+            key = { source },
+            modifier = { $($modifier:tt)* },
+            fields = $fields:tt,
+            kept = { $($kept:tt)* },
+            then = $then:tt,
+            cx = $cx:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@serde_keep_case
+            key = { source },
+            modifier = { $($modifier)* },
+            fields = $fields,
+            kept = { $($kept)* {
+                $source =>
+                #[serde(rename = "" $source $($modifier)*)]
+                $($field)*
+            } },
+            then = $then,
+            cx = $cx,
+        }
+    };
+    // `#[flatten(rename_all = "...")]` renames the fields under the names they
+    // have at this point
+    (@serde_find_rename
+        serde = {},
+        attrs = {},
+        field = {
+            $source:ident =>
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        },
+        state = {
+            key = { field },
+            modifier = { $($modifier:tt)* },
+            fields = $fields:tt,
+            kept = { $($kept:tt)* },
+            then = $then:tt,
+            cx = $cx:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@serde_keep_case
+            key = { field },
+            modifier = { $($modifier)* },
+            fields = $fields,
+            kept = { $($kept)* {
+                $source =>
+                #[serde(rename = "" $field_name $($modifier)*)]
+                $(#[$($field_attr)*])*
+                $field_vis $field_name: $field_type $(= $field_default)?
+            } },
+            then = $then,
+            cx = $cx,
+        }
+    };
+    // Merge the inlined fields into the expanded fields, conflicting field
+    // names are reported once all fields are gathered.
+    (@merge_fields
+        on_conflict = { "error" },
+        fields = { $($fields:tt)* },
+        cx = {
+            dollar = $dollar:tt,
+            expanded_fields = { $($expanded_fields:tt)* },
+            queued_fields = $queued_fields:tt,
+            cx = $cx:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@gather_fields
+            expanded_fields = { $($expanded_fields)* $($fields)* },
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+    // Keep the already expanded fields, drop conflicting inlined fields
+    (@merge_fields
+        on_conflict = { "first" },
+        fields = $fields:tt,
+        cx = {
+            dollar = $dollar:tt,
+            expanded_fields = { $({
+                $(#[$($field_attr:tt)*])*
+                $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+            })* },
+            queued_fields = $queued_fields:tt,
+            cx = $cx:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@filter_fields
+            remove = { $($field_name)* },
+            fields = $fields,
+            then = {
+                before = { $({
+                    $(#[$($field_attr)*])*
+                    $field_vis $field_name: $field_type $(= $field_default)?
+                })* },
+                after = {},
+                queued_fields = $queued_fields,
+                cx = $cx,
+            },
+            dollar = $dollar,
+        }
+    };
+    // Drop conflicting already expanded fields, keep the inlined fields
+    (@merge_fields
+        on_conflict = { "last" },
+        fields = { $({
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        cx = {
+            dollar = $dollar:tt,
+            expanded_fields = $expanded_fields:tt,
+            queued_fields = $queued_fields:tt,
+            cx = $cx:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@filter_fields
+            remove = { $($field_name)* },
+            fields = $expanded_fields,
+            then = {
+                before = {},
+                after = { $({
+                    $(#[$($field_attr)*])*
+                    $field_vis $field_name: $field_type $(= $field_default)?
+                })* },
+                queued_fields = $queued_fields,
+                cx = $cx,
+            },
+            dollar = $dollar,
+        }
+    };
+    // Keep the already expanded fields like `"first"`, the conflicting
+    // inlined fields need to have the same type, or be the same field for
+    // `dedup`.
+    (@merge_fields
+        on_conflict = { "merge" },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@merge_same
+            policy = { "merge" },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    (@merge_fields
+        on_conflict = { dedup },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@merge_same
+            policy = { dedup },
+            fields = $fields,
+            cx = $cx,
+        }
+    };
+    (@merge_fields
+        on_conflict = { $on_conflict:tt },
+        fields = $fields:tt,
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "unsupported `on_conflict` policy ",
+            ::core::stringify!($on_conflict),
+            ", expected \"error\", \"first\", \"last\" or \"merge\", ",
+            "identical fields are dropped with `#[flatten(dedup)]` instead",
+        ));
+    };
+    (@merge_same
+        policy = $policy:tt,
+        fields = $fields:tt,
+        cx = {
+            dollar = { $dollar:tt },
+            expanded_fields = { $({
+                $(#[$($field_attr:tt)*])*
+                $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+            })* },
+            queued_fields = $queued_fields:tt,
+            cx = $cx:tt,
+        },
+    ) => {
+        macro_rules! __flatten_structs_merge {
+            $((
+                fields = { {
+                    $dollar(#[$dollar($dollar merged_attr:tt)*])*
+                    $dollar merged_vis:vis $field_name: $dollar merged_type:ty $dollar(= $dollar merged_default:expr)?
+                } $dollar($dollar fields:tt)* },
+            ) => {
+                $crate::flatten_structs!{@merge_check
+                    policy = $policy,
+                    name = $field_name,
+                    field = { $(#[$($field_attr)*])* $field_type },
+                    merged = { $dollar(#[$dollar($dollar merged_attr)*])* $dollar merged_type },
+                }
+                __flatten_structs_merge!{
+                    fields = { $dollar($dollar fields)* },
+                }
+            };)*
+            (fields = { $dollar field:tt $dollar($dollar fields:tt)* },) => {
+                __flatten_structs_merge!{
+                    fields = { $dollar($dollar fields)* },
+                }
+            };
+            (fields = {},) => {};
+        }
+        __flatten_structs_merge!{
+            fields = $fields,
+        }
+        $crate::flatten_structs!{@filter_fields
+            remove = { $($field_name)* },
+            fields = $fields,
+            then = {
+                before = { $({
+                    $(#[$($field_attr)*])*
+                    $field_vis $field_name: $field_type $(= $field_default)?
+                })* },
+                after = {},
+                queued_fields = $queued_fields,
+                cx = $cx,
+            },
+            dollar = { $dollar },
+        }
+    };
+    // Fail to compile if a merged field isn't written like the field it is
+    // merged with, only the types are compared for `"merge"`.
+    (@merge_check
+        policy = { "merge" },
+        name = $field_name:ident,
+        field = { $(#[$($field_attr:tt)*])* $field_type:ty },
+        merged = { $(#[$($merged_attr:tt)*])* $merged_type:ty },
+    ) => {
+        const _: () = {
+            let field_types: &[&str] = &[::core::stringify!($field_type)];
+            if $crate::__private_count_field(field_types, ::core::stringify!($merged_type)) == 0 {
+                ::core::panic!(::core::concat!(
+                    "field `",
+                    ::core::stringify!($field_name),
+                    "` can't be merged, its types `",
+                    ::core::stringify!($field_type),
+                    "` and `",
+                    ::core::stringify!($merged_type),
+                    "` differ",
+                ));
+            }
+        };
+    };
+    (@merge_check
+        policy = { dedup },
+        name = $field_name:ident,
+        field = { $(#[$($field_attr:tt)*])* $field_type:ty },
+        merged = { $(#[$($merged_attr:tt)*])* $merged_type:ty },
+    ) => {
+        const _: () = {
+            let fields: &[&str] = &[::core::stringify!($(#[$($field_attr)*])* $field_type)];
+            if $crate::__private_count_field(
+                fields,
+                ::core::stringify!($(#[$($merged_attr)*])* $merged_type),
+            ) == 0
+            {
+                ::core::panic!(::core::concat!(
+                    "field `",
+                    ::core::stringify!($field_name),
+                    "` can't be deduplicated, it is declared as `",
+                    ::core::stringify!($(#[$($field_attr)*])* $field_type),
+                    "` and as `",
+                    ::core::stringify!($(#[$($merged_attr)*])* $merged_type),
+                    "`",
+                ));
+            }
+        };
+    };
+    // Remove all fields with one of the given names, then continue gathering
+    // with the remaining fields placed between `before` and `after`.
+    (@filter_fields
+        remove = { $($remove:ident)* },
+        fields = $fields:tt,
+        then = $then:tt,
+        dollar = { $dollar:tt },
+    ) => {
+        macro_rules! __flatten_structs_filter {
+            $((
+                kept = $dollar kept:tt,
+                fields = { {
+                    $dollar(#[$dollar($dollar field_attr:tt)*])*
+                    $dollar field_vis:vis $remove: $dollar field_type:ty $dollar(= $dollar field_default:expr)?
+                } $dollar($dollar fields:tt)* },
+                then = $dollar then:tt,
+            ) => {
+                __flatten_structs_filter!{
+                    kept = $dollar kept,
+                    fields = { $dollar($dollar fields)* },
+                    then = $dollar then,
+                }
+            };)*
+            (
+                kept = { $dollar($dollar kept:tt)* },
+                fields = { $dollar field:tt $dollar($dollar fields:tt)* },
+                then = $dollar then:tt,
+            ) => {
+                __flatten_structs_filter!{
+                    kept = { $dollar($dollar kept)* $dollar field },
+                    fields = { $dollar($dollar fields)* },
+                    then = $dollar then,
+                }
+            };
+            (
+                kept = { $dollar($dollar kept:tt)* },
+                fields = {},
+                then = {
+                    before = { $dollar($dollar before:tt)* },
+                    after = { $dollar($dollar after:tt)* },
+                    queued_fields = $dollar queued_fields:tt,
+                    cx = $dollar cx:tt,
+                },
+            ) => {
+                $crate::flatten_structs!{@gather_fields
+                    expanded_fields = {
+                        $dollar($dollar before)*
+                        $dollar($dollar kept)*
+                        $dollar($dollar after)*
+                    },
+                    queued_fields = $dollar queued_fields,
+                    cx = $dollar cx,
+                }
+            };
+        }
+        __flatten_structs_filter!{
+            kept = {},
+            fields = $fields,
+            then = $then,
+        }
+    };
+    // Done, have gathered info about all fields. With
+    // `#[flatten(assert_unique)]` duplicated field names are reported by the
+    // generated assertions instead:
+    (@gather_fields
+        expanded_fields = $expanded_fields:tt,
+        queued_fields = {},
+        cx = {
+            definition = $definition:tt,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            duplicates = { assert },
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@gathered
+            expanded_fields = $expanded_fields,
+            cx = {
+                definition = $definition,
+                groups = $groups,
+                own_fields = $own_fields,
+                duplicates = { assert },
+                dollar = $dollar,
+            },
+        }
+    };
+    // Make sure no field name is declared twice, flattening makes it easy to
+    // pull in the same field name from different types and the compiler's
+    // error would point into macro generated code. The names are compared in
+    // a const assertion, comparing them in the macro would need a recursion
+    // per field. The error names the types of the first two declarations of
+    // the field, differing types usually mean that different fields got the
+    // same name
+    (@gather_fields
+        expanded_fields = { $({
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        queued_fields = {},
+        cx = $cx:tt,
+    ) => {
+        const _: () = {
+            const MESSAGE: [&str; 8] = $crate::__private_duplicate(
+                &[$(::core::stringify!($field_name)),*],
+                &[$(::core::stringify!($field_type)),*],
+            );
+            const LEN: usize = $crate::__private_concat_len(&MESSAGE);
+            const BYTES: [u8; LEN] = $crate::__private_concat(&MESSAGE);
+            if LEN > 0 {
+                match ::core::str::from_utf8(&BYTES) {
+                    ::core::result::Result::Ok(message) => ::core::panic!("{}", message),
+                    ::core::result::Result::Err(_) => ::core::unreachable!(),
+                }
+            }
+        };
+        $crate::flatten_structs!{@gathered
+            expanded_fields = { $({
+                $(#[$($field_attr)*])*
+                $field_vis $field_name: $field_type $(= $field_default)?
+            })* },
+            cx = $cx,
+        }
+    };
+    // All field names of an enum variant are unique, continue with the next
+    // variant. Variant fields can't have a visibility, and there is no
+    // `Default` implementation for their default values
+    (@gathered
+        expanded_fields = { $({
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        cx = {
+            definition = {
+                variant = {
+                    attrs = { $($variant_attrs:tt)* },
+                    name = $variant:ident,
+                    enum = {
+                        attrs = $attrs:tt,
+                        vis = $vis:tt,
+                        name = $name:ident,
+                        variants = { $($variants:tt)* },
+                        queued_variants = $queued_variants:tt,
+                    },
+                },
+            },
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            duplicates = $duplicates:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@variant_groups
+            variant = $variant,
+            groups = $groups,
+        }
+        $crate::flatten_structs!{@enum_variants
+            attrs = $attrs,
+            vis = $vis,
+            name = $name,
+            variants = { $($variants)* $($variant_attrs)* $variant {$(
+                $(#[$($field_attr)*])*
+                $field_name: $field_type,
+            )*}, },
+            queued_variants = $queued_variants,
+        }
+    };
+    // Nothing is generated for the groups of enum variants, only `filtered`
+    // doesn't generate any code. Each group is checked on its own.
+    (@variant_groups
+        variant = $variant:ident,
+        groups = { $($group:tt)* },
+    ) => {
+        $($crate::flatten_structs!{@variant_group
+            variant = $variant,
+            group = $group,
+        })*
+    };
+    (@variant_group
+        variant = $variant:ident,
+        group = {
+            generate = { filtered $($generate:ident)* },
+            flattened = $flattened:tt,
+            fields = $fields:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@variant_group
+            variant = $variant,
+            group = {
+                generate = { $($generate)* },
+                flattened = $flattened,
+                fields = $fields,
+            },
+        }
+    };
+    (@variant_group
+        variant = $variant:ident,
+        group = {
+            generate = { $option:ident $($generate:ident)* },
+            flattened = $flattened:tt,
+            fields = $fields:tt,
+        },
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten(",
+            ::core::stringify!($option),
+            ")]` generates code for structs, it can't be used in the enum variant `",
+            ::core::stringify!($variant),
+            "`",
+        ));
+    };
+    (@variant_group
+        variant = $variant:ident,
+        group = {
+            generate = {},
+            flattened = $flattened:tt,
+            fields = $fields:tt,
+        },
+    ) => {};
+    // `#[flatten(order(...))]` reorders the fields before the struct is
+    // emitted, every field has to be listed exactly once
+    (@gathered
+        expanded_fields = { $({
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        cx = {
+            definition = {
+                config = {
+                    export = $export:tt,
+                    export_attrs = $export_attrs:tt,
+                    macro_name = $macro_name:tt,
+                    serde_rename_all = $serde_rename_all:tt,
+                    derives = $derives:tt,
+                    copies = $copies:tt,
+                    module = $module:tt,
+                    kind = $kind:tt,
+                    inspect = $inspect:tt,
+                    order = { $($order:ident)+ },
+                    strict = $strict:tt,
+                    postprocess = $postprocess:tt,
+                },
+                attrs = $attrs:tt,
+                vis = $vis:tt,
+                name = $name:ident,
+                $($item:tt)*
+            },
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            duplicates = $duplicates:tt,
+            dollar = { $dollar:tt },
+        },
+    ) => {
+        const _: () = {
+            let order: &[&str] = &[$(::core::stringify!($order)),*];
+            $(
+                if $crate::__private_count_field(order, ::core::stringify!($field_name)) != 1 {
+                    ::core::panic!(::core::concat!(
+                        "`#[flatten(order(...))]` of `",
+                        ::core::stringify!($name),
+                        "` needs to list the field `",
+                        ::core::stringify!($field_name),
+                        "` exactly once",
+                    ));
+                }
+            )*
+        };
+        macro_rules! __flatten_structs_order {
+            $((
+                ordered = { $dollar($dollar ordered:tt)* },
+                order = { $field_name $dollar($dollar order:ident)* },
+                then = $dollar then:tt,
+            ) => {
+                __flatten_structs_order!{
+                    ordered = { $dollar($dollar ordered)* {
+                        $(#[$($field_attr)*])*
+                        $field_vis $field_name: $field_type $(= $field_default)?
+                    } },
+                    order = { $dollar($dollar order)* },
+                    then = $dollar then,
+                }
+            };)*
+            (
+                ordered = $dollar ordered:tt,
+                order = { $dollar unknown:ident $dollar($dollar order:ident)* },
+                then = $dollar then:tt,
+            ) => {
+                ::core::compile_error!(::core::concat!(
+                    "`#[flatten(order(...))]` names the field `",
+                    ::core::stringify!($dollar unknown),
+                    "` which `",
+                    ::core::stringify!($name),
+                    "` doesn't have",
+                ));
+            };
+            (
+                ordered = $dollar ordered:tt,
+                order = {},
+                then = { $dollar($dollar then:tt)* },
+            ) => {
+                $crate::flatten_structs!{@gathered
+                    expanded_fields = $dollar ordered,
+                    $dollar($dollar then)*
+                }
+            };
+        }
+        __flatten_structs_order!{
+            ordered = {},
+            order = { $($order)+ },
+            then = {
+                cx = {
+                    definition = {
+                        config = {
+                            export = $export,
+                            export_attrs = $export_attrs,
+                            macro_name = $macro_name,
+                            serde_rename_all = $serde_rename_all,
+                            derives = $derives,
+                            copies = $copies,
+                            module = $module,
+                            kind = $kind,
+                            inspect = $inspect,
+                            order = {},
+                            strict = $strict,
+                            postprocess = $postprocess,
+                        },
+                        attrs = $attrs,
+                        vis = $vis,
+                        name = $name,
+                        $($item)*
+                    },
+                    groups = $groups,
+                    own_fields = $own_fields,
+                    duplicates = $duplicates,
+                    dollar = { $dollar },
+                },
+            },
+        }
+    };
+    // All field names are unique, emit the struct:
+    (@gathered
+        expanded_fields = { $({
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?
+        })* },
+        cx = {
+            definition = {
+                config = {
+                    export = { $export:vis },
+                    export_attrs = { $(#[$export_attr:meta])* },
+                    macro_name = { $macro_name:ident },
+                    serde_rename_all = { $($serde_rename_all:tt)? },
+                    derives = { $($derives:tt)* },
+                    copies = $copies:tt,
+                    module = { $($module:ident)? },
+                    kind = { $kind:ident },
+                    inspect = $inspect:tt,
+                    order = {},
+                    strict = $strict:tt,
+                    postprocess = $postprocess:tt,
+                },
+                attrs = { $(#[$struct_attr:meta])* },
+                vis = { $vis:vis },
+                name = $name:ident,
+                generics = { $($generics:tt)* },
+                params = { $($params:tt)* },
+                impl_generics = { $($impl_generics:tt)* },
+                where_clause = { $($where_clause:tt)* },
+            },
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            duplicates = $_duplicates:tt,
+            dollar = { $dollar:tt },
+        },
+    ) => {
+        $crate::flatten_structs!{@copies
+            copies = $copies,
+            vis = { $vis },
+            generics = { $($generics)* },
+            where_clause = { $($where_clause)* },
+            body = { {$(
+                $(#[$($field_attr)*])*
+                $field_vis $field_name: $field_type,
+            )*} },
+            fields = { $({ $field_name: $field_type $(= $field_default)? })* },
+            container = {
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                groups = $groups,
+                own_fields = $own_fields,
+                serde_fields = { $({
+                    attrs = { $(#[$($field_attr)*])* },
+                    name = $field_name,
+                    type = { $field_type },
+                    default = { $($field_default)? },
+                })* },
+                dollar = { $dollar },
+            },
+        }
+        $crate::flatten_structs!{@struct_items
+            kind = { $kind },
+            items = {
+                $crate::flatten_structs!{@doc_origins
+                    scan = { $($derives)* },
+                    groups = $groups,
+                    attrs = { $(#[$struct_attr])* },
+                    item = {
+                        postprocess = $postprocess,
+                        vis = { $vis },
+                        name = $name,
+                        generics = { $($generics)* },
+                        where_clause = { $($where_clause)* },
+                        fields = {$(
+                            $(#[$($field_attr)*])*
+                            $field_vis $field_name: $field_type $(= $field_default)?,
+                        )*},
+                    },
+                }
+                #[allow(deprecated, single_use_lifetimes)]
+                impl<$($impl_generics)*> $name<$($params)*> $($where_clause)* {
+                    /// The names of all fields, including the inlined ones.
+                    #[allow(dead_code)]
+                    pub const FIELD_NAMES: &'static [&'static str] = &[$(
+                        $crate::__private_unraw(::core::stringify!($field_name))
+                    ),*];
+                    /// The number of fields, including the inlined ones.
+                    #[allow(dead_code)]
+                    pub const FIELD_COUNT: usize = Self::FIELD_NAMES.len();
+                    /// The names of all fields, including the inlined ones, same as
+                    /// [`Self::FIELD_NAMES`].
+                    #[allow(dead_code)]
+                    pub fn field_names(&self) -> &'static [&'static str] {
+                        Self::FIELD_NAMES
+                    }
+                }
+                #[allow(deprecated)]
+                #[automatically_derived]
+                impl<$($impl_generics)*> $crate::__PrivateFlattened for $name<$($params)*>
+                $($where_clause)* {}
+                $crate::flatten_structs!{@default
+                    defaults = { $($($field_default)?)* },
+                    fields = { $({ $field_name $(= $field_default)? })* },
+                    container = {
+                        generics = { $($impl_generics)* },
+                        params = { $($params)* },
+                        where_clause = { $($where_clause)* },
+                        name = $name,
+                    },
+                }
+                $crate::flatten_structs!{@derives
+                    derives = { $($derives)* },
+                    fields = { $({ $field_name: $field_type $(= $field_default)? })* },
+                    container = {
+                        vis = { $vis },
+                        generics = { $($generics)* },
+                        impl_generics = { $($impl_generics)* },
+                        params = { $($params)* },
+                        where_clause = { $($where_clause)* },
+                        name = $name,
+                        groups = $groups,
+                        own_fields = $own_fields,
+                        dollar = { $dollar },
+                    },
+                }
+                $crate::flatten_structs!{@try_from_map
+                    scan = { $($derives)* },
+                    fields = { $({
+                        attrs = { $(#[$($field_attr)*])* },
+                        name = $field_name,
+                        type = { $field_type },
+                        default = { $($field_default)? },
+                    })* },
+                    container = {
+                        rename_all = { $($serde_rename_all)? },
+                        impl_generics = { $($impl_generics)* },
+                        params = { $($params)* },
+                        where_clause = { $($where_clause)* },
+                        name = $name,
+                    },
+                }
+                $crate::flatten_structs!{@groups
+                    groups = $groups,
+                    container = {
+                        generics = { $($impl_generics)* },
+                        params = { $($params)* },
+                        where_clause = { $($where_clause)* },
+                        name = $name,
+                        field_names = { $($field_name)* },
+                    },
+                }
+                $crate::flatten_structs!{@accept_nested
+                    scan = $groups,
+                    nested = {},
+                    fields = { $({
+                        attrs = { $(#[$($field_attr)*])* },
+                        kept = {},
+                        cfg = {},
+                        name = $field_name,
+                        type = { $field_type },
+                    })* },
+                    done = {},
+                    container = {
+                        serde_rename_all = { $($serde_rename_all)? },
+                        generics = { $($generics)* },
+                        impl_generics = { $($impl_generics)* },
+                        params = { $($params)* },
+                        where_clause = { $($where_clause)* },
+                        name = $name,
+                        dollar = { $dollar },
+                    },
+                }
+            },
+        }
+        $crate::flatten_structs!{@module
+            inspect = $inspect,
+            module = { $($module)? },
+            export = { $export },
+            items = {
+                $crate::__private_codegen_paste!{
+                    // Unique name for this macro:
+                    // This macro allows another macro to query this types fields.
+                    $(#[$export_attr])*
+                    macro_rules! [<__private_field_inspect_for $macro_name>] {
+                        (
+                            call = { $dollar($dollarcall:tt)* },
+                            prefix = { $dollar($dollarprefix:tt)* },
+                            cx = $dollarcx:tt,
+                        ) => {
+                            $dollar($dollarcall)*! {$dollar($dollarprefix)*
+                                fields = {$(
+                                    $(#[$($field_attr)*])*
+                                    $field_vis $field_name: $field_type $(= $field_default)?,
+                                )*},
+                                serde_rename_all = { $($serde_rename_all)? },
+                                field_types = {
+                                    type_name = $name,
+                                    types_trait = [<__private_field_types_of $macro_name>],
+                                    type_module = { $($module)? },
+                                    kind = { $kind },
+                                },
+                                cx = $dollarcx,
+                            }
+                        };
+                    }
+                    // But expose the macro with the same name as the generated
+                    // type. This works because types, macros and values all
+                    // have different namespaces so they don't conflict.
+                    #[allow(unused_imports)]
+                    $export use [<__private_field_inspect_for $macro_name>] as $macro_name;
+
+                    $crate::flatten_structs!{@field_types
+                        export = { $export },
+                        types_trait = [<__private_field_types_of $macro_name>],
+                        generics = { $($impl_generics)* },
+                        params = { $($params)* },
+                        where_clause = { $($where_clause)* },
+                        name = $name,
+                        fields = { $({ $field_name: $field_type })* },
+                    }
+                }
+            },
+        }
+    };
+    // `#[flatten_derive(doc_origins)]` appends a paragraph naming the
+    // flattened types to the documentation of the struct, which needs to be
+    // part of its attributes.
+    (@doc_origins
+        scan = { doc_origins $($scan:tt)* },
+        groups = $groups:tt,
+        attrs = $attrs:tt,
+        item = $item:tt,
+    ) => {
+        $crate::flatten_structs!{@doc_origins_add
+            groups = $groups,
+            attrs = $attrs,
+            item = $item,
+        }
+    };
+    (@doc_origins
+        scan = { $_derive:tt $($scan:tt)* },
+        groups = $groups:tt,
+        attrs = $attrs:tt,
+        item = $item:tt,
+    ) => {
+        $crate::flatten_structs!{@doc_origins
+            scan = { $($scan)* },
+            groups = $groups,
+            attrs = $attrs,
+            item = $item,
+        }
+    };
+    (@doc_origins
+        scan = {},
+        groups = $groups:tt,
+        attrs = $attrs:tt,
+        item = $item:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_item
+            attrs = $attrs,
+            item = $item,
+        }
+    };
+    (@doc_origins_add
+        groups = { $({
+            generate = $generate:tt,
+            flattened = {
+                attrs = $flattened_attrs:tt,
+                vis = $flattened_vis:tt,
+                name = $flattened_name:ident,
+                type = { $($flattened_type:tt)* },
+            },
+            fields = $fields:tt,
+        })+ },
+        attrs = { $($attrs:tt)* },
+        item = { $($item:tt)* },
+    ) => {
+        $crate::flatten_structs!{@struct_item
+            attrs = {
+                $($attrs)*
+                #[doc = ""]
+                #[doc = "Flattened from:"]
+                $(#[doc = ::core::concat!("- `", ::core::stringify!($($flattened_type)*), "`")])+
+            },
+            item = { $($item)* },
+        }
+    };
+    (@doc_origins_add
+        groups = {},
+        attrs = $attrs:tt,
+        item = $item:tt,
+    ) => {
+        $crate::flatten_structs!{@struct_item
+            attrs = $attrs,
+            item = $item,
+        }
+    };
+    // The struct is declared with all gathered fields, with
+    // `#[flatten(postprocess = macro_name)]` the macro is called with them
+    // instead and declares the struct itself
+    (@struct_item
+        attrs = { $($attrs:tt)* },
+        item = {
+            postprocess = {},
+            vis = { $vis:vis },
+            name = $name:ident,
+            generics = { $($generics:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            fields = {$(
+                $(#[$($field_attr:tt)*])*
+                $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?,
+            )*},
+        },
+    ) => {
+        $($attrs)*
+        $vis struct $name <$($generics)*> $($where_clause)* {$(
+            $(#[$($field_attr)*])*
+            $field_vis $field_name: $field_type,
+        )*}
+    };
+    (@struct_item
+        attrs = $attrs:tt,
+        item = {
+            postprocess = { $($postprocess:tt)+ },
+            $($item:tt)*
+        },
+    ) => {
+        $($postprocess)+!{
+            attrs = $attrs,
+            $($item)*
+        }
+    };
+    // Groups only declare the inspection macro, the struct and the code
+    // generated for it are skipped
+    (@struct_items
+        kind = { struct },
+        items = { $($items:tt)* },
+    ) => {
+        $($items)*
+    };
+    (@struct_items
+        kind = { group },
+        items = $items:tt,
+    ) => {};
+    // Enums aren't configured by this macro, but
+    // `#[flatten(postprocess = macro_name)]` gets a clearer error than the
+    // unknown attribute
+    (@enum_attrs
+        attrs = { #[flatten(postprocess = $($postprocess:ident)::+)] $($attrs:tt)* },
+        name = $name:ident,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten(postprocess = ...)]` needs a struct with named fields, `",
+            ::core::stringify!($name),
+            "` is an enum",
+        ));
+    };
+    (@enum_attrs
+        attrs = { #[$($attr:tt)*] $($attrs:tt)* },
+        name = $name:ident,
+    ) => {
+        $crate::flatten_structs!{@enum_attrs
+            attrs = { $($attrs)* },
+            name = $name,
+        }
+    };
+    (@enum_attrs
+        attrs = {},
+        name = $name:ident,
+    ) => {};
+    // The variants of an enum are handled one at a time, the fields of
+    // struct-like variants are gathered like the fields of a struct
+    (@enum_variants
+        attrs = { $(#[$enum_attr:meta])* },
+        vis = { $vis:vis },
+        name = $name:ident,
+        variants = { $($variants:tt)* },
+        queued_variants = {},
+    ) => {
+        $(#[$enum_attr])*
+        $vis enum $name {
+            $($variants)*
+        }
+    };
+    (@enum_variants
+        attrs = $attrs:tt,
+        vis = $vis:tt,
+        name = $name:ident,
+        variants = $variants:tt,
+        queued_variants = {
+            $(#[$($variant_attr:tt)*])*
+            $variant:ident { $($fields:tt)* }
+            $(, $($queued_variants:tt)*)?
+        },
+    ) => {
+        $crate::flatten_structs!{@gather_fields
+            expanded_fields = {},
+            queued_fields = { $($fields)* },
+            cx = {
+                definition = {
+                    variant = {
+                        attrs = { $(#[$($variant_attr)*])* },
+                        name = $variant,
+                        enum = {
+                            attrs = $attrs,
+                            vis = $vis,
+                            name = $name,
+                            variants = $variants,
+                            queued_variants = { $($($queued_variants)*)? },
+                        },
+                    },
+                },
+                groups = {},
+                own_fields = {},
+                duplicates = { error },
+                dollar = { $ },
+            },
+        }
+    };
+    // Tuple and unit variants are kept as they are
+    (@enum_variants
+        attrs = $attrs:tt,
+        vis = $vis:tt,
+        name = $name:ident,
+        variants = { $($variants:tt)* },
+        queued_variants = {
+            $(#[$($variant_attr:tt)*])*
+            $variant:ident $(( $($variant_fields:tt)* ))? $(= $discriminant:expr)?
+            $(, $($queued_variants:tt)*)?
+        },
+    ) => {
+        $crate::flatten_structs!{@enum_variants
+            attrs = $attrs,
+            vis = $vis,
+            name = $name,
+            variants = { $($variants)*
+                $(#[$($variant_attr)*])*
+                $variant $(( $($variant_fields)* ))? $(= $discriminant)?,
+            },
+            queued_variants = { $($($queued_variants)*)? },
+        }
+    };
+    // `#[flatten_mod(...)]` declares the inspection macro of the struct in a
+    // module, the struct itself stays where it was declared. Nothing is
+    // declared with `#[flatten(no_inspect)]`
+    (@module
+        inspect = {},
+        module = $module:tt,
+        export = $export:tt,
+        items = $items:tt,
+    ) => {};
+    (@module
+        inspect = $inspect:tt,
+        module = {},
+        export = $export:tt,
+        items = { $($items:tt)* },
+    ) => {
+        $($items)*
+    };
+    (@module
+        inspect = $inspect:tt,
+        module = { $module:ident },
+        export = { $export:vis },
+        items = { $($items:tt)* },
+    ) => {
+        $export mod $module {
+            #[allow(unused_imports)]
+            use super::*;
+
+            $($items)*
+        }
+    };
+    // The structs declared with `#[flatten_as(...)]` only get the fields and
+    // the code of their own `flatten_derive`s, the rest of the generated code
+    // is only for the declared struct
+    (@copies
+        copies = {},
+        vis = $vis:tt,
+        generics = $generics:tt,
+        where_clause = $where_clause:tt,
+        body = $body:tt,
+        fields = $fields:tt,
+        container = $container:tt,
+    ) => {};
+    // Tuple structs don't support `flatten_derive`
+    (@copies
+        copies = { {
+            name = $copy_name:ident,
+            attrs = { $(#[$copy_attr:meta])* },
+            rename_all = $_copy_rename_all:tt,
+            derives = $_copy_derives:tt,
+        } $($copies:tt)* },
+        vis = { $vis:vis },
+        generics = { $($generics:tt)* },
+        where_clause = { $($where_clause:tt)* },
+        body = { $($body:tt)* },
+        fields = {},
+        container = {},
+    ) => {
+        $(#[$copy_attr])*
+        $vis struct $copy_name <$($generics)*> $($where_clause)* $($body)*
+        $crate::flatten_structs!{@copies
+            copies = { $($copies)* },
+            vis = { $vis },
+            generics = { $($generics)* },
+            where_clause = { $($where_clause)* },
+            body = { $($body)* },
+            fields = {},
+            container = {},
+        }
+    };
+    (@copies
+        copies = { {
+            name = $copy_name:ident,
+            attrs = { $(#[$copy_attr:meta])* },
+            rename_all = $copy_rename_all:tt,
+            derives = { $($copy_derives:tt)* },
+        } $($copies:tt)* },
+        vis = { $vis:vis },
+        generics = { $($generics:tt)* },
+        where_clause = { $($where_clause:tt)* },
+        body = { $($body:tt)* },
+        fields = $fields:tt,
+        container = {
+            impl_generics = $impl_generics:tt,
+            params = $params:tt,
+            where_clause = $impl_where_clause:tt,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            serde_fields = $serde_fields:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $(#[$copy_attr])*
+        $vis struct $copy_name <$($generics)*> $($where_clause)* $($body)*
+        $crate::flatten_structs!{@derives
+            derives = { $($copy_derives)* },
+            fields = $fields,
+            container = {
+                vis = { $vis },
+                generics = { $($generics)* },
+                impl_generics = $impl_generics,
+                params = $params,
+                where_clause = { $($where_clause)* },
+                name = $copy_name,
+                groups = $groups,
+                own_fields = $own_fields,
+                dollar = $dollar,
+            },
+        }
+        $crate::flatten_structs!{@try_from_map
+            scan = { $($copy_derives)* },
+            fields = $serde_fields,
+            container = {
+                rename_all = $copy_rename_all,
+                impl_generics = $impl_generics,
+                params = $params,
+                where_clause = $impl_where_clause,
+                name = $copy_name,
+            },
+        }
+        $crate::flatten_structs!{@copies
+            copies = { $($copies)* },
+            vis = { $vis },
+            generics = { $($generics)* },
+            where_clause = { $($where_clause)* },
+            body = { $($body)* },
+            fields = $fields,
+            container = {
+                impl_generics = $impl_generics,
+                params = $params,
+                where_clause = $impl_where_clause,
+                groups = $groups,
+                own_fields = $own_fields,
+                serde_fields = $serde_fields,
+                dollar = $dollar,
+            },
+        }
+    };
+    // Fields declared with a default value, e.g. `retries: u32 = 3`, make the
+    // struct implement `Default`, the other fields use their `Default`
+    (@default
+        defaults = {},
+        fields = $fields:tt,
+        container = $container:tt,
+    ) => {};
+    (@default
+        defaults = $defaults:tt,
+        fields = { $({ $field_name:ident $(= $field_default:expr)? })* },
+        container = {
+            generics = { $($generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+        },
+    ) => {
+        #[allow(deprecated)]
+        #[automatically_derived]
+        impl<$($generics)*> ::core::default::Default for $name<$($params)*> $($where_clause)* {
+            fn default() -> Self {
+                Self {$(
+                    $field_name: $crate::flatten_structs!{@default_value $($field_default)?},
+                )*}
+            }
+        }
+    };
+    (@default_value $field_default:expr) => {
+        $field_default
+    };
+    (@default_value) => {
+        ::core::default::Default::default()
+    };
+    // Generate the code for `#[flatten_derive(...)]`
+    (@derives
+        derives = {},
+        fields = $fields:tt,
+        container = $container:tt,
+    ) => {};
+    // `default` implements `Default` with the `Default` of every field, structs
+    // with default values implement it already
+    (@derives
+        derives = { default $($derives:tt)* },
+        fields = { $({ $field_name:ident: $field_type:ty })* },
+        container = {
+            vis = $vis:tt,
+            generics = $generics:tt,
+            impl_generics = $impl_generics:tt,
+            params = $params:tt,
+            where_clause = $where_clause:tt,
+            name = $name:ident,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@default
+            defaults = { default },
+            fields = { $({ $field_name })* },
+            container = {
+                generics = $impl_generics,
+                params = $params,
+                where_clause = $where_clause,
+                name = $name,
+            },
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = { $({ $field_name: $field_type })* },
+            container = {
+                vis = $vis,
+                generics = $generics,
+                impl_generics = $impl_generics,
+                params = $params,
+                where_clause = $where_clause,
+                name = $name,
+                groups = $groups,
+                own_fields = $own_fields,
+                dollar = $dollar,
+            },
+        }
+    };
+    (@derives
+        derives = { default $($derives:tt)* },
+        fields = $fields:tt,
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = $fields,
+            container = $container,
+        }
+    };
+    // `builder` generates a builder with a `with_<field>()` method per field
+    (@derives
+        derives = { builder $($derives:tt)* },
+        fields = { $({ $field_name:ident: $field_type:ty $(= $field_default:expr)? })* },
+        container = {
+            vis = { $vis:vis },
+            generics = { $($generics:tt)* },
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::__private_codegen_paste!{
+            #[doc = "Builder for [`" $name "`], every field without a default value needs to be set before building it."]
+            $vis struct [<$name Builder>]<$($generics)*> $($where_clause)* {
+                $($field_name: ::core::option::Option<$field_type>,)*
+            }
+            #[automatically_derived]
+            impl<$($impl_generics)*> ::core::default::Default
+                for [<$name Builder>]<$($params)*> $($where_clause)*
+            {
+                fn default() -> Self {
+                    Self {
+                        $($field_name: $crate::flatten_structs!{@builder_default
+                            $($field_default)?
+                        },)*
+                    }
+                }
+            }
+            // Not every field needs to be set through the builder
+            #[allow(dead_code, deprecated, single_use_lifetimes)]
+            impl<$($impl_generics)*> [<$name Builder>]<$($params)*> $($where_clause)* {
+                $(
+                    #[doc = "Sets `" $field_name "`."]
+                    $vis fn [<with_ $field_name>](mut self, value: $field_type) -> Self {
+                        self.$field_name = ::core::option::Option::Some(value);
+                        self
+                    }
+                )*
+                #[doc = "Builds the [`" $name "`], fails with the first field that wasn't set."]
+                $vis fn build(self) -> ::core::result::Result<$name<$($params)*>, $crate::MissingField> {
+                    ::core::result::Result::Ok($name {$(
+                        $field_name: match self.$field_name {
+                            ::core::option::Option::Some(value) => value,
+                            ::core::option::Option::None => {
+                                return ::core::result::Result::Err($crate::MissingField {
+                                    field: $crate::__private_unraw(::core::stringify!($field_name)),
+                                });
+                            }
+                        },
+                    )*})
+                }
+            }
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = { $({ $field_name: $field_type $(= $field_default)? })* },
+            container = {
+                vis = { $vis },
+                generics = { $($generics)* },
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                groups = $groups,
+                own_fields = $own_fields,
+                dollar = $dollar,
+            },
+        }
+    };
+    // Builders start out with the default values, so only the fields without
+    // one need to be set
+    (@builder_default $field_default:expr) => {
+        ::core::option::Option::Some($field_default)
+    };
+    (@builder_default) => {
+        ::core::option::Option::None
+    };
+    // `patch` generates a struct with every field optional that is applied to
+    // the struct, fields that are `Option`s already are nested in another one
+    (@derives
+        derives = { patch $($derives:tt)* },
+        fields = { $({ $field_name:ident: $field_type:ty $(= $field_default:expr)? })* },
+        container = {
+            vis = { $vis:vis },
+            generics = { $($generics:tt)* },
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::__private_codegen_paste!{
+            #[doc = "Patch for [`" $name "`], only the fields that are set are applied."]
+            $vis struct [<$name Patch>]<$($generics)*> $($where_clause)* {
+                $($vis $field_name: ::core::option::Option<$field_type>,)*
+            }
+            #[automatically_derived]
+            impl<$($impl_generics)*> ::core::default::Default
+                for [<$name Patch>]<$($params)*> $($where_clause)*
+            {
+                fn default() -> Self {
+                    Self {
+                        $($field_name: ::core::option::Option::None,)*
+                    }
+                }
+            }
+            #[allow(dead_code, deprecated, single_use_lifetimes)]
+            impl<$($impl_generics)*> $name<$($params)*> $($where_clause)* {
+                #[doc = "Overwrites the fields that are set in the [`" $name "Patch`]."]
+                $vis fn apply(&mut self, patch: [<$name Patch>]<$($params)*>) {
+                    $(
+                        if let ::core::option::Option::Some(value) = patch.$field_name {
+                            self.$field_name = value;
+                        }
+                    )*
+                }
+            }
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = { $({ $field_name: $field_type $(= $field_default)? })* },
+            container = {
+                vis = { $vis },
+                generics = { $($generics)* },
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                groups = $groups,
+                own_fields = $own_fields,
+                dollar = $dollar,
+            },
+        }
+    };
+    // `display` implements `Display` listing every field with its value
+    (@derives
+        derives = { display $($derives:tt)* },
+        fields = { $({ $field_name:ident: $field_type:ty $(= $field_default:expr)? })* },
+        container = {
+            vis = $vis:tt,
+            generics = $generics:tt,
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        #[allow(deprecated)]
+        #[automatically_derived]
+        impl<$($impl_generics)*> ::core::fmt::Display for $name<$($params)*> $($where_clause)* {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                let fields: &[(&str, &dyn ::core::fmt::Display)] = &[$(
+                    ($crate::__private_unraw(::core::stringify!($field_name)), &self.$field_name),
+                )*];
+                for (i, (field_name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    ::core::write!(f, "{} = {}", field_name, value)?;
+                }
+                ::core::result::Result::Ok(())
+            }
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = { $({ $field_name: $field_type $(= $field_default)? })* },
+            container = {
+                vis = $vis,
+                generics = $generics,
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                groups = $groups,
+                own_fields = $own_fields,
+                dollar = $dollar,
+            },
+        }
+    };
+    // `accessors` generates a getter and a setter for every field
+    (@derives
+        derives = { accessors $($derives:tt)* },
+        fields = { $({ $field_name:ident: $field_type:ty $(= $field_default:expr)? })* },
+        container = {
+            vis = { $vis:vis },
+            generics = { $($generics:tt)* },
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::__private_codegen_paste!{
+            // Not every field needs both accessors
+            #[allow(dead_code, deprecated, single_use_lifetimes)]
+            impl<$($impl_generics)*> $name<$($params)*> $($where_clause)* {
+                $(
+                    #[doc = "Returns `" $field_name "`."]
+                    $vis fn $field_name(&self) -> &$field_type {
+                        &self.$field_name
+                    }
+                    #[doc = "Sets `" $field_name "`."]
+                    $vis fn [<set_ $field_name>](&mut self, value: $field_type) {
+                        self.$field_name = value;
+                    }
+                )*
+            }
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = { $({ $field_name: $field_type $(= $field_default)? })* },
+            container = {
+                vis = { $vis },
+                generics = { $($generics)* },
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                groups = $groups,
+                own_fields = $own_fields,
+                dollar = $dollar,
+            },
+        }
+    };
+    // `split` converts the struct into a tuple of its flattened types, which
+    // needs all of its fields to be inlined from them
+    (@derives
+        derives = { split $($derives:tt)* },
+        fields = { $({ $all_field_name:ident: $all_field_type:ty $(= $all_field_default:expr)? })* },
+        container = {
+            vis = $vis:tt,
+            generics = $generics:tt,
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            groups = { $({
+                generate = $generate:tt,
+                flattened = {
+                    attrs = $flattened_attrs:tt,
+                    vis = $flattened_vis:tt,
+                    name = $flattened_name:ident,
+                    type = { $($flattened_type:tt)* },
+                },
+                fields = { $({ $source:ident => $field_name:ident: $field_type:ty })* },
+            })* },
+            own_fields = $own_fields:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $($crate::flatten_structs!{@filtered
+            generate = $generate,
+            filtered = {},
+            needs_all = { split },
+        })*
+        #[allow(unused_variables)]
+        const _: () = {
+            let field_names: &[&str] = &[$($(::core::stringify!($field_name),)*)*];
+            $(
+                if $crate::__private_count_field(field_names, ::core::stringify!($all_field_name)) == 0 {
+                    ::core::panic!(::core::concat!(
+                        "field `",
+                        ::core::stringify!($all_field_name),
+                        "` of `",
+                        ::core::stringify!($name),
+                        "` is not inlined from a flattened field, ",
+                        "`#[flatten_derive(split)]` needs all fields to be inlined from flattened fields",
+                    ));
+                }
+            )*
+        };
+        #[allow(deprecated, unused_variables, clippy::unused_unit)]
+        #[automatically_derived]
+        impl<$($impl_generics)*> ::core::convert::From<$name<$($params)*>> for ($($($flattened_type)*,)*)
+        $($where_clause)*
+        {
+            fn from(value: $name<$($params)*>) -> Self {
+                ($($($flattened_type)* {$(
+                    $source: value.$field_name,
+                )*},)*)
+            }
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = { $({ $all_field_name: $all_field_type $(= $all_field_default)? })* },
+            container = {
+                vis = $vis,
+                generics = $generics,
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                groups = { $({
+                    generate = $generate,
+                    flattened = {
+                        attrs = $flattened_attrs,
+                        vis = $flattened_vis,
+                        name = $flattened_name,
+                        type = { $($flattened_type)* },
+                    },
+                    fields = { $({ $source => $field_name: $field_type })* },
+                })* },
+                own_fields = $own_fields,
+                dollar = $dollar,
+            },
+        }
+    };
+    // `from_parts` creates the struct from its flattened types and its other
+    // fields, and from a tuple of the flattened types if there are no others
+    (@derives
+        derives = { from_parts $($derives:tt)* },
+        fields = $fields:tt,
+        container = {
+            vis = { $vis:vis },
+            generics = $generics:tt,
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            groups = { $({
+                generate = $generate:tt,
+                flattened = {
+                    attrs = $flattened_attrs:tt,
+                    vis = $flattened_vis:tt,
+                    name = $flattened_name:ident,
+                    type = { $($flattened_type:tt)* },
+                },
+                fields = { $({ $source:ident => $field_name:ident: $field_type:ty })* },
+            })* },
+            own_fields = { $({ $own_field_name:ident: $own_field_type:ty })* },
+            dollar = $dollar:tt,
+        },
+    ) => {
+        #[allow(deprecated, single_use_lifetimes)]
+        impl<$($impl_generics)*> $name<$($params)*> $($where_clause)* {
+            /// Creates the struct from the values of its flattened fields,
+            /// followed by its other fields.
+            #[allow(dead_code, unused_variables, clippy::too_many_arguments)]
+            $vis fn from_parts(
+                $($flattened_name: $($flattened_type)*,)*
+                $($own_field_name: $own_field_type,)*
+            ) -> Self {
+                Self {
+                    $($($field_name: $flattened_name.$source,)*)*
+                    $($own_field_name,)*
+                }
+            }
+        }
+        $crate::flatten_structs!{@from_tuple
+            own_fields = { $($own_field_name)* },
+            flattened = { $({ $flattened_name: $($flattened_type)* })* },
+            container = {
+                generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+            },
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = $fields,
+            container = {
+                vis = { $vis },
+                generics = $generics,
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                groups = { $({
+                    generate = $generate,
+                    flattened = {
+                        attrs = $flattened_attrs,
+                        vis = $flattened_vis,
+                        name = $flattened_name,
+                        type = { $($flattened_type)* },
+                    },
+                    fields = { $({ $source => $field_name: $field_type })* },
+                })* },
+                own_fields = { $({ $own_field_name: $own_field_type })* },
+                dollar = $dollar,
+            },
+        }
+    };
+    // `eq_ignoring(...)` generates an `eq_ignoring_volatile()` method comparing
+    // all fields except the listed ones
+    (@derives
+        derives = { eq_ignoring($($ignored:ident),* $(,)?) $($derives:tt)* },
+        fields = { $({ $field_name:ident: $field_type:ty $(= $field_default:expr)? })* },
+        container = {
+            vis = { $vis:vis },
+            generics = $generics:tt,
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            dollar = { $dollar:tt },
+        },
+    ) => {
+        #[allow(unused_variables)]
+        const _: () = {
+            let field_names: &[&str] = &[$(::core::stringify!($field_name)),*];
+            $(
+                if $crate::__private_count_field(field_names, ::core::stringify!($ignored)) == 0 {
+                    ::core::panic!(::core::concat!(
+                        "`#[flatten_derive(eq_ignoring(...))]` names the field `",
+                        ::core::stringify!($ignored),
+                        "` which `",
+                        ::core::stringify!($name),
+                        "` doesn't have",
+                    ));
+                }
+            )*
+        };
+        macro_rules! __flatten_structs_eq_ignoring {
+            $((
+                compared = $dollar compared:tt,
+                fields = { $ignored $dollar($dollar fields:ident)* },
+            ) => {
+                __flatten_structs_eq_ignoring!{
+                    compared = $dollar compared,
+                    fields = { $dollar($dollar fields)* },
+                }
+            };)*
+            (
+                compared = { $dollar($dollar compared:ident)* },
+                fields = { $dollar field:ident $dollar($dollar fields:ident)* },
+            ) => {
+                __flatten_structs_eq_ignoring!{
+                    compared = { $dollar($dollar compared)* $dollar field },
+                    fields = { $dollar($dollar fields)* },
+                }
+            };
+            (
+                compared = { $dollar($dollar compared:ident)* },
+                fields = {},
+            ) => {
+                #[allow(deprecated, single_use_lifetimes)]
+                impl<$($impl_generics)*> $name<$($params)*> $($where_clause)* {
+                    /// Compares all fields except the ignored ones.
+                    #[allow(dead_code, unused_variables)]
+                    $vis fn eq_ignoring_volatile(&self, other: &Self) -> bool {
+                        true $dollar(&& self.$dollar compared == other.$dollar compared)*
+                    }
+                }
+            };
+        }
+        __flatten_structs_eq_ignoring!{
+            compared = {},
+            fields = { $($field_name)* },
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = { $({ $field_name: $field_type $(= $field_default)? })* },
+            container = {
+                vis = { $vis },
+                generics = $generics,
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                groups = $groups,
+                own_fields = $own_fields,
+                dollar = { $dollar },
+            },
+        }
+    };
+    // `const_new` generates a `const fn new()` taking every field in order
+    (@derives
+        derives = { const_new $($derives:tt)* },
+        fields = { $({ $field_name:ident: $field_type:ty $(= $field_default:expr)? })* },
+        container = {
+            vis = { $vis:vis },
+            generics = $generics:tt,
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        #[allow(deprecated, single_use_lifetimes)]
+        impl<$($impl_generics)*> $name<$($params)*> $($where_clause)* {
+            /// Creates the struct from the values of all fields, including the
+            /// inlined ones, in the order they are declared.
+            #[allow(dead_code, clippy::too_many_arguments)]
+            $vis const fn new($($field_name: $field_type),*) -> Self {
+                Self { $($field_name),* }
+            }
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = { $({ $field_name: $field_type $(= $field_default)? })* },
+            container = {
+                vis = { $vis },
+                generics = $generics,
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                groups = $groups,
+                own_fields = $own_fields,
+                dollar = $dollar,
+            },
+        }
+    };
+    // `metadata` generates a `field_metadata()` function listing the names and
+    // types of all fields
+    (@derives
+        derives = { metadata $($derives:tt)* },
+        fields = { $({ $field_name:ident: $field_type:ty $(= $field_default:expr)? })* },
+        container = {
+            vis = { $vis:vis },
+            generics = $generics:tt,
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        #[allow(deprecated, single_use_lifetimes)]
+        impl<$($impl_generics)*> $name<$($params)*> $($where_clause)* {
+            /// The names of all fields, including the inlined ones, with their
+            /// types as written.
+            #[allow(dead_code)]
+            $vis fn field_metadata() -> &'static [(&'static str, &'static str)] {
+                const FIELD_METADATA: &[(&str, &str)] = &[$((
+                    $crate::__private_unraw(::core::stringify!($field_name)),
+                    ::core::stringify!($field_type),
+                )),*];
+                FIELD_METADATA
+            }
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = { $({ $field_name: $field_type $(= $field_default)? })* },
+            container = {
+                vis = { $vis },
+                generics = $generics,
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                groups = $groups,
+                own_fields = $own_fields,
+                dollar = $dollar,
+            },
+        }
+    };
+    // `layout` generates a `LAYOUT` constant with the offset and size of every
+    // field
+    (@derives
+        derives = { layout $($derives:tt)* },
+        fields = { $({ $field_name:ident: $field_type:ty $(= $field_default:expr)? })* },
+        container = {
+            vis = { $vis:vis },
+            generics = $generics:tt,
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            dollar = $dollar:tt,
+        },
+    ) => {
+        $crate::__private_layout!{
+            #[allow(deprecated, single_use_lifetimes)]
+            impl<$($impl_generics)*> $name<$($params)*> $($where_clause)* {
+                /// The size and alignment of the struct and the offset and size
+                /// of all fields, including the inlined ones.
+                #[allow(dead_code)]
+                $vis const LAYOUT: $crate::Layout = $crate::Layout {
+                    name: ::core::stringify!($name),
+                    size: ::core::mem::size_of::<Self>(),
+                    align: ::core::mem::align_of::<Self>(),
+                    fields: &[$($crate::FieldLayout {
+                        name: $crate::__private_unraw(::core::stringify!($field_name)),
+                        offset: ::core::mem::offset_of!(Self, $field_name),
+                        size: ::core::mem::size_of::<$field_type>(),
+                    }),*],
+                };
+            }
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = { $({ $field_name: $field_type $(= $field_default)? })* },
+            container = {
+                vis = { $vis },
+                generics = $generics,
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                groups = $groups,
+                own_fields = $own_fields,
+                dollar = $dollar,
+            },
+        }
+    };
+    // `try_from_map` is generated along with the struct, which knows the
+    // serde attributes of the fields
+    (@derives
+        derives = { try_from_map $($derives:tt)* },
+        fields = $fields:tt,
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = $fields,
+            container = $container,
+        }
+    };
+    // `try_from_map` deserializes every field from a `serde_json` map under
+    // the key serde would use for it, see `@try_from_map_key`. The fields are
+    // collected as `Option`s first to report all errors at once.
+    (@try_from_map
+        scan = { try_from_map $($_scan:tt)* },
+        fields = { $({
+            attrs = $attrs:tt,
+            name = $field_name:ident,
+            type = { $field_type:ty },
+            default = { $($field_default:expr)? },
+        })* },
+        container = {
+            rename_all = $rename_all:tt,
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $(where $($where_bounds:tt)*)? },
+            name = $name:ident,
+        },
+    ) => {
+        $crate::__private_serde_json!{
+            #[allow(deprecated)]
+            #[automatically_derived]
+            impl<$($impl_generics)*>
+                ::core::convert::TryFrom<
+                    $crate::__private_serde_json::Map<
+                        $crate::__PrivateString,
+                        $crate::__private_serde_json::Value,
+                    >,
+                > for $name<$($params)*>
+            where
+                $($field_type: $crate::__private_serde_dep::de::DeserializeOwned,)*
+                $($($where_bounds)*)?
+            {
+                type Error = $crate::MapError;
+
+                #[allow(unreachable_patterns)]
+                fn try_from(
+                    mut map: $crate::__private_serde_json::Map<
+                        $crate::__PrivateString,
+                        $crate::__private_serde_json::Value,
+                    >,
+                ) -> ::core::result::Result<Self, Self::Error> {
+                    let mut error = <$crate::MapError as ::core::default::Default>::default();
+                    $(
+                        let key: &'static str = $crate::flatten_structs!{@try_from_map_key
+                            attrs = $attrs,
+                            args = {},
+                            key = {},
+                            rename_all = $rename_all,
+                            name = $field_name,
+                        };
+                        let $field_name = match map.remove(key) {
+                            ::core::option::Option::Some(value) => {
+                                match $crate::__private_serde_json::from_value::<$field_type>(value) {
+                                    ::core::result::Result::Ok(value) => {
+                                        ::core::option::Option::Some(value)
+                                    }
+                                    ::core::result::Result::Err(value_error) => {
+                                        error.invalid.push((key, value_error));
+                                        ::core::option::Option::None
+                                    }
+                                }
+                            }
+                            ::core::option::Option::None => {
+                                $crate::flatten_structs!{@try_from_map_missing
+                                    error = error,
+                                    key = key,
+                                    $(default = $field_default)?
+                                }
+                            }
+                        };
+                    )*
+                    error.unknown.extend(map.into_iter().map(|(key, _)| key));
+                    match ($($field_name,)*) {
+                        ($(::core::option::Option::Some($field_name),)*) if error.is_empty() => {
+                            ::core::result::Result::Ok(Self { $($field_name),* })
+                        }
+                        _ => ::core::result::Result::Err(error),
+                    }
+                }
+            }
+        }
+    };
+    (@try_from_map
+        scan = { $_derive:tt $($scan:tt)* },
+        fields = $fields:tt,
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@try_from_map
+            scan = { $($scan)* },
+            fields = $fields,
+            container = $container,
+        }
+    };
+    (@try_from_map
+        scan = {},
+        fields = $fields:tt,
+        container = $container:tt,
+    ) => {};
+    // The key of a field is the last `rename = "..."` in its `serde`
+    // attributes like in serde, otherwise its name in the case of the
+    // `rename_all` of the struct. Each key is looked up on its own, so the
+    // recursion depth only depends on the attributes of that field.
+    (@try_from_map_key
+        attrs = $attrs:tt,
+        args = { rename = $key:literal $($args:tt)* },
+        key = $_key:tt,
+        rename_all = $rename_all:tt,
+        name = $field_name:ident,
+    ) => {
+        $crate::flatten_structs!{@try_from_map_key
+            attrs = $attrs,
+            args = { $($args)* },
+            key = { $key },
+            rename_all = $rename_all,
+            name = $field_name,
+        }
+    };
+    (@try_from_map_key
+        attrs = $attrs:tt,
+        args = { $_arg:tt $($args:tt)* },
+        key = $key:tt,
+        rename_all = $rename_all:tt,
+        name = $field_name:ident,
+    ) => {
+        $crate::flatten_structs!{@try_from_map_key
+            attrs = $attrs,
+            args = { $($args)* },
+            key = $key,
+            rename_all = $rename_all,
+            name = $field_name,
+        }
+    };
+    (@try_from_map_key
+        attrs = { #[serde($($args:tt)*)] $($attrs:tt)* },
+        args = {},
+        key = $key:tt,
+        rename_all = $rename_all:tt,
+        name = $field_name:ident,
+    ) => {
+        $crate::flatten_structs!{@try_from_map_key
+            attrs = { $($attrs)* },
+            args = { $($args)* },
+            key = $key,
+            rename_all = $rename_all,
+            name = $field_name,
+        }
+    };
+    // `field_attr(...)` adds its attributes with `cfg_attr(all(), ...)`
+    (@try_from_map_key
+        attrs = { #[cfg_attr(all(), serde($($args:tt)*))] $($attrs:tt)* },
+        args = {},
+        key = $key:tt,
+        rename_all = $rename_all:tt,
+        name = $field_name:ident,
+    ) => {
+        $crate::flatten_structs!{@try_from_map_key
+            attrs = { $($attrs)* },
+            args = { $($args)* },
+            key = $key,
+            rename_all = $rename_all,
+            name = $field_name,
+        }
+    };
+    (@try_from_map_key
+        attrs = { #[$($_attr:tt)*] $($attrs:tt)* },
+        args = {},
+        key = $key:tt,
+        rename_all = $rename_all:tt,
+        name = $field_name:ident,
+    ) => {
+        $crate::flatten_structs!{@try_from_map_key
+            attrs = { $($attrs)* },
+            args = {},
+            key = $key,
+            rename_all = $rename_all,
+            name = $field_name,
+        }
+    };
+    (@try_from_map_key
+        attrs = {},
+        args = {},
+        key = { $key:literal },
+        rename_all = $_rename_all:tt,
+        name = $_field_name:ident,
+    ) => {
+        $key
+    };
+    (@try_from_map_key
+        attrs = {},
+        args = {},
+        key = {},
+        rename_all = { $rename_all:literal },
+        name = $field_name:ident,
+    ) => {{
+        const NAME: &str = $crate::__private_unraw(::core::stringify!($field_name));
+        const LEN: usize = $crate::__private_serde_case_len(NAME, $rename_all);
+        const KEY: [u8; LEN] = $crate::__private_serde_case::<LEN>(NAME, $rename_all);
+        $crate::__private_serde_key(&KEY)
+    }};
+    (@try_from_map_key
+        attrs = {},
+        args = {},
+        key = {},
+        rename_all = {},
+        name = $field_name:ident,
+    ) => {
+        $crate::__private_unraw(::core::stringify!($field_name))
+    };
+    (@try_from_map_missing error = $error:ident, key = $key:ident, default = $field_default:expr) => {
+        ::core::option::Option::Some($field_default)
+    };
+    (@try_from_map_missing error = $error:ident, key = $key:ident,) => {{
+        $error.missing.push($key);
+        ::core::option::Option::None
+    }};
+    // `doc_origins` is handled when emitting the struct
+    (@derives
+        derives = { doc_origins $($derives:tt)* },
+        fields = $fields:tt,
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = $fields,
+            container = $container,
+        }
+    };
+    // `merge` generates a `merge()` method overwriting the fields with the ones
+    // of another value, except for the skipped fields. Whether a field is an
+    // `Option` is left to method resolution, the types can't be matched.
+    (@derives
+        derives = { merge(skip($($skipped:ident),* $(,)?)) $($derives:tt)* },
+        fields = { $({ $field_name:ident: $field_type:ty $(= $field_default:expr)? })* },
+        container = {
+            vis = { $vis:vis },
+            generics = $generics:tt,
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            groups = $groups:tt,
+            own_fields = $own_fields:tt,
+            dollar = { $dollar:tt },
+        },
+    ) => {
+        #[allow(unused_variables)]
+        const _: () = {
+            let field_names: &[&str] = &[$(::core::stringify!($field_name)),*];
+            $(
+                if $crate::__private_count_field(field_names, ::core::stringify!($skipped)) == 0 {
+                    ::core::panic!(::core::concat!(
+                        "`#[flatten_derive(merge(skip(...)))]` names the field `",
+                        ::core::stringify!($skipped),
+                        "` which `",
+                        ::core::stringify!($name),
+                        "` doesn't have",
+                    ));
+                }
+            )*
+        };
+        macro_rules! __flatten_structs_merge_field {
+            $(($skipped, $dollar this:ident, $dollar other:ident) => {};)*
+            ($dollar field:ident, $dollar this:ident, $dollar other:ident) => {
+                $crate::__PrivateMergeField {
+                    field: &mut $dollar this.$dollar field,
+                    other: $dollar other.$dollar field,
+                }
+                .__private_merge();
+            };
+        }
+        #[allow(deprecated, single_use_lifetimes)]
+        impl<$($impl_generics)*> $name<$($params)*> $($where_clause)* {
+            /// Overwrites the fields with the ones of `other`, `Option`
+            /// fields only if `other` has a value.
+            #[allow(dead_code, unused_variables)]
+            $vis fn merge(&mut self, other: Self) {
+                #[allow(unused_imports)]
+                use $crate::{__PrivateMergeOption as _, __PrivateMergeValue as _};
+                $(__flatten_structs_merge_field!{$field_name, self, other})*
+            }
+        }
+        $crate::flatten_structs!{@derives
+            derives = { $($derives)* },
+            fields = { $({ $field_name: $field_type $(= $field_default)? })* },
+            container = {
+                vis = { $vis },
+                generics = $generics,
+                impl_generics = { $($impl_generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                groups = $groups,
+                own_fields = $own_fields,
+                dollar = { $dollar },
+            },
+        }
+    };
+    (@derives
+        derives = { merge $($derives:tt)* },
+        fields = $fields:tt,
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@derives
+            derives = { merge(skip()) $($derives)* },
+            fields = $fields,
+            container = $container,
+        }
+    };
+    (@derives
+        derives = { $derive:ident $($derives:tt)* },
+        fields = $fields:tt,
+        container = $container:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "unsupported `#[flatten_derive]`: ",
+            ::core::stringify!($derive),
+        ));
+    };
+    (@from_tuple
+        own_fields = {},
+        flattened = { $({ $flattened_name:ident: $($flattened_type:tt)* })* },
+        container = {
+            generics = { $($generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+        },
+    ) => {
+        #[allow(deprecated)]
+        #[automatically_derived]
+        impl<$($generics)*> ::core::convert::From<($($($flattened_type)*,)*)> for $name<$($params)*>
+        $($where_clause)*
+        {
+            fn from(($($flattened_name,)*): ($($($flattened_type)*,)*)) -> Self {
+                Self::from_parts($($flattened_name),*)
+            }
+        }
+    };
+    (@from_tuple
+        own_fields = $own_fields:tt,
+        flattened = $flattened:tt,
+        container = $container:tt,
+    ) => {};
+    // Generate the code for the options collected for each flattened field,
+    // each group is expanded on its own so the recursion depth doesn't grow
+    // with the number of flattened fields
+    (@groups
+        groups = { $($group:tt)* },
+        container = $container:tt,
+    ) => {
+        $($crate::flatten_structs!{@group
+            group = $group,
+            container = $container,
+        })*
+    };
+    (@group
+        group = {
+            generate = {},
+            flattened = $flattened:tt,
+            fields = $fields:tt,
+        },
+        container = $container:tt,
+    ) => {};
+    // `filtered` only marks groups inlined with `skip` or `only`
+    (@group
+        group = {
+            generate = { filtered $($generate:ident)* },
+            flattened = $flattened:tt,
+            fields = $fields:tt,
+        },
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@group
+            group = {
+                generate = { $($generate)* },
+                flattened = $flattened,
+                fields = $fields,
+            },
+            container = $container,
+        }
+    };
+    // `#[flatten(serde_accept_nested)]` is generated by `@accept_nested`
+    (@group
+        group = {
+            generate = { serde_accept_nested $($generate:ident)* },
+            flattened = $flattened:tt,
+            fields = $fields:tt,
+        },
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@group
+            group = {
+                generate = { $($generate)* },
+                flattened = $flattened,
+                fields = $fields,
+            },
+            container = $container,
+        }
+    };
+    // `#[flatten(reconstruct)]` generates a `to_<field>()` method that clones
+    // the inlined fields back into the flattened type
+    (@group
+        group = {
+            generate = { reconstruct $($generate:ident)* },
+            flattened = {
+                attrs = { $($flattened_attr:tt)* },
+                vis = { $flattened_vis:vis },
+                name = $flattened_name:ident,
+                type = { $($flattened_type:tt)* },
+            },
+            fields = { $({ $source:ident => $field_name:ident: $field_type:ty })* },
+        },
+        container = {
+            generics = { $($generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            field_names = $field_names:tt,
+        },
+    ) => {
+        $crate::__private_codegen_paste!{
+            $($flattened_attr)*
+            #[allow(deprecated, single_use_lifetimes)]
+            impl<$($generics)*> $name<$($params)*> $($where_clause)* {
+                #[doc = "Clones the fields inlined from `" $flattened_name "` back into its type."]
+                $flattened_vis fn [<to_ $flattened_name>](&self) -> $($flattened_type)* {
+                    $($flattened_type)* {$(
+                        $source: ::core::clone::Clone::clone(&self.$field_name),
+                    )*}
+                }
+            }
+        }
+        $crate::flatten_structs!{@group
+            group = {
+                generate = { $($generate)* },
+                flattened = {
+                    attrs = { $($flattened_attr)* },
+                    vis = { $flattened_vis },
+                    name = $flattened_name,
+                    type = { $($flattened_type)* },
+                },
+                fields = { $({ $source => $field_name: $field_type })* },
+            },
+            container = {
+                generics = { $($generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                field_names = $field_names,
+            },
+        }
+    };
+    // `#[flatten(into_nested)]` converts a reference to the struct into the
+    // flattened type by cloning the inlined fields
+    (@group
+        group = {
+            generate = { into_nested $($generate:ident)* },
+            flattened = {
+                attrs = { $($flattened_attr:tt)* },
+                vis = $flattened_vis:tt,
+                name = $flattened_name:ident,
+                type = { $($flattened_type:tt)* },
+            },
+            fields = { $({ $source:ident => $field_name:ident: $field_type:ty })* },
+        },
+        container = {
+            generics = { $($generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            field_names = $field_names:tt,
+        },
+    ) => {
+        $($flattened_attr)*
+        #[allow(deprecated)]
+        #[automatically_derived]
+        impl<$($generics)*> ::core::convert::From<&$name<$($params)*>> for $($flattened_type)*
+        $($where_clause)*
+        {
+            fn from(value: &$name<$($params)*>) -> Self {
+                $($flattened_type)* {$(
+                    $source: ::core::clone::Clone::clone(&value.$field_name),
+                )*}
+            }
+        }
+        $crate::flatten_structs!{@group
+            group = {
+                generate = { $($generate)* },
+                flattened = {
+                    attrs = { $($flattened_attr)* },
+                    vis = $flattened_vis,
+                    name = $flattened_name,
+                    type = { $($flattened_type)* },
+                },
+                fields = { $({ $source => $field_name: $field_type })* },
+            },
+            container = {
+                generics = { $($generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                field_names = $field_names,
+            },
+        }
+    };
+    // `#[flatten(view)]` generates a struct with references to the inlined
+    // fields, named after the flattened field, and a method returning it
+    (@group
+        group = {
+            generate = { view $($generate:ident)* },
+            flattened = {
+                attrs = { $($flattened_attr:tt)* },
+                vis = { $flattened_vis:vis },
+                name = $flattened_name:ident,
+                type = { $($flattened_type:tt)* },
+            },
+            fields = { $({ $source:ident => $field_name:ident: $field_type:ty })* },
+        },
+        container = {
+            generics = { $($generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            field_names = $field_names:tt,
+        },
+    ) => {
+        $crate::__private_codegen_paste!{
+            #[doc = "References to the fields of [`" $name "`] inlined from `" $flattened_name "`."]
+            $($flattened_attr)*
+            #[allow(deprecated)]
+            $flattened_vis struct [<$name $flattened_name:camel Ref>]<'view, $($generics)*> $($where_clause)* {
+                $($flattened_vis $source: &'view $field_type,)*
+                _marker: ::core::marker::PhantomData<&'view $name<$($params)*>>,
+            }
+            $($flattened_attr)*
+            #[allow(deprecated, single_use_lifetimes)]
+            impl<$($generics)*> $name<$($params)*> $($where_clause)* {
+                #[doc = "Borrows the fields inlined from `" $flattened_name "`."]
+                $flattened_vis fn $flattened_name(&self) -> [<$name $flattened_name:camel Ref>]<'_, $($params)*> {
+                    [<$name $flattened_name:camel Ref>] {
+                        $($source: &self.$field_name,)*
+                        _marker: ::core::marker::PhantomData,
+                    }
+                }
+            }
+        }
+        $crate::flatten_structs!{@group
+            group = {
+                generate = { $($generate)* },
+                flattened = {
+                    attrs = { $($flattened_attr)* },
+                    vis = { $flattened_vis },
+                    name = $flattened_name,
+                    type = { $($flattened_type)* },
+                },
+                fields = { $({ $source => $field_name: $field_type })* },
+            },
+            container = {
+                generics = { $($generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                field_names = $field_names,
+            },
+        }
+    };
+    // `#[flatten(view_mut)]` generates a struct with mutable references to the
+    // inlined fields and a `<field>_mut()` method returning it
+    (@group
+        group = {
+            generate = { view_mut $($generate:ident)* },
+            flattened = {
+                attrs = { $($flattened_attr:tt)* },
+                vis = { $flattened_vis:vis },
+                name = $flattened_name:ident,
+                type = { $($flattened_type:tt)* },
+            },
+            fields = { $({ $source:ident => $field_name:ident: $field_type:ty })* },
+        },
+        container = {
+            generics = { $($generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            field_names = $field_names:tt,
+        },
+    ) => {
+        $crate::__private_codegen_paste!{
+            #[doc = "Mutable references to the fields of [`" $name "`] inlined from `" $flattened_name "`."]
+            $($flattened_attr)*
+            #[allow(deprecated)]
+            $flattened_vis struct [<$name $flattened_name:camel Mut>]<'view, $($generics)*> $($where_clause)* {
+                $($flattened_vis $source: &'view mut $field_type,)*
+                _marker: ::core::marker::PhantomData<&'view mut $name<$($params)*>>,
+            }
+            $($flattened_attr)*
+            #[allow(deprecated, single_use_lifetimes)]
+            impl<$($generics)*> $name<$($params)*> $($where_clause)* {
+                #[doc = "Borrows the fields inlined from `" $flattened_name "` mutably."]
+                $flattened_vis fn [<$flattened_name _mut>](&mut self) -> [<$name $flattened_name:camel Mut>]<'_, $($params)*> {
+                    [<$name $flattened_name:camel Mut>] {
+                        $($source: &mut self.$field_name,)*
+                        _marker: ::core::marker::PhantomData,
+                    }
+                }
+            }
+        }
+        $crate::flatten_structs!{@group
+            group = {
+                generate = { $($generate)* },
+                flattened = {
+                    attrs = { $($flattened_attr)* },
+                    vis = { $flattened_vis },
+                    name = $flattened_name,
+                    type = { $($flattened_type)* },
+                },
+                fields = { $({ $source => $field_name: $field_type })* },
+            },
+            container = {
+                generics = { $($generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                field_names = $field_names,
+            },
+        }
+    };
+    // `#[flatten(field_count)]` generates a `<FIELD>_FIELD_COUNT` constant
+    // with the number of inlined fields
+    (@group
+        group = {
+            generate = { field_count $($generate:ident)* },
+            flattened = {
+                attrs = { $($flattened_attr:tt)* },
+                vis = $flattened_vis:tt,
+                name = $flattened_name:ident,
+                type = $flattened_type:tt,
+            },
+            fields = { $({ $source:ident => $field_name:ident: $field_type:ty })* },
+        },
+        container = {
+            generics = { $($generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $($where_clause:tt)* },
+            name = $name:ident,
+            field_names = $field_names:tt,
+        },
+    ) => {
+        $crate::__private_codegen_paste!{
+            $($flattened_attr)*
+            #[allow(single_use_lifetimes)]
+            impl<$($generics)*> $name<$($params)*> $($where_clause)* {
+                #[doc = "The number of fields inlined from `" $flattened_name "`."]
+                #[allow(dead_code)]
+                pub const [<$flattened_name:upper _FIELD_COUNT>]: usize = {
+                    let field_names: &[&str] = &[$(::core::stringify!($field_name)),*];
+                    field_names.len()
+                };
+            }
+        }
+        $crate::flatten_structs!{@group
+            group = {
+                generate = { $($generate)* },
+                flattened = {
+                    attrs = { $($flattened_attr)* },
+                    vis = $flattened_vis,
+                    name = $flattened_name,
+                    type = $flattened_type,
+                },
+                fields = { $({ $source => $field_name: $field_type })* },
+            },
+            container = {
+                generics = { $($generics)* },
+                params = { $($params)* },
+                where_clause = { $($where_clause)* },
+                name = $name,
+                field_names = $field_names,
+            },
+        }
+    };
+    // `#[flatten(assert_unique)]` checks that the names of the inlined fields
+    // are only used once when evaluating a constant
+    (@group
+        group = {
+            generate = { assert_unique $($generate:ident)* },
+            flattened = {
+                attrs = { $($flattened_attr:tt)* },
+                vis = $flattened_vis:tt,
+                name = $flattened_name:ident,
+                type = $flattened_type:tt,
+            },
+            fields = { $({ $source:ident => $field_name:ident: $field_type:ty })* },
+        },
+        container = {
+            generics = $generics:tt,
+            params = $params:tt,
+            where_clause = $where_clause:tt,
+            name = $name:ident,
+            field_names = { $($all_field_name:ident)* },
+        },
+    ) => {
+        $($flattened_attr)*
+        const _: () = {
+            let field_names: &[&str] = &[$(::core::stringify!($all_field_name)),*];
+            $(
+                if $crate::__private_count_field(field_names, ::core::stringify!($field_name)) > 1 {
+                    ::core::panic!(::core::concat!(
+                        "field `",
+                        ::core::stringify!($field_name),
+                        "` inlined from `",
+                        ::core::stringify!($flattened_name),
+                        "` is declared more than once in `",
+                        ::core::stringify!($name),
+                        "`",
+                    ));
+                }
+            )*
+        };
+        $crate::flatten_structs!{@group
+            group = {
+                generate = { $($generate)* },
+                flattened = {
+                    attrs = { $($flattened_attr)* },
+                    vis = $flattened_vis,
+                    name = $flattened_name,
+                    type = $flattened_type,
+                },
+                fields = { $({ $source => $field_name: $field_type })* },
+            },
+            container = {
+                generics = $generics,
+                params = $params,
+                where_clause = $where_clause,
+                name = $name,
+                field_names = { $($all_field_name)* },
+            },
+        }
+    };
+    // `#[flatten(serde_accept_nested)]` implements `Deserialize` through an
+    // untagged enum of two helper structs, one with all fields and one with
+    // the fields of the flattened types nested under their field names.
+    // Collect the groups with the option first, it is always the first code
+    // they generate. None of them has it if no group has any options:
+    (@accept_nested
+        scan = { $({
+            generate = {},
+            flattened = $_flattened:tt,
+            fields = $_group_fields:tt,
+        })* },
+        nested = {},
+        fields = $fields:tt,
+        done = $done:tt,
+        container = $container:tt,
+    ) => {};
+    (@accept_nested
+        scan = { {
+            generate = { serde_accept_nested $($_generate:ident)* },
+            flattened = $flattened:tt,
+            fields = $group_fields:tt,
+        } $($scan:tt)* },
+        nested = { $($nested:tt)* },
+        fields = $fields:tt,
+        done = $done:tt,
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@accept_nested
+            scan = { $($scan)* },
+            nested = { $($nested)* { flattened = $flattened, fields = $group_fields, } },
+            fields = $fields,
+            done = $done,
+            container = $container,
+        }
+    };
+    (@accept_nested
+        scan = { {
+            generate = { $($_generate:ident)* },
+            flattened = $_flattened:tt,
+            fields = $_group_fields:tt,
+        } $($scan:tt)* },
+        nested = $nested:tt,
+        fields = $fields:tt,
+        done = $done:tt,
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@accept_nested
+            scan = { $($scan)* },
+            nested = $nested,
+            fields = $fields,
+            done = $done,
+            container = $container,
+        }
+    };
+    (@accept_nested
+        scan = {},
+        nested = {},
+        fields = $fields:tt,
+        done = $done:tt,
+        container = $container:tt,
+    ) => {};
+    // The helper structs only get the `serde` and `cfg` attributes of the
+    // fields, they don't have the other derives of the struct. The `cfg`
+    // attributes are kept apart for constructing the struct.
+    (@accept_nested
+        scan = {},
+        nested = $nested:tt,
+        fields = { {
+            attrs = { #[serde $($serde:tt)*] $($attrs:tt)* },
+            kept = { $($kept:tt)* },
+            cfg = $cfg:tt,
+            name = $field_name:ident,
+            type = $field_type:tt,
+        } $($fields:tt)* },
+        done = $done:tt,
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@accept_nested
+            scan = {},
+            nested = $nested,
+            fields = { {
+                attrs = { $($attrs)* },
+                kept = { $($kept)* #[serde $($serde)*] },
+                cfg = $cfg,
+                name = $field_name,
+                type = $field_type,
+            } $($fields)* },
+            done = $done,
+            container = $container,
+        }
+    };
+    (@accept_nested
+        scan = {},
+        nested = $nested:tt,
+        fields = { {
+            attrs = { #[cfg_attr($predicate:meta, serde $($serde:tt)*)] $($attrs:tt)* },
+            kept = { $($kept:tt)* },
+            cfg = $cfg:tt,
+            name = $field_name:ident,
+            type = $field_type:tt,
+        } $($fields:tt)* },
+        done = $done:tt,
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@accept_nested
+            scan = {},
+            nested = $nested,
+            fields = { {
+                attrs = { $($attrs)* },
+                kept = { $($kept)* #[cfg_attr($predicate, serde $($serde)*)] },
+                cfg = $cfg,
+                name = $field_name,
+                type = $field_type,
+            } $($fields)* },
+            done = $done,
+            container = $container,
+        }
+    };
+    (@accept_nested
+        scan = {},
+        nested = $nested:tt,
+        fields = { {
+            attrs = { #[cfg $($predicate:tt)*] $($attrs:tt)* },
+            kept = { $($kept:tt)* },
+            cfg = { $($cfg:tt)* },
+            name = $field_name:ident,
+            type = $field_type:tt,
+        } $($fields:tt)* },
+        done = $done:tt,
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@accept_nested
+            scan = {},
+            nested = $nested,
+            fields = { {
+                attrs = { $($attrs)* },
+                kept = { $($kept)* #[cfg $($predicate)*] },
+                cfg = { $($cfg)* #[cfg $($predicate)*] },
+                name = $field_name,
+                type = $field_type,
+            } $($fields)* },
+            done = $done,
+            container = $container,
+        }
+    };
+    (@accept_nested
+        scan = {},
+        nested = $nested:tt,
+        fields = { {
+            attrs = { #[$($_attr:tt)*] $($attrs:tt)* },
+            kept = $kept:tt,
+            cfg = $cfg:tt,
+            name = $field_name:ident,
+            type = $field_type:tt,
+        } $($fields:tt)* },
+        done = $done:tt,
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@accept_nested
+            scan = {},
+            nested = $nested,
+            fields = { {
+                attrs = { $($attrs)* },
+                kept = $kept,
+                cfg = $cfg,
+                name = $field_name,
+                type = $field_type,
+            } $($fields)* },
+            done = $done,
+            container = $container,
+        }
+    };
+    (@accept_nested
+        scan = {},
+        nested = $nested:tt,
+        fields = { {
+            attrs = {},
+            kept = $kept:tt,
+            cfg = $cfg:tt,
+            name = $field_name:ident,
+            type = $field_type:tt,
+        } $($fields:tt)* },
+        done = { $($done:tt)* },
+        container = $container:tt,
+    ) => {
+        $crate::flatten_structs!{@accept_nested
+            scan = {},
+            nested = $nested,
+            fields = { $($fields)* },
+            done = { $($done)* {
+                name = $field_name,
+                kept = $kept,
+                cfg = $cfg,
+                type = $field_type,
+            } },
+            container = $container,
+        }
+    };
+    // Emit the helpers once the attributes of all fields are filtered. The
+    // fields inlined from the nested types are told apart by name with a
+    // local macro, which builds the nested helper without them.
+    (@accept_nested
+        scan = {},
+        nested = { $({
+            flattened = {
+                attrs = { $($flattened_attr:tt)* },
+                vis = $_flattened_vis:tt,
+                name = $flattened_name:ident,
+                type = { $($flattened_type:tt)* },
+            },
+            fields = { $({ $source:ident => $nested_field_name:ident: $_nested_field_type:ty })* },
+        })+ },
+        fields = {},
+        done = { $({
+            name = $field_name:ident,
+            kept = { $($kept:tt)* },
+            cfg = { $($cfg:tt)* },
+            type = { $field_type:ty },
+        })* },
+        container = {
+            serde_rename_all = { $($serde_rename_all:tt)? },
+            generics = { $($generics:tt)* },
+            impl_generics = { $($impl_generics:tt)* },
+            params = { $($params:tt)* },
+            where_clause = { $(where $($where_bounds:tt)*)? },
+            name = $name:ident,
+            dollar = { $dollar:tt },
+        },
+    ) => {
+        #[allow(deprecated, non_camel_case_types)]
+        const _: () = {
+            #[derive(::serde::Deserialize)]
+            $(#[serde(rename_all = $serde_rename_all)])?
+            struct __FlattenStructsFlat<$($generics)*> $(where $($where_bounds)*)? {
+                $($($kept)* $field_name: $field_type,)*
+            }
+            macro_rules! __flatten_structs_accept_nested {
+                $($(
+                    (
+                        kept = $dollar kept:tt,
+                        inlined = { $dollar($dollar inlined:tt)* },
+                        fields = { {
+                            name = $nested_field_name,
+                            kept = $dollar _kept:tt,
+                            cfg = $dollar cfg:tt,
+                            type = $dollar _type:tt,
+                        } $dollar($dollar fields:tt)* },
+                    ) => {
+                        __flatten_structs_accept_nested!{
+                            kept = $dollar kept,
+                            inlined = { $dollar($dollar inlined)* {
+                                name = $nested_field_name,
+                                cfg = $dollar cfg,
+                                path = { $flattened_name.$source },
+                            } },
+                            fields = { $dollar($dollar fields)* },
+                        }
+                    };
+                )*)+
+                (
+                    kept = { $dollar($dollar kept:tt)* },
+                    inlined = $dollar inlined:tt,
+                    fields = { $dollar field:tt $dollar($dollar fields:tt)* },
+                ) => {
+                    __flatten_structs_accept_nested!{
+                        kept = { $dollar($dollar kept)* $dollar field },
+                        inlined = $dollar inlined,
+                        fields = { $dollar($dollar fields)* },
+                    }
+                };
+                (
+                    kept = { $dollar({
+                        name = $dollar kept_name:ident,
+                        kept = { $dollar($dollar kept_attr:tt)* },
+                        cfg = { $dollar($dollar kept_cfg:tt)* },
+                        type = { $dollar kept_type:ty },
+                    })* },
+                    inlined = { $dollar({
+                        name = $dollar inlined_name:ident,
+                        cfg = { $dollar($dollar inlined_cfg:tt)* },
+                        path = { $dollar($dollar inlined_path:tt)* },
+                    })* },
+                    fields = {},
+                ) => {
+                    #[derive(::serde::Deserialize)]
+                    $(#[serde(rename_all = $serde_rename_all)])?
+                    struct __FlattenStructsNested<$($generics)*> $(where $($where_bounds)*)? {
+                        $dollar($dollar($dollar kept_attr)* $dollar kept_name: $dollar kept_type,)*
+                        $($($flattened_attr)* $flattened_name: $($flattened_type)*,)+
+                    }
+                    #[derive(::serde::Deserialize)]
+                    #[serde(untagged)]
+                    enum __FlattenStructsShape<$($generics)*> $(where $($where_bounds)*)? {
+                        Flat(__FlattenStructsFlat<$($params)*>),
+                        Nested(__FlattenStructsNested<$($params)*>),
+                    }
+                    #[automatically_derived]
+                    impl<'de, $($impl_generics)*> ::serde::Deserialize<'de> for $name<$($params)*>
+                    where
+                        __FlattenStructsShape<$($params)*>: ::serde::Deserialize<'de>,
+                        $($($where_bounds)*)?
+                    {
+                        fn deserialize<D: ::serde::Deserializer<'de>>(
+                            deserializer: D,
+                        ) -> ::core::result::Result<Self, D::Error> {
+                            let shape = <__FlattenStructsShape<$($params)*> as ::serde::Deserialize<'de>>::deserialize(deserializer)?;
+                            ::core::result::Result::Ok(match shape {
+                                __FlattenStructsShape::Flat(flat) => Self {
+                                    $($($cfg)* $field_name: flat.$field_name,)*
+                                },
+                                __FlattenStructsShape::Nested(nested) => Self {
+                                    $dollar($dollar($dollar kept_cfg)* $dollar kept_name: nested.$dollar kept_name,)*
+                                    $dollar(
+                                        $dollar($dollar inlined_cfg)*
+                                        $dollar inlined_name: nested.$dollar($dollar inlined_path)*,
+                                    )*
+                                },
+                            })
+                        }
+                    }
+                };
+            }
+            __flatten_structs_accept_nested!{
+                kept = {},
+                inlined = {},
+                fields = { $({
+                    name = $field_name,
+                    kept = { $($kept)* },
+                    cfg = { $($cfg)* },
+                    type = { $field_type },
+                })* },
+            }
+        };
+    };
+    // Generic types make their field types nameable with the generic
+    // arguments they are flattened with:
+    (@field_types
+        export = $export:tt,
+        types_trait = $types_trait:ident,
+        generics = {},
+        params = $params:tt,
+        where_clause = $where_clause:tt,
+        name = $name:ident,
+        fields = $fields:tt,
+    ) => {};
+    (@field_types
+        export = { $export:vis },
+        types_trait = $types_trait:ident,
+        generics = { $($generics:tt)* },
+        params = { $($params:tt)* },
+        where_clause = { $($where_clause:tt)* },
+        name = $name:ident,
+        fields = { $({ $field_name:ident: $field_type:ty })* },
+    ) => {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        $export trait $types_trait {
+            $(type $field_name;)*
+        }
+        #[allow(non_camel_case_types, deprecated)]
+        #[automatically_derived]
+        impl<$($generics)*> $types_trait for $name<$($params)*> $($where_clause)* {
+            $(type $field_name = $field_type;)*
+        }
+    };
+    // Tuple structs have no named fields to pass to
+    // `#[flatten(postprocess = macro_name)]`
+    (@gather_tuple_fields
+        expanded_fields = $expanded_fields:tt,
+        field = { attrs = {}, flatten = {} },
+        queued_fields = {},
+        cx = {
+            definition = {
+                config = {
+                    export = $export:tt,
+                    export_attrs = $export_attrs:tt,
+                    macro_name = $macro_name:tt,
+                    serde_rename_all = $serde_rename_all:tt,
+                    derives = $derives:tt,
+                    copies = $copies:tt,
+                    module = $module:tt,
+                    kind = $kind:tt,
+                    inspect = $inspect:tt,
+                    order = $order:tt,
+                    strict = $strict:tt,
+                    postprocess = { $($postprocess:tt)+ },
+                },
+                attrs = $attrs:tt,
+                vis = $vis:tt,
+                name = $name:ident,
+                $($item:tt)*
+            },
+            dollar = $dollar:tt,
+        },
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten(postprocess = ...)]` needs a struct with named fields, `",
+            ::core::stringify!($name),
+            "` is a tuple struct",
+        ));
+    };
+    // Done, have gathered all fields of a tuple struct, emit the struct:
+    (@gather_tuple_fields
+        expanded_fields = { $({
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_type:ty
+        })* },
+        field = { attrs = {}, flatten = {} },
+        queued_fields = {},
+        cx = {
+            definition = {
+                config = {
+                    export = { $export:vis },
+                    export_attrs = { $(#[$export_attr:meta])* },
+                    macro_name = { $macro_name:ident },
+                    serde_rename_all = $serde_rename_all:tt,
+                    derives = $_derives:tt,
+                    copies = $copies:tt,
+                    module = { $($module:ident)? },
+                    kind = $_kind:tt,
+                    inspect = $inspect:tt,
+                    order = $_order:tt,
+                    strict = $strict:tt,
+                    postprocess = {},
+                },
+                attrs = { $(#[$struct_attr:meta])* },
+                vis = { $vis:vis },
+                name = $name:ident,
+                generics = {},
+                params = {},
+                impl_generics = {},
+                where_clause = {},
+            },
+            dollar = { $dollar:tt },
+        },
+    ) => {
+        $(#[$struct_attr])*
+        $vis struct $name($(
+            $(#[$($field_attr)*])*
+            $field_vis $field_type,
+        )*);
+        #[allow(deprecated)]
+        #[automatically_derived]
+        impl $crate::__PrivateFlattened for $name {}
+        $crate::flatten_structs!{@copies
+            copies = $copies,
+            vis = { $vis },
+            generics = {},
+            where_clause = {},
+            body = { ($(
+                $(#[$($field_attr)*])*
+                $field_vis $field_type,
+            )*); },
+            fields = {},
+            container = {},
+        }
+        $crate::flatten_structs!{@module
+            inspect = $inspect,
+            module = { $($module)? },
+            export = { $export },
+            items = {
+                $crate::__private_codegen_paste!{
+                    $(#[$export_attr])*
+                    macro_rules! [<__private_field_inspect_for $macro_name>] {
+                        (
+                            call = { $dollar($dollarcall:tt)* },
+                            prefix = { $dollar($dollarprefix:tt)* },
+                            cx = $dollarcx:tt,
+                        ) => {
+                            $dollar($dollarcall)*! {$dollar($dollarprefix)*
+                                tuple_fields = {$(
+                                    $(#[$($field_attr)*])*
+                                    $field_vis $field_type,
+                                )*},
+                                cx = $dollarcx,
+                            }
+                        };
+                    }
+                    #[allow(unused_imports)]
+                    $export use [<__private_field_inspect_for $macro_name>] as $macro_name;
+                }
+            },
+        }
+    };
+    // Fields of tuple structs are gathered by position, the attributes of a
+    // field are collected first to look for a `#[flatten]` attribute
+    (@gather_tuple_fields
+        expanded_fields = $expanded_fields:tt,
+        field = { attrs = $attrs:tt, flatten = $_flatten:tt },
+        queued_fields = { #[flatten $(($($flatten_opts:tt)*))?] $($queued_fields:tt)* },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@gather_tuple_fields
+            expanded_fields = $expanded_fields,
+            field = { attrs = $attrs, flatten = { opts = { $($($flatten_opts)*)? } } },
+            queued_fields = { $($queued_fields)* },
+            cx = $cx,
+        }
+    };
+    (@gather_tuple_fields
+        expanded_fields = $expanded_fields:tt,
+        field = { attrs = { $($attrs:tt)* }, flatten = $flatten:tt },
+        queued_fields = { #[$($attr:tt)*] $($queued_fields:tt)* },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@gather_tuple_fields
+            expanded_fields = $expanded_fields,
+            field = { attrs = { $($attrs)* #[$($attr)*] }, flatten = $flatten },
+            queued_fields = { $($queued_fields)* },
+            cx = $cx,
+        }
+    };
+    // No flatten attribute, keep the field as is
+    (@gather_tuple_fields
+        expanded_fields = { $($expanded_fields:tt)* },
+        field = { attrs = { $($attrs:tt)* }, flatten = {} },
+        queued_fields = { $field_vis:vis $field_type:ty $(, $($queued_fields:tt)*)? },
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@gather_tuple_fields
+            expanded_fields = { $($expanded_fields)* {
+                $($attrs)*
+                $field_vis $field_type
+            }},
+            field = { attrs = {}, flatten = {} },
+            queued_fields = { $($($queued_fields)*)? },
+            cx = $cx,
+        }
+    };
+    (@gather_tuple_fields
+        expanded_fields = $expanded_fields:tt,
+        field = { attrs = $attrs:tt, flatten = { opts = { $($flatten_opts:tt)+ } } },
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(
+            "`#[flatten]` fields of tuple structs don't support any flatten options"
+        );
+    };
+    // Found the flatten attribute, inline the fields of the type by position
+    (@gather_tuple_fields
+        expanded_fields = $expanded_fields:tt,
+        field = { attrs = $attrs:tt, flatten = { opts = {} } },
+        queued_fields = { $field_vis:vis $($type_path:ident)::+ $(, $($queued_fields:tt)*)? },
+        cx = $cx:tt,
+    ) => {
+        $($type_path)::+!{
+            call = { $crate::flatten_structs },
+            prefix = { @tuple_callback },
+            cx = {
+                flattened_vis = { $field_vis },
+                expanded_fields = $expanded_fields,
+                queued_fields = { $($($queued_fields)*)? },
+                cx = $cx,
+            },
+        }
+    };
+    (@gather_tuple_fields
+        expanded_fields = $expanded_fields:tt,
+        field = { attrs = $attrs:tt, flatten = { opts = {} } },
+        queued_fields = $queued_fields:tt,
+        cx = $cx:tt,
+    ) => {
+        ::core::compile_error!(
+            "`#[flatten]` fields of tuple structs need to be a type declared with \
+            `flatten_structs!` without generic arguments"
+        );
+    };
+    // Callback from "inspection" macro when flattening into a tuple struct,
+    // the fields of structs with named fields are inlined in declaration order
+    (@tuple_callback
+        fields = {$(
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?,
+        )*},
+        serde_rename_all = $serde_rename_all:tt,
+        field_types = $field_types:tt,
+        cx = $cx:tt,
+    ) => {
+        $crate::flatten_structs!{@tuple_callback
+            tuple_fields = {$(
+                $(#[$($field_attr)*])*
+                $field_vis $field_type,
+            )*},
+            cx = $cx,
+        }
+    };
+    (@tuple_callback
+        tuple_fields = {$(
+            $(#[$($field_attr:tt)*])*
+            $field_vis:vis $field_type:ty,
+        )*},
+        cx = {
+            flattened_vis = { $flattened_vis:vis },
+            expanded_fields = { $($expanded_fields:tt)* },
+            queued_fields = $queued_fields:tt,
+            cx = $cx:tt,
+        },
+    ) => {
+        $crate::flatten_structs!{@gather_tuple_fields
+            expanded_fields = { $($expanded_fields)* $({
+                $(#[$($field_attr)*])*
+                $flattened_vis $field_type
+            })* },
+            field = { attrs = {}, flatten = {} },
+            queued_fields = $queued_fields,
+            cx = $cx,
+        }
+    };
+}
+
+/// Passes the fields of a type declared with [`flatten_structs!`], including the
+/// inlined ones, to another macro.
+///
+/// `inspect!(Type => callback!)` invokes `callback!` with the fields as
+/// `fields = { ... }`, every field is written as `#[attr]* vis name: Type`,
+/// followed by `= default` for fields with a default value, and ends with a
+/// comma. Tuple structs pass `tuple_fields = { ... }` instead, with every field
+/// written as `#[attr]* vis Type,`. Tokens in parentheses after the callback,
+/// e.g. `inspect!(Type => callback!(args))`, are passed in front of the fields.
+/// The field types of generic types refer to their generic parameters.
+///
+/// The type is named by the path of its inspection macro, like for
+/// `#[flatten]` fields, and can be inspected wherever it could be flattened.
+///
+/// ```rust
+/// use flatten_structs::{flatten_structs, inspect};
+///
+/// flatten_structs!(
+///     #[allow(unused)]
+///     struct Sensor {
+///         /// The measured value
+///         value: f32,
+///         retries: u32 = 3,
+///     }
+/// );
+///
+/// macro_rules! field_names {
+///     (fields = {$(
+///         $(#[$attr:meta])*
+///         $vis:vis $name:ident: $ty:ty $(= $default:expr)?,
+///     )*}) => {
+///         [$(stringify!($name)),*]
+///     };
+/// }
+///
+/// assert_eq!(inspect!(Sensor => field_names!), ["value", "retries"]);
+/// ```
+#[macro_export]
+macro_rules! inspect {
+    (
+        $($type_path:ident)::+ => $($callback:ident)::+ ! $(( $($args:tt)* ))? $(,)?
+    ) => {
+        $($type_path)::+! {
+            call = { $crate::flatten_structs },
+            prefix = { @inspect },
+            cx = {
+                callback = { $($callback)::+ },
+                args = { $($($args)*)? },
+            },
+        }
+    };
+}
+
+/// Lists the fields of a type declared with [`flatten_structs!`], including the
+/// inlined ones, as a `&'static str`.
+///
+/// Every field is written as it is declared after flattening, with its
+/// attributes, visibility and default value, followed by a comma and a line
+/// break. This allows snapshot tests of what flattening produced. The type is
+/// named like for [`inspect!`], and the exact formatting of the tokens is the
+/// one of [`stringify!`].
+///
+/// ```rust
+/// use flatten_structs::{dump_fields, flatten_structs};
+///
+/// flatten_structs!(
+///     #[allow(unused)]
+///     struct Sensors {
+///         enable: bool,
+///         #[flatten(prefix = "left_")]
+///         left: Sensor,
+///     }
+///
+///     #[allow(unused)]
+///     struct Sensor {
+///         pub value: f32,
+///         retries: u32 = 3,
+///     }
+/// );
+///
+/// const FIELDS: &str = dump_fields!(Sensors);
+/// assert_eq!(
+///     FIELDS,
+///     "enable: bool,\nleft_value: f32,\nleft_retries: u32 = 3,\n",
+/// );
+/// ```
+#[macro_export]
+macro_rules! dump_fields {
+    ($($type_path:ident)::+ $(,)?) => {
+        $($type_path)::+! {
+            call = { $crate::flatten_structs },
+            prefix = { @dump_fields },
+            cx = {},
+        }
+    };
+}
+
+/// Continues with the flatten options that generate serde attributes, which
+/// need the `serde` feature.
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_serde {
+    (
+        option = $option:ident,
+        then = { $($then:tt)* },
+    ) => {
+        $crate::flatten_structs!{$($then)*}
+    };
+}
+
+/// Emits the code generated by `#[flatten_derive(try_from_map)]`, which needs
+/// the `serde_json` feature.
+#[cfg(feature = "serde_json")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_serde_json {
+    ($($items:tt)*) => {
+        $($items)*
+    };
+}
+
+/// Reports `#[flatten_derive(try_from_map)]` as unsupported without the
+/// `serde_json` feature.
+#[cfg(not(feature = "serde_json"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_serde_json {
+    ($($items:tt)*) => {
+        ::core::compile_error!(
+            "`#[flatten_derive(try_from_map)]` needs the `serde_json` feature of `flatten_structs`"
+        );
+    };
+}
+
+/// Emits the code generated by `#[flatten_derive(layout)]`, which needs the
+/// `layout` feature.
+#[cfg(feature = "layout")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_layout {
+    ($($items:tt)*) => {
+        $($items)*
+    };
+}
+
+/// Reports `#[flatten_derive(layout)]` as unsupported without the `layout`
+/// feature.
+#[cfg(not(feature = "layout"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_layout {
+    ($($items:tt)*) => {
+        ::core::compile_error!(
+            "`#[flatten_derive(layout)]` needs the `layout` feature of `flatten_structs`"
+        );
+    };
+}
+
+/// Reports the flatten options that generate serde attributes as unsupported
+/// without the `serde` feature.
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __private_serde {
+    (
+        option = $option:ident,
+        then = $then:tt,
+    ) => {
+        ::core::compile_error!(::core::concat!(
+            "`#[flatten(",
+            ::core::stringify!($option),
+            ")]` needs the `serde` feature of `flatten_structs`",
+        ));
+    };
+}
+
+/// A shorter name for [`flatten_structs!`], which it forwards to.
+///
+/// The generated inspection macros are still named after the declared types,
+/// so types declared with either name can be flattened by both.
+#[macro_export]
+macro_rules! flatten {
+    ($($input:tt)*) => {
+        $crate::flatten_structs!{$($input)*}
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    #[test]
+    fn flatten_structs() {
+        flatten_structs!(
+            #[allow(unused)]
+            pub struct BaseStruct {
+                enable: bool,
+                #[flatten]
+                n1: NestedStruct1,
+                #[flatten]
+                n2: NestedStruct2,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct NestedStruct1 {
+                value: f32,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct NestedStruct2 {
+                goal: f32,
+                #[flatten]
+                sn: SubNestedStruct,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct SubNestedStruct {
+                min: f32,
+                max: f32,
+            }
+        );
+
+        let base_struct = BaseStruct {
+            enable: true,
+            value: 0.0,
+            goal: 10.0,
+            min: 0.0,
+            max: 20.0,
+        };
+        assert!(base_struct.enable);
+    }
+
+    #[test]
+    fn flatten_serde_struct() {
+        flatten_structs!(
+            #[derive(Serialize, Deserialize)]
+            pub struct BaseSerdeStruct {
+                enable: bool,
+                #[flatten]
+                nested: NestedSerdeStruct,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Serialize, Deserialize)]
+            struct NestedSerdeStruct {
+                min: Option<f32>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                max: Option<f32>,
+            }
+        );
+
+        let base_struct = BaseSerdeStruct {
+            enable: false,
+            min: Some(0.0),
+            max: None,
+        };
+        let base_struct_json = serde_json::to_string_pretty(&base_struct).unwrap();
+        pretty_assertions::assert_eq!(
+            r#"{
+  "enable": false,
+  "min": 0.0
+}"#,
+            base_struct_json
+        );
+    }
+
+    #[test]
+    fn flatten_attribute_position() {
+        flatten_structs!(
+            #[derive(Serialize)]
+            struct LateFlattenStruct {
+                /// Documented flattened field
+                #[allow(unused)]
+                #[flatten]
+                nested: LateFlattenNested,
+                #[serde(rename = "enabled")]
+                enable: bool,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Serialize)]
+            struct LateFlattenNested {
+                /// Documented inlined field
+                #[serde(skip_serializing_if = "Option::is_none")]
+                min: Option<f32>,
+                max: f32,
+            }
+        );
+
+        let late_flatten_struct = LateFlattenStruct {
+            min: None,
+            max: 1.0,
+            enable: true,
+        };
+        pretty_assertions::assert_eq!(
+            r#"{"max":1.0,"enabled":true}"#,
+            serde_json::to_string(&late_flatten_struct).unwrap()
+        );
+    }
+
+    #[test]
+    fn flatten_generic_container() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct GenericWrapper<T: Clone + Into<Vec<u8>>, U = f32> {
+                #[flatten]
+                inner: GenericWrapperInner,
+                extra: T,
+                others: Vec<U>,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct GenericWrapperInner {
+                value: f32,
+            }
+        );
+
+        let wrapper: GenericWrapper<String> = GenericWrapper {
+            value: 0.0,
+            extra: String::from("extra"),
+            others: vec![1.0],
+        };
+        assert_eq!(wrapper.extra, "extra");
+    }
+
+    #[test]
+    fn flatten_const_generics() {
+        flatten_structs!(
+            #[derive(Clone)]
+            #[flatten_derive(builder)]
+            struct ConstBuffer<const N: usize, const M: usize = 2> {
+                #[flatten]
+                hdr: ConstHeader,
+                data: [u8; N],
+                #[flatten(prefix = "tail_")]
+                tail: ConstTail<M>,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct ConstHeader {
+                len: u16,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct ConstTail<const N: usize> {
+                checksum: [u8; N],
+            }
+        );
+
+        let buffer = ConstBuffer::<4> {
+            len: 4,
+            data: [1, 2, 3, 4],
+            tail_checksum: [0; 2],
+        };
+        assert_eq!(buffer.data.len(), usize::from(buffer.len));
+        let built = ConstBufferBuilder::<1, 1>::default()
+            .with_len(1)
+            .with_data([1])
+            .with_tail_checksum([1])
+            .build()
+            .unwrap();
+        assert_eq!(built.clone().tail_checksum, [1]);
+    }
+
+    #[test]
+    fn flatten_generic_nested() {
+        flatten_structs!(
+            #[derive(Serialize)]
+            struct GenericOuter {
+                enable: bool,
+                #[flatten(prefix = "count_")]
+                count: GenericRange<u32>,
+                #[flatten(prefix = "weight_")]
+                weight: GenericRange<f32, Vec<f32>>,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Serialize)]
+            struct GenericRange<T: Copy, E = Option<T>> {
+                min: T,
+                max: T,
+                extra: E,
+            }
+        );
+
+        let generic_outer = GenericOuter {
+            enable: true,
+            count_min: 0,
+            count_max: 10,
+            count_extra: None,
+            weight_min: 0.5,
+            weight_max: 1.5,
+            weight_extra: vec![1.0],
+        };
+        let count_max: u32 = generic_outer.count_max;
+        assert_eq!(count_max, 10);
+        pretty_assertions::assert_eq!(
+            r#"{"enable":true,"count_min":0,"count_max":10,"count_extra":null,"weight_min":0.5,"weight_max":1.5,"weight_extra":[1.0]}"#,
+            serde_json::to_string(&generic_outer).unwrap()
+        );
+    }
+
+    #[test]
+    fn flatten_generic_passthrough() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct PassthroughOuter<U: Clone, const N: usize> {
+                id: u8,
+                #[flatten]
+                inner: PassthroughInner<Vec<U>, N>,
+                #[flatten(prefix = "other_")]
+                other: PassthroughInner<U, 2>,
+            }
+
+            #[allow(unused)]
+            struct PassthroughInner<T, const M: usize> {
+                value: T,
+                buf: [u8; M],
+            }
+        );
+
+        let outer = PassthroughOuter::<&str, 1> {
+            id: 0,
+            value: vec!["value"],
+            buf: [1],
+            other_value: "other",
+            other_buf: [2, 3],
+        };
+        assert_eq!(outer.value, ["value"]);
+        assert_eq!(outer.other_buf, [2, 3]);
+        assert_eq!(
+            PassthroughOuter::<&str, 1>::FIELD_NAMES,
+            ["id", "value", "buf", "other_value", "other_buf"]
+        );
+    }
+
+    #[test]
+    fn flatten_where_clause() {
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Clone)]
+            struct WhereOuter<T>
+            where
+                T: Clone + Default,
+            {
+                #[flatten]
+                inner: WhereInner<T>,
+                extra: T,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Clone)]
+            struct WhereInner<T>
+            where
+                T: Clone,
+            {
+                value: T,
+            }
+        );
+
+        let where_outer = WhereOuter {
+            value: String::from("value"),
+            extra: String::new(),
+        };
+        assert_eq!(where_outer.clone().value, "value");
+    }
+
+    #[test]
+    fn flatten_where_clause_mixed_items() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct WhereEnumFirst<T>
+            where
+                T: Copy,
+            {
+                value: T,
+            }
+            #[allow(unused)]
+            enum WhereEnum {
+                Created {
+                    #[flatten]
+                    group: WhereGroup,
+                },
+            }
+            #[allow(unused)]
+            struct WhereGroupFirst<T>
+            where
+                T: Copy,
+            {
+                #[flatten]
+                group: WhereGroup,
+                value: T,
+            }
+            group WhereGroup {
+                id: u64,
+            }
+            #[allow(unused)]
+            struct WhereTupleFirst<T>
+            where
+                T: Copy,
+            {
+                value: T,
+            }
+            #[allow(unused)]
+            struct WhereTuple(u8, u16);
+        );
+
+        assert_eq!(WhereEnumFirst::<u8>::FIELD_NAMES, ["value"]);
+        assert!(matches!(
+            WhereEnum::Created { id: 1 },
+            WhereEnum::Created { id: 1 }
+        ));
+        assert_eq!(WhereGroupFirst::<u8>::FIELD_NAMES, ["id", "value"]);
+        assert_eq!(WhereTupleFirst::<u8>::FIELD_NAMES, ["value"]);
+        assert_eq!(WhereTuple(1, 2).1, 2);
+    }
+
+    #[test]
+    fn flatten_any_field_type() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct AnyTypeStruct<'a> {
+                coords: (f32, f32),
+                #[flatten]
+                nested: AnyTypeNested,
+                buf: [u8; 4],
+                slice: &'a [u8],
+                callback: fn(u8) -> u8,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct AnyTypeNested {
+                pair: (u8, (u8, u8)),
+                unit: (),
+            }
+        );
+
+        let any_type_struct = AnyTypeStruct {
+            coords: (0.0, 1.0),
+            pair: (0, (1, 2)),
+            unit: (),
+            buf: [0; 4],
+            slice: &[1, 2],
+            callback: |value| value + 1,
+        };
+        assert_eq!((any_type_struct.callback)(any_type_struct.pair.1.1), 3);
+    }
+
+    #[test]
+    fn flatten_lifetimes() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct LifetimeView<'a, 'b: 'a> {
+                #[flatten]
+                header: LifetimeHeader<'b>,
+                body: &'a [u8],
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct LifetimeHeader<'a> {
+                name: &'a str,
+                version: u8,
+            }
+        );
+
+        let body = [1, 2, 3];
+        let view = LifetimeView {
+            name: "header",
+            version: 1,
+            body: &body,
+        };
+        assert_eq!(view.name, "header");
+        assert_eq!(view.body, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn flatten_prefix() {
+        flatten_structs!(
+            #[derive(Serialize, Deserialize)]
+            pub struct PrefixedStruct {
+                enable: bool,
+                #[flatten(prefix = "input_")]
+                input: PrefixedRange,
+                #[flatten(prefix = "output_")]
+                output: PrefixedRange,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Serialize, Deserialize)]
+            struct PrefixedRange {
+                min: f32,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                max: Option<f32>,
+            }
+        );
+
+        let prefixed_struct = PrefixedStruct {
+            enable: true,
+            input_min: 0.0,
+            input_max: Some(1.0),
+            output_min: 2.0,
+            output_max: None,
+        };
+        let prefixed_struct_json = serde_json::to_string_pretty(&prefixed_struct).unwrap();
+        pretty_assertions::assert_eq!(
+            r#"{
+  "enable": true,
+  "input_min": 0.0,
+  "input_max": 1.0,
+  "output_min": 2.0
+}"#,
+            prefixed_struct_json
+        );
+    }
+
+    #[test]
+    fn flatten_rename() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct RenamedStruct {
+                #[flatten(rename(value = primary_value, goal = target))]
+                primary: RenamedNested,
+                #[flatten(prefix = "secondary_", rename(goal = secondary))]
+                secondary: RenamedNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct RenamedNested {
+                value: f32,
+                goal: f32,
+                weight: f32,
+            }
+        );
+
+        let renamed_struct = RenamedStruct {
+            primary_value: 0.0,
+            target: 1.0,
+            weight: 2.0,
+            secondary_value: 3.0,
+            secondary: 4.0,
+            secondary_weight: 5.0,
+        };
+        assert_eq!(renamed_struct.secondary, 4.0);
+    }
+
+    #[test]
+    fn flatten_keep() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct KeepStruct {
+                #[flatten(prefix = "left_", keep(id))]
+                left: KeepNested,
+                #[flatten(suffix = "_right", keep(value))]
+                right: KeepNested,
+            }
+
+            #[allow(unused)]
+            struct KeepNested {
+                id: u32,
+                value: f32,
+            }
+        );
+
+        assert_eq!(
+            KeepStruct::FIELD_NAMES,
+            ["id", "left_value", "id_right", "value"]
+        );
+    }
+
+    #[test]
+    fn flatten_doc_prefix() {
+        flatten_structs!(
+            /// Documented struct
+            #[allow(unused)]
+            pub struct DocumentedStruct {
+                /// Whether it is enabled
+                pub enable: bool,
+                #[flatten(doc_prefix = "From `DocumentedNested`:")]
+                pub nested: DocumentedNested,
+            }
+        );
+
+        flatten_structs!(
+            /// Documented nested struct
+            #[allow(unused)]
+            pub struct DocumentedNested {
+                /// The smallest value
+                pub min: f32,
+                pub max: f32,
+            }
+        );
+
+        let documented_struct = DocumentedStruct {
+            enable: true,
+            min: 0.0,
+            max: 1.0,
+        };
+        assert!(documented_struct.enable);
+        assert_eq!(
+            dump_fields!(DocumentedStruct),
+            "#[doc = r\" Whether it is enabled\"] pub enable: bool,\n\
+             #[doc = \"From `DocumentedNested`:\"] #[doc = r\" The smallest value\"] pub min: f32,\n\
+             #[doc = \"From `DocumentedNested`:\"] pub max: f32,\n"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn flatten_serde_keep_case() {
+        flatten_structs!(
+            #[derive(Serialize, Deserialize)]
+            #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+            pub struct CasedStruct {
+                enable: bool,
+                #[flatten(serde_keep_case)]
+                pascal: PascalCased,
+                #[flatten(serde_keep_case, prefix = "plain_")]
+                plain: PlainCased,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Serialize, Deserialize)]
+            #[serde(rename_all = "PascalCase")]
+            struct PascalCased {
+                min_value: f32,
+                #[serde(skip_serializing_if = "Option::is_none", rename = "Max")]
+                max_value: Option<f32>,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Serialize, Deserialize)]
+            struct PlainCased {
                 goal: f32,
+            }
+        );
+
+        let cased_struct = CasedStruct {
+            enable: true,
+            min_value: 0.0,
+            max_value: Some(1.0),
+            plain_goal: 2.0,
+        };
+        pretty_assertions::assert_eq!(
+            r#"{"ENABLE":true,"MinValue":0.0,"Max":1.0,"goal":2.0}"#,
+            serde_json::to_string(&cased_struct).unwrap()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn flatten_rename_all() {
+        flatten_structs!(
+            #[derive(Serialize)]
+            struct RenameAllStruct {
+                enable: bool,
+                #[flatten(prefix = "left_", rename_all = "SCREAMING_SNAKE_CASE")]
+                left: RenameAllNested,
+                #[flatten(rename_all = "PascalCase", prefix = "right_", skip(max_value))]
+                right: RenameAllNested,
+            }
+
+            #[allow(unused)]
+            #[derive(Serialize)]
+            struct RenameAllNested {
+                min_value: f32,
+                #[serde(rename = "max")]
+                max_value: f32,
+            }
+        );
+
+        let rename_all_struct = RenameAllStruct {
+            enable: true,
+            left_min_value: 0.0,
+            left_max_value: 1.0,
+            right_min_value: 2.0,
+        };
+        pretty_assertions::assert_eq!(
+            r#"{"enable":true,"LEFT_MIN_VALUE":0.0,"max":1.0,"MinValue":2.0}"#,
+            serde_json::to_string(&rename_all_struct).unwrap()
+        );
+    }
+
+    #[test]
+    fn flatten_prefix_serde_rename() {
+        flatten_structs!(
+            #[derive(Serialize)]
+            struct PrefixRenameStruct {
+                #[flatten(prefix = "left_", suffix = "_value")]
+                left: PrefixRenameNested,
+                count: u32,
+            }
+
+            #[allow(unused)]
+            #[derive(Serialize)]
+            struct PrefixRenameNested {
+                #[serde(rename = "explicit")]
+                min: f32,
+                max: f32,
+            }
+        );
+
+        let prefix_rename_struct = PrefixRenameStruct {
+            left_min_value: 0.0,
+            left_max_value: 1.0,
+            count: 2,
+        };
+        pretty_assertions::assert_eq!(
+            r#"{"explicit":0.0,"left_max_value":1.0,"count":2}"#,
+            serde_json::to_string(&prefix_rename_struct).unwrap()
+        );
+    }
+
+    #[test]
+    fn flatten_on_conflict() {
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Serialize)]
+            struct OverriddenConfig {
+                #[flatten]
+                defaults: DefaultConfig,
+                #[flatten(on_conflict = "last")]
+                overrides: OverrideConfig,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Serialize)]
+            struct KeptConfig {
+                #[flatten]
+                defaults: DefaultConfig,
+                #[flatten(on_conflict = "first")]
+                overrides: OverrideConfig,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Serialize)]
+            struct DefaultConfig {
+                name: String,
+                retries: u32,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Serialize)]
+            struct OverrideConfig {
+                #[serde(rename = "max_retries")]
+                retries: u32,
+                verbose: bool,
+            }
+        );
+
+        let overridden_config = OverriddenConfig {
+            name: String::from("overridden"),
+            retries: 3,
+            verbose: true,
+        };
+        let kept_config = KeptConfig {
+            name: String::from("kept"),
+            retries: 3,
+            verbose: true,
+        };
+        pretty_assertions::assert_eq!(
+            r#"{"name":"overridden","max_retries":3,"verbose":true}"#,
+            serde_json::to_string(&overridden_config).unwrap()
+        );
+        pretty_assertions::assert_eq!(
+            r#"{"name":"kept","retries":3,"verbose":true}"#,
+            serde_json::to_string(&kept_config).unwrap()
+        );
+    }
+
+    #[test]
+    fn flatten_on_conflict_merge() {
+        flatten_structs!(
+            #[derive(Debug, Default, PartialEq)]
+            struct MergedRecord {
+                #[flatten]
+                created: MergeCreated,
+                #[flatten(on_conflict = "merge")]
+                updated: MergeUpdated,
+            }
+
+            #[allow(unused)]
+            struct MergeCreated {
+                id: u64,
+                created_at: u64,
+            }
+
+            #[allow(unused)]
+            struct MergeUpdated {
+                id: u64,
+                updated_at: u64,
+            }
+        );
+
+        assert_eq!(
+            MergedRecord::FIELD_NAMES,
+            ["id", "created_at", "updated_at"]
+        );
+        let record = MergedRecord {
+            id: 1,
+            created_at: 2,
+            updated_at: 3,
+        };
+        assert_eq!(record.id, 1);
+    }
+
+    #[test]
+    fn flatten_dedup() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct DedupDiamond {
+                #[flatten]
+                left: DedupLeft,
+                #[flatten(dedup)]
+                right: DedupRight,
+            }
+
+            #[allow(unused)]
+            struct DedupLeft {
+                #[flatten]
+                base: DedupBase,
+                left: u8,
+            }
+
+            #[allow(unused)]
+            struct DedupRight {
+                #[flatten]
+                base: DedupBase,
+                right: u8,
+            }
+
+            #[allow(unused)]
+            struct DedupBase {
+                /// The identifier
+                id: u64,
+                name: &'static str,
+            }
+        );
+
+        assert_eq!(DedupDiamond::FIELD_NAMES, ["id", "name", "left", "right"]);
+        let diamond = DedupDiamond {
+            id: 1,
+            name: "diamond",
+            left: 2,
+            right: 3,
+        };
+        assert_eq!(diamond.name, "diamond");
+    }
+
+    mod exported {
+        flatten_structs!(
+            #[flatten_export(pub(super))]
+            #[allow(unused)]
+            pub struct ExportedSuper {
+                pub value: f32,
+            }
+        );
+
+        flatten_structs!(
+            #[flatten_export(pub)]
+            #[allow(unused)]
+            pub struct ExportedPub {
+                pub goal: f32,
+            }
+        );
+    }
+
+    #[test]
+    fn flatten_macro_name() {
+        flatten_structs!(
+            #[flatten_macro_name(inspect_named_inner)]
+            #[allow(unused)]
+            struct NamedInner {
+                value: f32,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct NamedOuter {
+                #[flatten]
+                inner: inspect_named_inner,
+            }
+        );
+
+        let named_outer = NamedOuter { value: 1.0 };
+        assert_eq!(named_outer.value, 1.0);
+    }
+
+    #[test]
+    fn flatten_export() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct ExportedOuter {
+                #[flatten]
+                exported_super: exported::ExportedSuper,
+                #[flatten]
+                exported_pub: exported::ExportedPub,
+            }
+        );
+
+        let exported_outer = ExportedOuter {
+            value: 0.0,
+            goal: 1.0,
+        };
+        assert_eq!(exported_outer.goal, 1.0);
+    }
+
+    #[test]
+    fn flatten_suffix() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct SuffixedStruct {
+                #[flatten(suffix = "_input")]
+                input: SuffixedRange,
+                #[flatten(prefix = "raw_", suffix = "_output")]
+                output: SuffixedRange,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct SuffixedRange {
+                min: f32,
+                max: f32,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct SuffixedOuterStruct {
+                enable: bool,
+                #[flatten]
+                suffixed: SuffixedStruct,
+            }
+        );
+
+        let outer_struct = SuffixedOuterStruct {
+            enable: true,
+            min_input: 0.0,
+            max_input: 1.0,
+            raw_min_output: 2.0,
+            raw_max_output: 3.0,
+        };
+        assert_eq!(outer_struct.raw_max_output, 3.0);
+    }
+
+    #[test]
+    fn flatten_tuple_struct() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct TupleStruct(#[flatten] TupleHeader, u32, #[flatten] TupleNamed);
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct TupleHeader(u8, bool);
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct TupleNamed {
+                value_0: f32,
+                value_1: f32,
+            }
+        );
+
+        let tuple_struct = TupleStruct(1, true, 2, 3.0, 4.0);
+        assert_eq!(tuple_struct, TupleStruct(1, true, 2, 3.0, 4.0));
+        assert_eq!(tuple_struct.4, 4.0);
+    }
+
+    #[test]
+    fn flatten_newtype() {
+        flatten_structs!(
+            #[flatten(strict)]
+            #[derive(Debug, PartialEq)]
+            struct NewtypeStruct {
+                #[flatten]
+                distance: NewtypeMeters,
+                #[flatten(prefix = "max_")]
+                limit: NewtypeMeters,
+                #[flatten(rename(speed = meters_per_second))]
+                speed: NewtypeMeters,
+            }
+
+            #[allow(unused)]
+            struct NewtypeMeters(pub f32);
+        );
+
+        assert_eq!(
+            NewtypeStruct::FIELD_NAMES,
+            ["distance", "max_limit", "meters_per_second"]
+        );
+        let newtype_struct = NewtypeStruct {
+            distance: 1.0,
+            max_limit: 2.0,
+            meters_per_second: 3.0,
+        };
+        assert_eq!(newtype_struct.max_limit, 2.0);
+    }
+
+    #[test]
+    fn flatten_reconstruct() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct ReconstructStruct {
+                enable: bool,
+                #[flatten(reconstruct)]
+                n1: ReconstructNested,
+                #[flatten(prefix = "n2_", rename(value_1 = other), reconstruct)]
+                n2: ReconstructNested,
+                #[flatten(reconstruct)]
+                range: ReconstructRange<u32>,
+            }
+        );
+
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct ReconstructNested {
+                value_0: f32,
+                value_1: f32,
+            }
+        );
+
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct ReconstructRange<T> {
+                start: T,
+                end: T,
+            }
+        );
+
+        let reconstruct_struct = ReconstructStruct {
+            enable: true,
+            value_0: 0.0,
+            value_1: 1.0,
+            n2_value_0: 2.0,
+            other: 3.0,
+            start: 4,
+            end: 5,
+        };
+        assert_eq!(
+            reconstruct_struct.to_n1(),
+            ReconstructNested {
+                value_0: 0.0,
+                value_1: 1.0,
+            }
+        );
+        assert_eq!(
+            reconstruct_struct.to_n2(),
+            ReconstructNested {
+                value_0: 2.0,
+                value_1: 3.0,
+            }
+        );
+        assert_eq!(
+            reconstruct_struct.to_range(),
+            ReconstructRange { start: 4, end: 5 }
+        );
+    }
+
+    #[test]
+    fn flatten_into_nested() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct IntoNestedStruct<T: Clone> {
+                enable: bool,
+                #[flatten(prefix = "n_", into_nested)]
+                nested: IntoNestedNested,
+                #[flatten(into_nested, reconstruct)]
+                range: IntoNestedRange<T>,
+            }
+        );
+
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct IntoNestedNested {
+                value: f32,
+            }
+        );
+
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct IntoNestedRange<T> {
+                start: T,
+                end: T,
+            }
+        );
+
+        let into_nested_struct = IntoNestedStruct {
+            enable: true,
+            n_value: 1.0,
+            start: 2u8,
+            end: 3,
+        };
+        let nested: IntoNestedNested = (&into_nested_struct).into();
+        assert_eq!(nested, IntoNestedNested { value: 1.0 });
+        let range: IntoNestedRange<u8> = (&into_nested_struct).into();
+        assert_eq!(range, into_nested_struct.to_range());
+    }
+
+    #[test]
+    fn flatten_view() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct ViewStruct<'a, T> {
+                name: &'a str,
+                #[flatten(prefix = "n_", view)]
+                nested: ViewNested,
+                #[flatten(view)]
+                range: ViewRange<T>,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct ViewNested {
+                value_0: f32,
+                value_1: f32,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct ViewRange<T> {
+                start: T,
+                end: T,
+            }
+        );
+
+        let view_struct = ViewStruct {
+            name: "view",
+            n_value_0: 0.0,
+            n_value_1: 1.0,
+            start: 2u8,
+            end: 3,
+        };
+        let nested = view_struct.nested();
+        assert_eq!((*nested.value_0, *nested.value_1), (0.0, 1.0));
+        let range = view_struct.range();
+        assert_eq!((*range.start, *range.end), (2, 3));
+
+        // The view structs are named after the container too, so several
+        // containers can have views of the same field name.
+        flatten_structs!(
+            struct OtherViewStruct {
+                #[flatten(view)]
+                nested: ViewNested,
+            }
+        );
+
+        let other_struct = OtherViewStruct {
+            value_0: 4.0,
+            value_1: 5.0,
+        };
+        let other_nested: OtherViewStructNestedRef<'_> = other_struct.nested();
+        assert_eq!((*other_nested.value_0, *other_nested.value_1), (4.0, 5.0));
+        let nested: ViewStructNestedRef<'_, '_, u8> = view_struct.nested();
+        assert_eq!(*nested.value_0, 0.0);
+    }
+
+    #[test]
+    fn flatten_view_mut() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct ViewMutStruct<'a, T> {
+                name: &'a str,
+                #[flatten(prefix = "n_", view, view_mut)]
+                nested: ViewMutNested,
+                #[flatten(view_mut)]
+                range: ViewMutRange<T>,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct ViewMutNested {
+                value_0: f32,
+                value_1: f32,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct ViewMutRange<T> {
+                start: T,
+                end: T,
+            }
+        );
+
+        fn reset(nested: ViewMutStructNestedMut<'_, '_, u8>) {
+            *nested.value_0 = 0.0;
+            *nested.value_1 = 0.0;
+        }
+
+        let mut view_struct = ViewMutStruct {
+            name: "view",
+            n_value_0: 1.0,
+            n_value_1: 2.0,
+            start: 2u8,
+            end: 3,
+        };
+        reset(view_struct.nested_mut());
+        let nested = view_struct.nested();
+        assert_eq!((*nested.value_0, *nested.value_1), (0.0, 0.0));
+        let range = view_struct.range_mut();
+        std::mem::swap(range.start, range.end);
+        assert_eq!((view_struct.start, view_struct.end), (3, 2));
+
+        flatten_structs!(
+            struct OtherViewMutStruct {
+                #[flatten(view_mut)]
+                nested: ViewMutNested,
+            }
+        );
+
+        let mut other_struct = OtherViewMutStruct {
+            value_0: 4.0,
+            value_1: 5.0,
+        };
+        let other_nested: OtherViewMutStructNestedMut<'_> = other_struct.nested_mut();
+        *other_nested.value_0 = 6.0;
+        *other_nested.value_1 += 1.0;
+        assert_eq!((other_struct.value_0, other_struct.value_1), (6.0, 6.0));
+    }
+
+    #[test]
+    fn flatten_field_names() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct FieldNamesStruct<T> {
+                enable: bool,
+                #[flatten(prefix = "n_", rename(value_1 = other))]
+                nested: FieldNamesNested<T>,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct FieldNamesNested<T> {
+                value_0: T,
+                value_1: T,
+            }
+        );
+
+        assert_eq!(
+            FieldNamesStruct::<u8>::FIELD_NAMES,
+            ["enable", "n_value_0", "other"]
+        );
+        assert_eq!(FieldNamesStruct::<u8>::FIELD_COUNT, 3);
+
+        let field_names_struct = FieldNamesStruct {
+            enable: true,
+            n_value_0: 0u8,
+            other: 1,
+        };
+        assert_eq!(
+            field_names_struct.field_names(),
+            FieldNamesStruct::<u8>::FIELD_NAMES
+        );
+    }
+
+    #[test]
+    fn flatten_field_count() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct FieldCountStruct {
+                enable: bool,
+                #[flatten(field_count)]
+                n2: FieldCountNested,
+                #[flatten(field_count, prefix = "other_", skip(value_1))]
+                other: FieldCountNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct FieldCountNested {
+                value_0: f32,
+                value_1: f32,
+            }
+        );
+
+        assert_eq!(FieldCountStruct::FIELD_COUNT, 4);
+        assert_eq!(FieldCountStruct::N2_FIELD_COUNT, 2);
+        assert_eq!(FieldCountStruct::OTHER_FIELD_COUNT, 1);
+    }
+
+    #[test]
+    fn flatten_multiple_structs() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct MultipleNested {
+                value: f32,
+            }
+
+            #[allow(unused)]
+            struct MultipleWhere<T>
+            where
+                T: Copy,
+            {
+                start: T,
+            }
+
+            #[allow(unused)]
+            struct MultipleTuple(u8, u8);
+
+            #[allow(unused)]
+            struct MultipleStruct<T: Copy> {
+                #[flatten]
+                nested: MultipleNested,
+                #[flatten]
+                range: MultipleWhere<T>,
+            }
+        );
+
+        let multiple_struct = MultipleStruct {
+            value: 0.0,
+            start: 1u8,
+        };
+        assert_eq!(multiple_struct.start, 1);
+    }
+
+    #[test]
+    fn flatten_builder() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(builder)]
+            struct BuilderStruct<T> {
+                enable: bool,
+                #[flatten(prefix = "n_")]
+                nested: BuilderNested<T>,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct BuilderNested<T> {
+                value: T,
+            }
+        );
+
+        let builder_struct = BuilderStructBuilder::default()
+            .with_enable(true)
+            .with_n_value(1u8)
+            .build();
+        assert_eq!(
+            builder_struct,
+            Ok(BuilderStruct {
+                enable: true,
+                n_value: 1,
+            })
+        );
+        let missing = BuilderStructBuilder::<u8>::default()
+            .with_enable(true)
+            .build();
+        assert_eq!(missing, Err(crate::MissingField { field: "n_value" }));
+    }
+
+    #[test]
+    fn flatten_builder_many_groups() {
+        // The builder and the accessors are generated for all 6 groups at
+        // once, without growing the recursion depth per group
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(builder, accessors)]
+            struct BuilderRack {
+                name: &'static str,
+                #[flatten(prefix = "slot_0_")]
+                slot_0: BuilderSlot,
+                #[flatten(prefix = "slot_1_")]
+                slot_1: BuilderSlot,
+                #[flatten(prefix = "slot_2_")]
+                slot_2: BuilderSlot,
+                #[flatten(prefix = "slot_3_")]
+                slot_3: BuilderSlot,
+                #[flatten(prefix = "slot_4_")]
+                slot_4: BuilderSlot,
+                #[flatten(prefix = "slot_5_")]
+                slot_5: BuilderSlot,
+            }
+
+            #[allow(unused)]
+            group BuilderSlot {
+                load: u8,
+                active: bool = true,
+            }
+        );
+
+        let builder = BuilderRackBuilder::default()
+            .with_name("rack")
+            .with_slot_0_load(0)
+            .with_slot_1_load(1)
+            .with_slot_2_load(2)
+            .with_slot_3_load(3)
+            .with_slot_4_load(4);
+        assert_eq!(
+            builder.build().err(),
+            Some(crate::MissingField {
+                field: "slot_5_load"
+            })
+        );
+        let rack = BuilderRackBuilder::default()
+            .with_name("rack")
+            .with_slot_0_load(0)
+            .with_slot_1_load(1)
+            .with_slot_2_load(2)
+            .with_slot_3_load(3)
+            .with_slot_4_load(4)
+            .with_slot_5_load(5)
+            .with_slot_5_active(false)
+            .build()
+            .unwrap();
+        assert_eq!((*rack.slot_0_load(), *rack.slot_5_load()), (0, 5));
+        assert!(*rack.slot_0_active() && !*rack.slot_5_active());
+    }
+
+    #[test]
+    fn flatten_accessors() {
+        mod accessors {
+            flatten_structs!(
+                #[flatten_derive(builder, accessors)]
+                pub struct AccessorsStruct {
+                    enable: bool,
+                    #[flatten]
+                    nested: AccessorsNested,
+                }
+            );
+
+            flatten_structs!(
+                #[allow(unused)]
+                struct AccessorsNested {
+                    value: f32,
+                }
+            );
+        }
+
+        let mut accessors_struct = accessors::AccessorsStructBuilder::default()
+            .with_enable(true)
+            .with_value(1.0)
+            .build()
+            .unwrap();
+        accessors_struct.set_value(2.0);
+        assert!(*accessors_struct.enable());
+        assert_eq!(*accessors_struct.value(), 2.0);
+    }
+
+    #[test]
+    fn flatten_accessors_many_groups() {
+        // The accessors and the patch are generated for all 6 groups at once,
+        // without growing the recursion depth per group
+        flatten_structs!(
+            #[derive(Default)]
+            #[flatten_derive(accessors, patch)]
+            struct AccessorsBoard {
+                #[flatten(prefix = "pin_0_")]
+                pin_0: AccessorsPin,
+                #[flatten(prefix = "pin_1_")]
+                pin_1: AccessorsPin,
+                #[flatten(prefix = "pin_2_")]
+                pin_2: AccessorsPin,
+                #[flatten(prefix = "pin_3_")]
+                pin_3: AccessorsPin,
+                #[flatten(prefix = "pin_4_")]
+                pin_4: AccessorsPin,
+                #[flatten(prefix = "pin_5_")]
+                pin_5: AccessorsPin,
+                #[flatten]
+                status: AccessorsStatus,
+            }
+
+            #[allow(unused)]
+            group AccessorsPin {
+                level: bool,
+                mode: u8,
+            }
+
+            #[allow(unused)]
+            group AccessorsStatus {
+                ready: bool,
+            }
+        );
+
+        let mut board = AccessorsBoard::default();
+        board.set_pin_0_mode(1);
+        board.set_pin_5_level(true);
+        board.set_ready(true);
+        assert_eq!((*board.pin_0_mode(), *board.pin_5_mode()), (1, 0));
+        assert!(*board.pin_5_level() && *board.ready());
+        board.apply(AccessorsBoardPatch {
+            pin_5_mode: Some(2),
+            ..AccessorsBoardPatch::default()
+        });
+        assert_eq!(*board.pin_5_mode(), 2);
+    }
+
+    #[test]
+    fn flatten_default_values() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(builder)]
+            struct DefaultStruct {
+                retries: u32 = 3,
+                name: &'static str,
+                #[flatten(prefix = "n_")]
+                nested: DefaultNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct DefaultNested {
+                value: f32 = 1.5,
+                count: u8,
+            }
+        );
+
+        assert_eq!(
+            DefaultStruct::default(),
+            DefaultStruct {
+                retries: 3,
+                name: "",
+                n_value: 1.5,
+                n_count: 0,
+            }
+        );
+        let default_struct = DefaultStructBuilder::default()
+            .with_name("default")
+            .with_n_count(2)
+            .build();
+        assert_eq!(
+            default_struct,
+            Ok(DefaultStruct {
+                retries: 3,
+                name: "default",
+                n_value: 1.5,
+                n_count: 2,
+            })
+        );
+        let overridden = DefaultStructBuilder::default()
+            .with_retries(5)
+            .with_name("overridden")
+            .with_n_count(2)
+            .build();
+        assert_eq!(overridden.map(|overridden| overridden.retries), Ok(5));
+        assert_eq!(
+            DefaultStructBuilder::default().with_name("missing").build(),
+            Err(crate::MissingField { field: "n_count" })
+        );
+    }
+
+    #[test]
+    fn flatten_cfg() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(builder)]
+            struct CfgStruct {
+                enable: bool = true,
+                #[cfg(any())]
+                #[flatten]
+                disabled: CfgDisabled,
+                #[cfg(any())]
+                #[flatten]
+                missing: CfgMissing,
+                #[flatten(reconstruct)]
+                #[cfg(not(any()))]
+                enabled: CfgEnabled,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct CfgDisabled {
+                disabled_value: f32 = 1.0,
+            }
+        );
+
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct CfgEnabled {
+                enabled_value: f32,
+            }
+        );
+
+        let cfg_struct = CfgStruct {
+            enable: true,
+            enabled_value: 1.0,
+        };
+        assert_eq!(cfg_struct.to_enabled(), CfgEnabled { enabled_value: 1.0 });
+        assert_eq!(CfgStruct::FIELD_NAMES, ["enable", "enabled_value"]);
+        assert_eq!(
+            CfgStruct::default(),
+            CfgStruct {
+                enable: true,
+                enabled_value: 0.0,
+            }
+        );
+        assert_eq!(
+            CfgStructBuilder::default().with_enabled_value(2.0).build(),
+            Ok(CfgStruct {
+                enable: true,
+                enabled_value: 2.0,
+            })
+        );
+    }
+
+    #[test]
+    fn flatten_cfg_option() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct CfgOptionStruct {
+                enable: bool,
+                #[flatten(cfg(any()), prefix = "disabled_")]
+                disabled: CfgOptionFields,
+                #[cfg(all())]
+                #[flatten(cfg(not(any())), reconstruct)]
+                enabled: CfgOptionFields,
+                #[cfg(any())]
+                #[flatten(cfg(all()), prefix = "mixed_")]
+                mixed: CfgOptionFields,
+            }
+
+            #[derive(Debug, PartialEq)]
+            struct CfgOptionFields {
+                value: f32,
+            }
+        );
+
+        let cfg_option_struct = CfgOptionStruct {
+            enable: true,
+            value: 1.0,
+        };
+        assert_eq!(
+            cfg_option_struct.to_enabled(),
+            CfgOptionFields { value: 1.0 }
+        );
+        assert_eq!(CfgOptionStruct::FIELD_NAMES, ["enable", "value"]);
+    }
+
+    #[test]
+    fn flatten_cfg_attr() {
+        flatten_structs!(
+            #[cfg_attr(all(), derive(Debug, Serialize))]
+            #[cfg_attr(any(), derive(Clone))]
+            #[cfg_attr(all(), serde(rename_all = "camelCase"))]
+            struct CfgAttrStruct {
+                #[cfg_attr(all(), serde(rename = "on"))]
+                enable: bool,
+                #[cfg_attr(all(), allow(unused))]
+                #[flatten]
+                #[cfg_attr(any(), serde(skip))]
+                nested: CfgAttrNested,
+            }
+        );
+
+        flatten_structs!(
+            #[cfg_attr(all(), derive(Debug, Serialize))]
+            #[allow(unused)]
+            struct CfgAttrNested {
+                #[cfg_attr(all(), serde(rename = "value"))]
+                inner_value: f32,
+                #[cfg_attr(any(), serde(skip))]
+                other_value: f32,
+            }
+        );
+
+        let cfg_attr_struct = CfgAttrStruct {
+            enable: true,
+            inner_value: 1.0,
+            other_value: 2.0,
+        };
+        assert_eq!(
+            serde_json::to_string(&cfg_attr_struct).unwrap(),
+            r#"{"on":true,"value":1.0,"otherValue":2.0}"#
+        );
+    }
+
+    #[test]
+    fn flatten_skip() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct SkipStruct {
+                #[flatten(skip(internal_id, cache), rename(value = renamed))]
+                nested: SkipNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct SkipNested {
+                internal_id: u32,
+                value: f32,
+                cache: Vec<u8>,
+            }
+        );
+
+        let skip_struct = SkipStruct { renamed: 1.0 };
+        assert_eq!(SkipStruct::FIELD_NAMES, ["renamed"]);
+        assert_eq!(skip_struct.renamed, 1.0);
+    }
+
+    #[test]
+    fn flatten_only() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct OnlyStruct {
+                #[flatten(prefix = "range_", only(max, min))]
+                range: OnlyNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct OnlyNested {
+                min: f32,
+                step: f32,
+                max: f32,
+            }
+        );
+
+        let only_struct = OnlyStruct {
+            range_min: 0.0,
+            range_max: 1.0,
+        };
+        assert_eq!(OnlyStruct::FIELD_NAMES, ["range_min", "range_max"]);
+        assert_eq!(only_struct.range_max, 1.0);
+    }
+
+    #[test]
+    fn flatten_retain() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct RetainStruct {
+                enable: bool,
+                #[flatten(retain, prefix = "nested_")]
+                nested: RetainNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Debug, Clone, Copy, PartialEq)]
+            struct RetainNested {
+                value_0: f32,
+                value_1: f32,
+            }
+        );
+
+        let nested = RetainNested {
+            value_0: 0.0,
+            value_1: 1.0,
+        };
+        let retain_struct = RetainStruct {
+            enable: true,
+            nested,
+            nested_value_0: nested.value_0,
+            nested_value_1: nested.value_1,
+        };
+        assert_eq!(
+            RetainStruct::FIELD_NAMES,
+            ["enable", "nested", "nested_value_0", "nested_value_1"]
+        );
+        assert_eq!(retain_struct.nested, nested);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn flatten_struct_attrs() {
+        flatten_structs!(
+            #[allow(unused)]
+            #[non_exhaustive]
+            #[repr(C)]
+            #[deprecated = "use `AttrsNested` instead"]
+            struct AttrsStruct {
+                tag: u8,
+                #[flatten]
+                nested: AttrsNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[repr(C)]
+            struct AttrsNested {
+                value: u32,
+            }
+        );
+
+        let attrs_struct = AttrsStruct { tag: 1, value: 2 };
+        assert_eq!(core::mem::offset_of!(AttrsStruct, tag), 0);
+        assert_eq!(core::mem::offset_of!(AttrsStruct, value), 4);
+        assert_eq!(attrs_struct.value, 2);
+    }
+
+    #[test]
+    fn flatten_packed_layout() {
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Clone, Copy)]
+            #[repr(C, packed)]
+            struct PackedFrame {
+                tag: u8,
+                #[flatten]
+                header: PackedHeader,
+                payload: [u8; 4],
+            }
+
+            #[allow(unused)]
+            #[repr(C)]
+            struct PackedHeader {
+                length: u32,
+                magic: [u8; 3],
+            }
+        );
+
+        // Nesting the header would pad the frame to the alignment of `u32`
+        assert_eq!(core::mem::size_of::<PackedFrame>(), 12);
+        assert_eq!(core::mem::size_of::<PackedHeader>(), 8);
+        assert_eq!(core::mem::offset_of!(PackedFrame, length), 1);
+        assert_eq!(core::mem::offset_of!(PackedFrame, payload), 8);
+    }
+
+    #[cfg(feature = "layout")]
+    #[test]
+    fn flatten_derive_layout() {
+        flatten_structs!(
+            #[allow(unused)]
+            #[repr(C)]
+            #[flatten_derive(layout)]
+            struct LayoutFrame<T> {
+                tag: u8,
+                #[flatten]
+                header: LayoutHeader,
+                payload: T,
+            }
+
+            #[allow(unused)]
+            struct LayoutHeader {
+                length: u32,
+                flag: bool,
+            }
+        );
+
+        let layout = LayoutFrame::<u16>::LAYOUT;
+        assert_eq!(layout.size, 12);
+        assert_eq!(layout.fields[1].range(), 4..8);
+        assert_eq!(layout.padding(), 4);
+        assert_eq!(
+            layout.to_string(),
+            "`LayoutFrame`: 12 bytes, aligned to 4 bytes
+  0..1 `tag`
+  1..4 padding
+  4..8 `length`
+  8..9 `flag`
+  9..10 padding
+  10..12 `payload`"
+        );
+    }
+
+    #[test]
+    fn flatten_raw_identifiers() {
+        flatten_structs!(
+            #[allow(unused)]
+            #[flatten_derive(builder)]
+            struct RawStruct {
+                r#type: u8,
+                #[flatten(prefix = "inner_")]
+                inner: RawNested,
+                #[flatten(rename(r#type = r#match))]
+                renamed: RawNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct RawNested {
+                r#type: u8,
+            }
+        );
+
+        let raw_struct = RawStruct {
+            r#type: 0,
+            inner_type: 1,
+            r#match: 2,
+        };
+        assert_eq!(RawStruct::FIELD_NAMES, ["type", "inner_type", "match"]);
+        assert_eq!(raw_struct.r#match, 2);
+        assert_eq!(
+            RawStructBuilder::default().with_type(0).build().err(),
+            Some(crate::MissingField {
+                field: "inner_type"
+            })
+        );
+    }
+
+    #[test]
+    fn flatten_many_fields() {
+        // Declares 200 fields, which needs to stay below the default
+        // recursion limit
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Default)]
+            struct ManyStruct {
+                f0: u8,
+                f1: u8,
+                f2: u8,
+                f3: u8,
+                f4: u8,
+                f5: u8,
+                f6: u8,
+                f7: u8,
+                f8: u8,
+                f9: u8,
+                f10: u8,
+                f11: u8,
+                f12: u8,
+                f13: u8,
+                f14: u8,
+                f15: u8,
+                f16: u8,
+                f17: u8,
+                f18: u8,
+                f19: u8,
+                f20: u8,
+                f21: u8,
+                f22: u8,
+                f23: u8,
+                f24: u8,
+                f25: u8,
+                f26: u8,
+                f27: u8,
+                f28: u8,
+                f29: u8,
+                f30: u8,
+                f31: u8,
+                f32: u8,
+                f33: u8,
+                f34: u8,
+                f35: u8,
+                f36: u8,
+                f37: u8,
+                f38: u8,
+                f39: u8,
+                f40: u8,
+                f41: u8,
+                f42: u8,
+                f43: u8,
+                f44: u8,
+                f45: u8,
+                f46: u8,
+                f47: u8,
+                f48: u8,
+                f49: u8,
+                f50: u8,
+                f51: u8,
+                f52: u8,
+                f53: u8,
+                f54: u8,
+                f55: u8,
+                f56: u8,
+                f57: u8,
+                f58: u8,
+                f59: u8,
+                f60: u8,
+                f61: u8,
+                f62: u8,
+                f63: u8,
+                f64: u8,
+                f65: u8,
+                f66: u8,
+                f67: u8,
+                f68: u8,
+                f69: u8,
+                f70: u8,
+                f71: u8,
+                f72: u8,
+                f73: u8,
+                f74: u8,
+                f75: u8,
+                f76: u8,
+                f77: u8,
+                f78: u8,
+                f79: u8,
+                f80: u8,
+                f81: u8,
+                f82: u8,
+                f83: u8,
+                f84: u8,
+                f85: u8,
+                f86: u8,
+                f87: u8,
+                f88: u8,
+                f89: u8,
+                f90: u8,
+                f91: u8,
+                f92: u8,
+                f93: u8,
+                f94: u8,
+                f95: u8,
+                f96: u8,
+                f97: u8,
+                f98: u8,
+                f99: u8,
+                f100: u8,
+                f101: u8,
+                f102: u8,
+                f103: u8,
+                f104: u8,
+                f105: u8,
+                f106: u8,
+                f107: u8,
+                f108: u8,
+                f109: u8,
+                f110: u8,
+                f111: u8,
+                f112: u8,
+                f113: u8,
+                f114: u8,
+                f115: u8,
+                f116: u8,
+                f117: u8,
+                f118: u8,
+                f119: u8,
+                f120: u8,
+                f121: u8,
+                f122: u8,
+                f123: u8,
+                f124: u8,
+                f125: u8,
+                f126: u8,
+                f127: u8,
+                f128: u8,
+                f129: u8,
+                f130: u8,
+                f131: u8,
+                f132: u8,
+                f133: u8,
+                f134: u8,
+                f135: u8,
+                f136: u8,
+                f137: u8,
+                f138: u8,
+                f139: u8,
+                f140: u8,
+                f141: u8,
+                f142: u8,
+                f143: u8,
+                f144: u8,
+                f145: u8,
+                f146: u8,
+                f147: u8,
+                f148: u8,
+                f149: u8,
+                f150: u8,
+                f151: u8,
+                f152: u8,
+                f153: u8,
+                f154: u8,
+                f155: u8,
+                f156: u8,
+                f157: u8,
+                f158: u8,
+                f159: u8,
+                f160: u8,
+                f161: u8,
+                f162: u8,
+                f163: u8,
+                f164: u8,
+                f165: u8,
+                f166: u8,
+                f167: u8,
+                f168: u8,
+                f169: u8,
+                f170: u8,
+                f171: u8,
+                f172: u8,
+                f173: u8,
+                f174: u8,
+                f175: u8,
+                f176: u8,
+                f177: u8,
+                f178: u8,
+                f179: u8,
+                f180: u8,
+                f181: u8,
+                f182: u8,
+                f183: u8,
+                f184: u8,
+                f185: u8,
+                f186: u8,
+                f187: u8,
+                f188: u8,
+                f189: u8,
+                #[flatten]
+                nested: ManyNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct ManyNested {
+                n0: u8,
+                n1: u8,
+                n2: u8,
+                n3: u8,
+                n4: u8,
+                n5: u8,
+                n6: u8,
+                n7: u8,
+                n8: u8,
+                n9: u8,
+            }
+        );
+
+        assert_eq!(ManyStruct::FIELD_NAMES.len(), 200);
+        assert_eq!(ManyStruct::default().n9, 0);
+    }
+
+    #[test]
+    fn flatten_many_flattened_fields() {
+        // Flattens 40 groups, which needs to stay below the default recursion
+        // limit
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Default)]
+            struct Many {
+                #[flatten]
+                group_0: Group0,
+                #[flatten]
+                group_1: Group1,
+                #[flatten]
+                group_2: Group2,
+                #[flatten]
+                group_3: Group3,
+                #[flatten]
+                group_4: Group4,
+                #[flatten]
+                group_5: Group5,
+                #[flatten]
+                group_6: Group6,
+                #[flatten]
+                group_7: Group7,
+                #[flatten]
+                group_8: Group8,
+                #[flatten]
+                group_9: Group9,
+                #[flatten]
+                group_10: Group10,
+                #[flatten]
+                group_11: Group11,
+                #[flatten]
+                group_12: Group12,
+                #[flatten]
+                group_13: Group13,
+                #[flatten]
+                group_14: Group14,
+                #[flatten]
+                group_15: Group15,
+                #[flatten]
+                group_16: Group16,
+                #[flatten]
+                group_17: Group17,
+                #[flatten]
+                group_18: Group18,
+                #[flatten]
+                group_19: Group19,
+                #[flatten]
+                group_20: Group20,
+                #[flatten]
+                group_21: Group21,
+                #[flatten]
+                group_22: Group22,
+                #[flatten]
+                group_23: Group23,
+                #[flatten]
+                group_24: Group24,
+                #[flatten]
+                group_25: Group25,
+                #[flatten]
+                group_26: Group26,
+                #[flatten]
+                group_27: Group27,
+                #[flatten]
+                group_28: Group28,
+                #[flatten]
+                group_29: Group29,
+                #[flatten]
+                group_30: Group30,
+                #[flatten]
+                group_31: Group31,
+                #[flatten]
+                group_32: Group32,
+                #[flatten]
+                group_33: Group33,
+                #[flatten]
+                group_34: Group34,
+                #[flatten]
+                group_35: Group35,
+                #[flatten]
+                group_36: Group36,
+                #[flatten]
+                group_37: Group37,
+                #[flatten]
+                group_38: Group38,
+                #[flatten]
+                group_39: Group39,
+            }
+            group Group0 { first_0: u8, second_0: u8 }
+            group Group1 { first_1: u8, second_1: u8 }
+            group Group2 { first_2: u8, second_2: u8 }
+            group Group3 { first_3: u8, second_3: u8 }
+            group Group4 { first_4: u8, second_4: u8 }
+            group Group5 { first_5: u8, second_5: u8 }
+            group Group6 { first_6: u8, second_6: u8 }
+            group Group7 { first_7: u8, second_7: u8 }
+            group Group8 { first_8: u8, second_8: u8 }
+            group Group9 { first_9: u8, second_9: u8 }
+            group Group10 { first_10: u8, second_10: u8 }
+            group Group11 { first_11: u8, second_11: u8 }
+            group Group12 { first_12: u8, second_12: u8 }
+            group Group13 { first_13: u8, second_13: u8 }
+            group Group14 { first_14: u8, second_14: u8 }
+            group Group15 { first_15: u8, second_15: u8 }
+            group Group16 { first_16: u8, second_16: u8 }
+            group Group17 { first_17: u8, second_17: u8 }
+            group Group18 { first_18: u8, second_18: u8 }
+            group Group19 { first_19: u8, second_19: u8 }
+            group Group20 { first_20: u8, second_20: u8 }
+            group Group21 { first_21: u8, second_21: u8 }
+            group Group22 { first_22: u8, second_22: u8 }
+            group Group23 { first_23: u8, second_23: u8 }
+            group Group24 { first_24: u8, second_24: u8 }
+            group Group25 { first_25: u8, second_25: u8 }
+            group Group26 { first_26: u8, second_26: u8 }
+            group Group27 { first_27: u8, second_27: u8 }
+            group Group28 { first_28: u8, second_28: u8 }
+            group Group29 { first_29: u8, second_29: u8 }
+            group Group30 { first_30: u8, second_30: u8 }
+            group Group31 { first_31: u8, second_31: u8 }
+            group Group32 { first_32: u8, second_32: u8 }
+            group Group33 { first_33: u8, second_33: u8 }
+            group Group34 { first_34: u8, second_34: u8 }
+            group Group35 { first_35: u8, second_35: u8 }
+            group Group36 { first_36: u8, second_36: u8 }
+            group Group37 { first_37: u8, second_37: u8 }
+            group Group38 { first_38: u8, second_38: u8 }
+            group Group39 { first_39: u8, second_39: u8 }
+        );
+
+        let many = Many {
+            first_39: 1,
+            second_39: 2,
+            ..Many::default()
+        };
+        assert_eq!(Many::FIELD_COUNT, 80);
+        assert_eq!(Many::FIELD_NAMES[..2], ["first_0", "second_0"]);
+        assert_eq!((many.first_0, many.first_39, many.second_39), (0, 1, 2));
+    }
+
+    #[test]
+    fn flatten_many_prefixed_fields() {
+        // Flattens the same group with 12 prefixes, which needs to stay below
+        // the default recursion limit
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Default)]
+            struct Sensors {
+                #[flatten(prefix = "sensor_0_")]
+                sensor_0: Sensor,
+                #[flatten(prefix = "sensor_1_")]
+                sensor_1: Sensor,
+                #[flatten(prefix = "sensor_2_")]
+                sensor_2: Sensor,
+                #[flatten(prefix = "sensor_3_")]
+                sensor_3: Sensor,
+                #[flatten(prefix = "sensor_4_")]
+                sensor_4: Sensor,
+                #[flatten(prefix = "sensor_5_")]
+                sensor_5: Sensor,
+                #[flatten(prefix = "sensor_6_")]
+                sensor_6: Sensor,
+                #[flatten(prefix = "sensor_7_")]
+                sensor_7: Sensor,
+                #[flatten(prefix = "sensor_8_")]
+                sensor_8: Sensor,
+                #[flatten(prefix = "sensor_9_")]
+                sensor_9: Sensor,
+                #[flatten(prefix = "sensor_10_")]
+                sensor_10: Sensor,
+                #[flatten(prefix = "sensor_11_")]
+                sensor_11: Sensor,
+            }
+            group Sensor {
+                value: f32,
+                unit: u8,
+            }
+        );
+
+        let sensors = Sensors {
+            sensor_11_value: 1.0,
+            ..Sensors::default()
+        };
+        assert_eq!(Sensors::FIELD_COUNT, 24);
+        assert_eq!(
+            Sensors::FIELD_NAMES[..2],
+            ["sensor_0_value", "sensor_0_unit"]
+        );
+        assert_eq!(
+            (sensors.sensor_0_value, sensors.sensor_11_value),
+            (0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn flatten_assert_unique() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct AssertStruct {
+                value: f32,
+                #[flatten(assert_unique, prefix = "nested_")]
+                nested: AssertNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct AssertNested {
+                value: f32,
+            }
+        );
+
+        let assert_struct = AssertStruct {
+            value: 0.0,
+            nested_value: 1.0,
+        };
+        assert_eq!(assert_struct.nested_value, 1.0);
+        assert_eq!(
+            crate::__private_count_field(&["value", "nested_value", "value"], "value"),
+            2
+        );
+    }
+
+    #[test]
+    fn flatten_via() {
+        type ViaAlias = ViaNested<u16>;
+        type OtherAlias = ViaOther;
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct ViaStruct {
+                #[flatten(prefix = "first_", via = ViaNested<u16>, reconstruct)]
+                first: ViaAlias,
+                #[flatten(via = ViaOther)]
+                other: OtherAlias,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Debug, Clone, PartialEq)]
+            struct ViaNested<T> {
+                value: T,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct ViaOther {
+                enable: bool,
+            }
+        );
+
+        let via_struct = ViaStruct {
+            first_value: 1,
+            enable: true,
+        };
+        let first: ViaAlias = via_struct.to_first();
+        let other: OtherAlias = ViaOther {
+            enable: via_struct.enable,
+        };
+        assert_eq!(first, ViaNested { value: 1 });
+        assert!(other.enable);
+    }
+
+    #[test]
+    fn flatten_via_associated_type() {
+        #[allow(unused)]
+        trait ViaSchema {
+            type Group;
+        }
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct ViaAssocStruct {
+                enable: bool,
+                #[flatten(via = ViaAssocGroup)]
+                group: <Self as ViaSchema>::Group,
+            }
+
+            #[allow(unused)]
+            struct ViaAssocGroup {
+                value: u32,
+            }
+        );
+
+        impl ViaSchema for ViaAssocStruct {
+            type Group = ViaAssocGroup;
+        }
+
+        let via_struct = ViaAssocStruct {
+            enable: true,
+            value: 1,
+        };
+        assert!(via_struct.enable);
+        assert_eq!(via_struct.value, 1);
+        assert_eq!(ViaAssocStruct::FIELD_NAMES, ["enable", "value"]);
+    }
+
+    #[test]
+    fn flatten_derive_default() {
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(default)]
+            struct DefaultDeriveStruct {
+                enable: bool,
+                #[flatten]
+                nested: DefaultDeriveNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[flatten_derive(default)]
+            struct DefaultDeriveNested {
+                value: u8,
+                retries: u8 = 3,
+            }
+        );
+
+        assert_eq!(
+            DefaultDeriveStruct::default(),
+            DefaultDeriveStruct {
+                enable: false,
+                value: 0,
+                retries: 3,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(default)]
+            struct PlainDefaultStruct {
+                enable: bool,
+                value: u8,
+            }
+        );
+
+        assert_eq!(
+            PlainDefaultStruct::default(),
+            PlainDefaultStruct {
+                enable: false,
+                value: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn flatten_vis() {
+        mod vis {
+            flatten_structs!(
+                #[allow(unused)]
+                pub struct VisStruct {
+                    #[flatten(vis(pub(super)), prefix = "public_")]
+                    pub public: VisNested,
+                    #[flatten(vis())]
+                    pub private: VisNested,
+                }
+            );
+
+            flatten_structs!(
+                #[allow(unused)]
+                pub struct VisNested {
+                    pub value: u8,
+                }
+            );
+
+            impl VisStruct {
+                pub fn new() -> Self {
+                    Self {
+                        public_value: 1,
+                        value: 2,
+                    }
+                }
+            }
+        }
+
+        let vis_struct = vis::VisStruct::new();
+        assert_eq!(vis_struct.public_value, 1);
+    }
+
+    #[test]
+    fn flatten_vis_inherit_source() {
+        mod source_vis {
+            flatten_structs!(
+                #[allow(unused)]
+                pub struct SourceVisStruct {
+                    #[flatten(vis = inherit_source)]
+                    pub account: SourceVisAccount,
+                    #[flatten(vis = inherit_flatten, prefix = "backup_")]
+                    pub backup: SourceVisAccount,
+                }
+
+                #[allow(unused)]
+                pub struct SourceVisAccount {
+                    pub name: &'static str,
+                    secret: u64,
+                }
+            );
+
+            impl SourceVisStruct {
+                pub fn new() -> Self {
+                    Self {
+                        name: "name",
+                        secret: 1,
+                        backup_name: "backup",
+                        backup_secret: 2,
+                    }
+                }
+
+                pub fn secret(&self) -> u64 {
+                    self.secret
+                }
+            }
+        }
+
+        let source_vis = source_vis::SourceVisStruct::new();
+        assert_eq!(source_vis.name, "name");
+        assert_eq!(source_vis.secret(), 1);
+        assert_eq!(source_vis.backup_secret, 2);
+    }
+
+    // The types are flattened through the modules `#[flatten_mod(...)]`
+    // declares for them, from another module like in a crate
+    mod flatten_mod {
+        mod inner {
+            flatten_structs!(
+                #[derive(Clone, Debug, PartialEq)]
+                #[flatten_mod(sensor_fields)]
+                pub struct Sensor {
+                    pub value: f32,
+                }
+            );
+
+            flatten_structs!(
+                #[derive(Clone, Debug, PartialEq)]
+                #[flatten_mod(range_fields)]
+                pub struct Range<T> {
+                    pub start: T,
+                    pub end: T,
+                }
+            );
+
+            flatten_structs!(
+                #[flatten_mod(pair_fields)]
+                pub struct Pair(pub u8, pub u8);
+            );
+        }
+
+        flatten_structs!(
+            struct ModStruct {
+                #[flatten(reconstruct)]
+                sensor: inner::sensor_fields::Sensor,
+                #[flatten(into_nested)]
+                range: inner::range_fields::Range<u8>,
+            }
+        );
+
+        flatten_structs!(
+            struct ModTuple(#[flatten] inner::pair_fields::Pair);
+        );
+
+        #[test]
+        fn flatten_mod() {
+            let mod_struct = ModStruct {
+                value: 1.0,
+                start: 2,
+                end: 3,
+            };
+            assert_eq!(mod_struct.to_sensor(), inner::Sensor { value: 1.0 });
+            let range: inner::Range<u8> = (&mod_struct).into();
+            assert_eq!(range, inner::Range { start: 2, end: 3 });
+            let mod_tuple = ModTuple(4, 5);
+            assert_eq!((mod_tuple.0, mod_tuple.1), (4, 5));
+            let pair = inner::Pair(6, 7);
+            assert_eq!((pair.0, pair.1), (6, 7));
+        }
+    }
+
+    // Declared at module level, where items can be used before they are
+    // declared like any other item
+    mod flatten_declaration_order {
+        flatten_structs!(
+            #[allow(unused)]
+            struct OrderFirst {
+                #[flatten]
+                second: OrderSecond,
+                first: u8,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct OrderSecond {
+                #[flatten]
+                third: later::OrderThird,
+                second: u8,
+            }
+        );
+
+        mod later {
+            flatten_structs!(
+                #[allow(unused)]
+                pub struct OrderThird {
+                    pub third: u8,
+                }
+            );
+        }
+
+        #[test]
+        fn flatten_declaration_order() {
+            assert_eq!(OrderFirst::FIELD_NAMES, ["third", "second", "first"]);
+        }
+    }
+
+    // Absolute paths need the modules to be declared outside of a function
+    mod flatten_paths {
+        mod schema {
+            mod inner {
+                flatten_structs!(
+                    #[allow(unused)]
+                    pub struct Header {
+                        pub id: u32,
+                    }
+                );
+            }
+
+            // Imports the inspection macro along with the struct
+            pub use inner::Header;
+
+            pub mod frame {
+                flatten_structs!(
+                    #[allow(unused)]
+                    pub struct Footer {
+                        pub crc: u32,
+                    }
+                );
+
+                flatten_structs!(
+                    #[allow(unused)]
+                    pub struct Trailer {
+                        #[flatten]
+                        pub footer: self::Footer,
+                        #[flatten(prefix = "header_")]
+                        pub header: super::Header,
+                    }
+                );
+            }
+        }
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct PathFrame {
+                #[flatten]
+                header: crate::test::flatten_paths::schema::Header,
+                #[flatten(prefix = "trailer_")]
+                trailer: Option<schema::frame::Trailer>,
+            }
+        );
+
+        #[test]
+        fn flatten_paths() {
+            assert_eq!(
+                PathFrame::FIELD_NAMES,
+                ["id", "trailer_crc", "trailer_header_id"]
+            );
+            let path_frame = PathFrame {
+                id: 1,
+                trailer_crc: Some(2),
+                trailer_header_id: None,
+            };
+            assert_eq!(path_frame.trailer_crc, Some(2));
+        }
+    }
+
+    // `single_use_lifetimes` skips impls marked `#[automatically_derived]`,
+    // every generated trait impl of a struct with a lifetime would fail the
+    // lint otherwise. Lints are configured per module, the generated impls
+    // can't take attributes of the test.
+    #[deny(single_use_lifetimes)]
+    mod flatten_automatically_derived {
+        flatten_structs!(
+            #[allow(unused)]
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(default, builder, patch, display)]
+            struct AutoDerived<'a> {
+                name: &'a str,
+                #[flatten(into_nested)]
+                sensor: AutoSensor,
+            }
+
+            #[allow(unused)]
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(split)]
+            struct AutoSplit<'a> {
+                #[flatten]
+                range: AutoRange<&'a str>,
+                #[flatten]
+                sensor: AutoSensor,
+            }
+
+            #[allow(unused)]
+            #[derive(Debug, PartialEq)]
+            struct AutoSensor {
+                value: u8,
+            }
+
+            #[allow(unused)]
+            #[derive(Debug, PartialEq)]
+            struct AutoRange<T> {
+                start: T,
+                end: T,
+            }
+        );
+
+        #[test]
+        fn flatten_automatically_derived() {
+            let auto_derived = AutoDerived::default();
+            assert_eq!(auto_derived, AutoDerived { name: "", value: 0 });
+            let sensor: AutoSensor = (&auto_derived).into();
+            assert_eq!(sensor, AutoSensor { value: 0 });
+            let auto_split = AutoSplit {
+                start: "a",
+                end: "b",
+                value: 1,
+            };
+            let (range, sensor) = auto_split.into();
+            assert_eq!(
+                range,
+                AutoRange {
+                    start: "a",
+                    end: "b"
+                }
+            );
+            assert_eq!(sensor, AutoSensor { value: 1 });
+        }
+    }
+
+    // Flattenable types of several modules re-exported from a central module
+    // are flattened by their path in it
+    mod flatten_central_reexport {
+        mod net {
+            flatten_structs!(
+                #[allow(unused)]
+                pub struct Header {
+                    pub id: u32,
+                }
+            );
+        }
+
+        mod storage {
+            flatten_structs!(
+                #[allow(unused)]
+                pub struct Record {
+                    pub offset: u64,
+                }
+            );
+        }
+
+        mod fields {
+            pub(crate) use super::net::Header;
+            pub(crate) use super::storage::Record;
+        }
+
+        mod frame {
+            flatten_structs!(
+                #[allow(unused)]
+                pub struct CentralFrame {
+                    #[flatten]
+                    pub header: crate::test::flatten_central_reexport::fields::Header,
+                    #[flatten]
+                    pub record: super::fields::Record,
+                }
+            );
+        }
+
+        #[test]
+        fn flatten_central_reexport() {
+            assert_eq!(frame::CentralFrame::FIELD_NAMES, ["id", "offset"]);
+            let central_frame = frame::CentralFrame { id: 1, offset: 2 };
+            assert_eq!((central_frame.id, central_frame.offset), (1, 2));
+        }
+    }
+
+    // `pub(in path)` needs a path to a module, which can't be declared in a
+    // function
+    mod pub_in_path {
+        pub mod inner {
+            flatten_structs!(
+                #[allow(unused)]
+                #[flatten_derive(accessors)]
+                pub(in crate::test::pub_in_path) struct PubInStruct {
+                    pub(in crate::test::pub_in_path) enable: bool,
+                    #[flatten(view)]
+                    pub(in crate::test::pub_in_path) nested: PubInNested,
+                }
+            );
+
+            flatten_structs!(
+                #[allow(unused)]
+                pub(in crate::test::pub_in_path) struct PubInNested {
+                    pub(in crate::test::pub_in_path) value: u8,
+                }
+            );
+
+            flatten_structs!(
+                #[allow(unused)]
+                pub(in crate::test::pub_in_path) struct PubInTuple(
+                    pub(in crate::test::pub_in_path) u8,
+                    #[flatten] pub(in crate::test::pub_in_path) PubInNested,
+                );
+            );
+        }
+
+        #[test]
+        fn flatten_pub_in_path() {
+            let pub_in_struct = inner::PubInStruct {
+                enable: true,
+                value: 1,
+            };
+            let pub_in_tuple = inner::PubInTuple(2, 3);
+            assert_eq!(*pub_in_struct.nested().value, 1);
+            assert_eq!(*pub_in_struct.value(), 1);
+            assert_eq!(pub_in_tuple.1, 3);
+        }
+    }
+
+    #[test]
+    fn flatten_nested_prefix() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct PrefixOuter {
+                #[flatten(prefix = "b_")]
+                b: PrefixMiddle,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct PrefixMiddle {
+                value: f32,
+                #[flatten(prefix = "c_", suffix = "_inner")]
+                c: PrefixInner,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct PrefixInner {
+                field: f32,
+            }
+        );
+
+        assert_eq!(PrefixOuter::FIELD_NAMES, ["b_value", "b_c_field_inner"]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn flatten_serde() {
+        flatten_structs!(
+            #[derive(Serialize, Deserialize, Debug, PartialEq)]
+            struct SerdeFlattenStruct {
+                enable: bool,
+                #[flatten(serde)]
+                nested: SerdeFlattenNested,
+            }
+        );
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct SerdeFlattenNested {
+            value: f32,
+        }
+
+        let serde_struct = SerdeFlattenStruct {
+            enable: true,
+            nested: SerdeFlattenNested { value: 1.0 },
+        };
+        let json = serde_json::to_string(&serde_struct).unwrap();
+        assert_eq!(json, r#"{"enable":true,"value":1.0}"#);
+        assert_eq!(
+            serde_json::from_str::<SerdeFlattenStruct>(&json).unwrap(),
+            serde_struct
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn flatten_serde_default() {
+        flatten_structs!(
+            #[derive(Serialize, Deserialize, Debug, PartialEq)]
+            struct SerdeDefaultStruct {
+                enable: bool,
+                #[flatten(serde_default)]
+                limits: SerdeDefaultLimits,
+            }
+
+            #[allow(unused)]
+            #[derive(Deserialize)]
+            struct SerdeDefaultLimits {
+                max: u32,
+                #[serde(rename = "minimum")]
+                min: u32,
+            }
+        );
+
+        assert_eq!(
+            serde_json::from_str::<SerdeDefaultStruct>(r#"{"enable":true,"minimum":2}"#).unwrap(),
+            SerdeDefaultStruct {
+                enable: true,
+                max: 0,
+                min: 2,
+            }
+        );
+        assert!(serde_json::from_str::<SerdeDefaultStruct>(r#"{"max":1}"#).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn flatten_serde_accept_nested() {
+        flatten_structs!(
+            #[derive(Serialize, Debug, PartialEq)]
+            #[serde(rename_all = "camelCase")]
+            struct AcceptNestedStruct<T> {
+                is_enabled: bool,
+                #[flatten(prefix = "limit_", serde_accept_nested)]
+                limits: AcceptNestedLimits<T>,
+                #[flatten]
+                other: AcceptNestedOther,
+            }
+
+            #[allow(unused)]
+            #[derive(Deserialize)]
+            struct AcceptNestedLimits<T> {
+                max: T,
+                #[serde(rename = "minimum")]
+                min: T,
+            }
+
+            #[allow(unused)]
+            #[derive(Deserialize)]
+            struct AcceptNestedOther {
+                #[serde(default)]
+                name: u8,
+            }
+        );
+
+        let expected = AcceptNestedStruct {
+            is_enabled: true,
+            limit_max: 3,
+            limit_min: 1,
+            name: 0,
+        };
+        assert_eq!(
+            serde_json::from_str::<AcceptNestedStruct<u32>>(
+                r#"{"isEnabled":true,"limitMax":3,"minimum":1}"#
+            )
+            .unwrap(),
+            expected
+        );
+        assert_eq!(
+            serde_json::from_str::<AcceptNestedStruct<u32>>(
+                r#"{"isEnabled":true,"limits":{"max":3,"minimum":1}}"#
+            )
+            .unwrap(),
+            expected
+        );
+        assert!(
+            serde_json::from_str::<AcceptNestedStruct<u32>>(
+                r#"{"isEnabled":true,"limits":{"max":3}}"#
+            )
+            .is_err()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn flatten_serde_accept_nested_box() {
+        flatten_structs!(
+            #[derive(Serialize, Debug, PartialEq)]
+            struct AcceptNestedBoxStruct {
+                #[flatten(serde_accept_nested)]
+                limits: Box<AcceptNestedBoxLimits>,
+            }
+
+            #[allow(unused)]
+            #[derive(Deserialize)]
+            struct AcceptNestedBoxLimits {
+                max: u32,
+                min: u32,
+            }
+        );
+
+        let expected = AcceptNestedBoxStruct { max: 3, min: 1 };
+        assert_eq!(
+            serde_json::from_str::<AcceptNestedBoxStruct>(r#"{"max":3,"min":1}"#).unwrap(),
+            expected
+        );
+        assert_eq!(
+            serde_json::from_str::<AcceptNestedBoxStruct>(r#"{"limits":{"max":3,"min":1}}"#)
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn flatten_derive_try_from_map() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(try_from_map)]
+            struct MapStruct<T>
+            where
+                T: Clone + Default,
+            {
+                enable: bool,
+                #[flatten(prefix = "limit_")]
+                limits: MapLimits,
+                value: T,
+            }
+
+            #[allow(unused)]
+            struct MapLimits {
+                max: u32 = 10,
+                min: u32,
+            }
+        );
+
+        let map = |value: serde_json::Value| value.as_object().unwrap().clone();
+        assert_eq!(
+            MapStruct::try_from(map(serde_json::json!({
+                "enable": true,
+                "limit_min": 1,
+                "value": "map",
+            })))
+            .unwrap(),
+            MapStruct {
+                enable: true,
+                limit_max: 10,
+                limit_min: 1,
+                value: String::from("map"),
+            }
+        );
+        let error = MapStruct::<String>::try_from(map(serde_json::json!({
+            "enable": 1,
+            "value": "map",
+            "limit_mid": 5,
+        })))
+        .unwrap_err();
+        assert_eq!(error.missing, ["limit_min"]);
+        assert_eq!(error.unknown, ["limit_mid"]);
+        assert_eq!(error.invalid[0].0, "enable");
+        assert_eq!(
+            error.to_string(),
+            "missing field `limit_min`, unknown field `limit_mid`, \
+             invalid field `enable`: invalid type: integer `1`, expected a boolean"
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn flatten_derive_try_from_map_many_groups() {
+        // Flattens the same group with 8 prefixes, the keys of the fields are
+        // looked up without growing the recursion depth per group
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(try_from_map)]
+            struct MapChannels {
+                #[flatten(prefix = "channel_0_")]
+                channel_0: MapChannel,
+                #[flatten(prefix = "channel_1_")]
+                channel_1: MapChannel,
+                #[flatten(prefix = "channel_2_")]
+                channel_2: MapChannel,
+                #[flatten(prefix = "channel_3_")]
+                channel_3: MapChannel,
+                #[flatten(prefix = "channel_4_")]
+                channel_4: MapChannel,
+                #[flatten(prefix = "channel_5_")]
+                channel_5: MapChannel,
+                #[flatten(prefix = "channel_6_")]
+                channel_6: MapChannel,
+                #[flatten(prefix = "channel_7_")]
+                channel_7: MapChannel,
+            }
+
+            #[allow(unused)]
+            struct MapChannel {
+                level: u8,
+                muted: bool = false,
+            }
+        );
+
+        let mut value = serde_json::Map::new();
+        for channel in 0..8 {
+            value.insert(format!("channel_{channel}_level"), channel.into());
+        }
+        let channels = MapChannels::try_from(value).unwrap();
+        assert_eq!((channels.channel_0_level, channels.channel_7_level), (0, 7));
+        assert!(!channels.channel_7_muted);
+        let error = MapChannels::try_from(serde_json::Map::new()).unwrap_err();
+        assert_eq!(error.missing.len(), 8);
+        assert_eq!(error.missing[7], "channel_7_level");
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn flatten_derive_try_from_map_renamed() {
+        flatten_structs!(
+            #[derive(Deserialize, Debug, PartialEq)]
+            #[serde(rename_all = "PascalCase")]
+            #[flatten_derive(try_from_map)]
+            #[flatten_as(
+                MapRenamedDto,
+                derive(Deserialize, Debug, PartialEq),
+                serde(rename_all = "camelCase"),
+                flatten_derive(try_from_map)
+            )]
+            struct MapRenamedStruct {
+                id: u32,
+                #[serde(rename = "OTHER")]
+                other: u32,
+                #[flatten(prefix = "limit_")]
+                limits: MapRenamedLimits,
+            }
+
+            #[allow(unused)]
+            #[derive(Deserialize)]
+            struct MapRenamedLimits {
+                max_value: u32,
+                #[serde(rename = "minimum")]
+                min: u32,
+            }
+        );
+
+        let value = serde_json::json!({
+            "Id": 1,
+            "OTHER": 2,
+            "LimitMaxValue": 3,
+            "minimum": 4,
+        });
+        let expected = MapRenamedStruct {
+            id: 1,
+            other: 2,
+            limit_max_value: 3,
+            limit_min: 4,
+        };
+        assert_eq!(
+            serde_json::from_value::<MapRenamedStruct>(value.clone()).unwrap(),
+            expected
+        );
+        assert_eq!(
+            MapRenamedStruct::try_from(value.as_object().unwrap().clone()).unwrap(),
+            expected
+        );
+        let error = MapRenamedStruct::try_from(
+            serde_json::json!({ "id": 1, "OTHER": 2, "minimum": 4 })
+                .as_object()
+                .unwrap()
+                .clone(),
+        )
+        .unwrap_err();
+        assert_eq!(error.missing, ["Id", "LimitMaxValue"]);
+        assert_eq!(error.unknown, ["id"]);
+
+        let dto = MapRenamedDto::try_from(
+            serde_json::json!({ "id": 1, "OTHER": 2, "limitMaxValue": 3, "minimum": 4 })
+                .as_object()
+                .unwrap()
+                .clone(),
+        )
+        .unwrap();
+        assert_eq!(dto.limit_max_value, 3);
+    }
+
+    #[test]
+    fn flatten_derive_patch() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(patch)]
+            struct PatchStruct {
+                enable: bool,
+                name: Option<String>,
+                #[flatten]
+                nested: PatchNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct PatchNested {
+                value: f32,
+                label: Option<&'static str>,
+            }
+        );
+
+        let mut patch_struct = PatchStruct {
+            enable: false,
+            name: Some("name".to_owned()),
+            value: 0.0,
+            label: Some("label"),
+        };
+        patch_struct.apply(PatchStructPatch::default());
+        patch_struct.apply(PatchStructPatch {
+            enable: Some(true),
+            value: Some(1.0),
+            label: Some(None),
+            ..Default::default()
+        });
+        assert_eq!(
+            patch_struct,
+            PatchStruct {
+                enable: true,
+                name: Some("name".to_owned()),
+                value: 1.0,
+                label: None,
+            }
+        );
+    }
+
+    #[test]
+    fn flatten_derive_patch_many_groups() {
+        // The patch and `Display` are generated for all 6 groups at once,
+        // without growing the recursion depth per group
+        flatten_structs!(
+            #[derive(Debug, Default, PartialEq)]
+            #[flatten_derive(patch, display)]
+            struct PatchStats {
+                #[flatten(prefix = "counter_0_")]
+                counter_0: PatchCounter,
+                #[flatten(prefix = "counter_1_")]
+                counter_1: PatchCounter,
+                #[flatten(prefix = "counter_2_")]
+                counter_2: PatchCounter,
+                #[flatten(prefix = "counter_3_")]
+                counter_3: PatchCounter,
+                #[flatten(prefix = "counter_4_")]
+                counter_4: PatchCounter,
+                #[flatten(prefix = "counter_5_")]
+                counter_5: PatchCounter,
+            }
+
+            #[allow(unused)]
+            group PatchCounter {
+                count: u32,
+            }
+        );
+
+        let mut stats = PatchStats::default();
+        stats.apply(PatchStatsPatch {
+            counter_0_count: Some(1),
+            counter_5_count: Some(6),
+            ..PatchStatsPatch::default()
+        });
+        assert_eq!((stats.counter_0_count, stats.counter_1_count), (1, 0));
+        assert_eq!(
+            stats.to_string(),
+            "counter_0_count = 1, counter_1_count = 0, counter_2_count = 0, \
+             counter_3_count = 0, counter_4_count = 0, counter_5_count = 6"
+        );
+    }
+
+    #[test]
+    fn flatten_option() {
+        flatten_structs!(
+            #[derive(Default, Debug, PartialEq)]
+            struct OptionStruct {
+                enable: bool,
+                #[flatten(prefix = "maybe_")]
+                maybe: Option<OptionNested>,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct OptionNested {
+                value: f32,
+                label: Option<&'static str> = Some("label"),
+            }
+        );
+
+        let option_struct = OptionStruct {
+            enable: true,
+            maybe_value: Some(1.0),
+            maybe_label: Some(None),
+        };
+        assert_eq!(option_struct.maybe_value, Some(1.0));
+        assert_eq!(option_struct.maybe_label, Some(None));
+        assert_eq!(
+            OptionStruct::default(),
+            OptionStruct {
+                enable: false,
+                maybe_value: None,
+                maybe_label: None,
+            }
+        );
+    }
+
+    #[test]
+    fn flatten_field_attr() {
+        flatten_structs!(
+            #[derive(Serialize, Deserialize, Debug, PartialEq)]
+            struct FieldAttrStruct {
+                enable: bool,
+                #[flatten(field_attr(serde(default), allow(unused)))]
+                nested: FieldAttrNested,
+            }
+        );
+
+        flatten_structs!(
+            #[derive(Serialize, Deserialize)]
+            struct FieldAttrNested {
+                value: f32,
+                #[serde(rename = "other")]
+                other_value: u32,
+            }
+        );
+
+        assert_eq!(
+            serde_json::from_str::<FieldAttrStruct>(r#"{"enable":true}"#).unwrap(),
+            FieldAttrStruct {
+                enable: true,
+                value: 0.0,
+                other_value: 0,
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<FieldAttrStruct>(r#"{"enable":true,"other":1}"#).unwrap(),
+            FieldAttrStruct {
+                enable: true,
+                value: 0.0,
+                other_value: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn flatten_alias() {
+        flatten!(
+            #[allow(unused)]
+            struct AliasStruct {
+                enable: bool,
+                #[flatten]
+                nested: AliasNested,
+            }
+        );
+
+        flatten!(
+            #[allow(unused)]
+            struct AliasNested {
+                value: f32,
+            }
+        );
+
+        assert_eq!(AliasStruct::FIELD_NAMES, ["enable", "value"]);
+    }
+
+    #[test]
+    fn flatten_as() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten_as(FlatAsDto, derive(Serialize), serde(rename_all = "camelCase"))]
+            #[flatten_as(FlatAsCopy, allow(unused))]
+            struct FlatAsStruct<T> {
+                enable_all: bool,
+                #[flatten]
+                nested: FlatAsNested,
+                extra: T,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct FlatAsNested {
+                inner_value: f32,
+            }
+        );
+
+        let flat_as_struct = FlatAsStruct {
+            enable_all: true,
+            inner_value: 1.0,
+            extra: 2,
+        };
+        assert_eq!(flat_as_struct.extra, 2);
+        let dto = FlatAsDto {
+            enable_all: flat_as_struct.enable_all,
+            inner_value: flat_as_struct.inner_value,
+            extra: flat_as_struct.extra,
+        };
+        assert_eq!(
+            serde_json::to_string(&dto).unwrap(),
+            r#"{"enableAll":true,"innerValue":1.0,"extra":2}"#
+        );
+        let copy = FlatAsCopy {
+            enable_all: false,
+            inner_value: 0.0,
+            extra: "extra",
+        };
+        assert_eq!(copy.extra, "extra");
+    }
+
+    #[test]
+    fn flatten_as_derives() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten_as(
+                FlatAsDerivesDto,
+                flatten_derive(accessors, builder),
+                derive(Debug, PartialEq)
+            )]
+            #[flatten_derive(accessors)]
+            struct FlatAsDerivesStruct {
+                enable: bool,
+                #[flatten]
+                nested: FlatAsDerivesNested,
+            }
+
+            #[allow(unused)]
+            struct FlatAsDerivesNested {
+                retries: u32 = 3,
+            }
+        );
+
+        let mut dto = FlatAsDerivesDtoBuilder::default()
+            .with_enable(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            dto,
+            FlatAsDerivesDto {
+                enable: true,
+                retries: 3,
+            }
+        );
+        dto.set_retries(5);
+        assert_eq!(*dto.retries(), 5);
+        assert!(FlatAsDerivesDtoBuilder::default().build().is_err());
+
+        let flat_as_struct = FlatAsDerivesStruct {
+            enable: *dto.enable(),
+            retries: *dto.retries(),
+        };
+        assert_eq!(*flat_as_struct.retries(), 5);
+    }
+
+    #[test]
+    fn flatten_nested_serde_flatten() {
+        flatten_structs!(
+            #[derive(Serialize, Deserialize, Debug, PartialEq)]
+            struct DynamicStruct {
+                enable: bool,
+                #[flatten]
+                nested: DynamicNested,
+            }
+        );
+
+        flatten_structs!(
+            #[derive(Serialize, Deserialize)]
+            struct DynamicNested {
+                value: f32,
+                #[serde(flatten)]
+                extra: std::collections::BTreeMap<String, serde_json::Value>,
+            }
+        );
+
+        let json = r#"{"enable":true,"value":1.0,"other":[1],"more":null}"#;
+        let dynamic_struct: DynamicStruct = serde_json::from_str(json).unwrap();
+        assert_eq!(dynamic_struct.value, 1.0);
+        assert_eq!(
+            dynamic_struct.extra.keys().collect::<Vec<_>>(),
+            ["more", "other"]
+        );
+        assert_eq!(
+            serde_json::to_string(&dynamic_struct).unwrap(),
+            r#"{"enable":true,"value":1.0,"more":null,"other":[1]}"#
+        );
+    }
+
+    #[test]
+    fn flatten_attribute_order() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct OrderStruct {
+                /// first
+                #[doc(alias = "second")]
+                /// third
+                #[allow(unused)]
+                /// fifth
+                enable: bool,
+                #[flatten]
+                nested: OrderNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct OrderNested {
+                #[allow(unused)]
+                /// nested second
+                #[doc(alias = "nested_third")]
+                /// nested fourth
+                value: f32,
+            }
+        );
+
+        macro_rules! stringify_fields {
+            (fields = { $($fields:tt)* }, $($rest:tt)*) => {
+                const FIELDS: &str = stringify!($($fields)*);
+            };
+        }
+
+        OrderStruct! {
+            call = { stringify_fields },
+            prefix = {},
+            cx = {},
+        }
+        let markers = [
+            "first",
+            "second",
+            "third",
+            "allow",
+            "fifth",
+            "enable",
+            "allow",
+            "nested second",
+            "nested_third",
+            "nested fourth",
+            "value",
+        ];
+        let mut rest = FIELDS;
+        for marker in markers {
+            let position = rest
+                .find(marker)
+                .unwrap_or_else(|| panic!("`{marker}` out of order in `{FIELDS}`"));
+            rest = &rest[position + marker.len()..];
+        }
+    }
+
+    #[test]
+    fn flatten_derive_display() {
+        flatten_structs!(
+            #[flatten_derive(display)]
+            struct DisplayStruct<T: std::fmt::Display> {
+                enable: bool,
+                r#type: &'static str,
+                #[flatten(prefix = "n_")]
+                nested: DisplayNested,
+                extra: T,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct DisplayNested {
+                value: f32,
+            }
+        );
+
+        flatten_structs!(
+            #[flatten_derive(display)]
+            struct DisplayEmpty {}
+        );
+
+        let display_struct = DisplayStruct {
+            enable: true,
+            r#type: "display",
+            n_value: 1.5,
+            extra: 2,
+        };
+        assert_eq!(
+            display_struct.to_string(),
+            "enable = true, type = display, n_value = 1.5, extra = 2"
+        );
+        assert_eq!(DisplayEmpty {}.to_string(), "");
+    }
+
+    #[test]
+    fn flatten_derive_display_many_groups() {
+        // `Display` and the builder are generated for all 6 groups at once,
+        // without growing the recursion depth per group
+        flatten_structs!(
+            #[flatten_derive(display, builder)]
+            struct DisplayRoute {
+                name: &'static str,
+                #[flatten(prefix = "stop_0_")]
+                stop_0: DisplayStop,
+                #[flatten(prefix = "stop_1_")]
+                stop_1: DisplayStop,
+                #[flatten(prefix = "stop_2_")]
+                stop_2: DisplayStop,
+                #[flatten(prefix = "stop_3_")]
+                stop_3: DisplayStop,
+                #[flatten(prefix = "stop_4_")]
+                stop_4: DisplayStop,
+                #[flatten(prefix = "stop_5_")]
+                stop_5: DisplayStop,
+            }
+
+            #[allow(unused)]
+            group DisplayStop {
+                minute: u8 = 0,
+            }
+        );
+
+        let route = DisplayRouteBuilder::default()
+            .with_name("route")
+            .with_stop_1_minute(5)
+            .with_stop_5_minute(30)
+            .build()
+            .unwrap();
+        assert_eq!(
+            route.to_string(),
+            "name = route, stop_0_minute = 0, stop_1_minute = 5, stop_2_minute = 0, \
+             stop_3_minute = 0, stop_4_minute = 0, stop_5_minute = 30"
+        );
+    }
+
+    #[test]
+    fn flatten_derive_split() {
+        flatten_structs!(
+            #[flatten_derive(split)]
+            struct SplitStruct<T> {
+                #[flatten(prefix = "n_")]
+                nested: SplitNested,
+                #[flatten(rename(value = other))]
+                other: SplitGeneric<T>,
+            }
+
+            #[flatten_derive(split)]
+            struct SplitSingle {
+                #[flatten]
+                nested: SplitNested,
+            }
+        );
+
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct SplitNested {
+                value: f32,
+                name: String,
+            }
+
+            #[derive(Debug, PartialEq)]
+            struct SplitGeneric<T> {
+                value: T,
+            }
+        );
+
+        let split_struct = SplitStruct {
+            n_value: 1.0,
+            n_name: "nested".to_string(),
+            other: 2u8,
+        };
+        let (nested, other) = split_struct.into();
+        assert_eq!(
+            nested,
+            SplitNested {
+                value: 1.0,
+                name: "nested".to_string(),
+            }
+        );
+        assert_eq!(other, SplitGeneric { value: 2 });
+        let (nested,) = SplitSingle {
+            value: 0.0,
+            name: String::new(),
+        }
+        .into();
+        assert_eq!(nested.value, 0.0);
+    }
+
+    #[test]
+    fn flatten_derive_from_parts() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(from_parts)]
+            struct FromPartsStruct<T> {
+                enable: bool,
+                #[flatten(prefix = "n_")]
+                nested: FromPartsNested,
+                #[flatten(rename(value = other))]
+                other: FromPartsGeneric<T>,
+                extra: T,
+            }
+
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(from_parts, split)]
+            struct FromPartsTuple {
+                #[flatten]
+                nested: FromPartsNested,
+            }
+        );
+
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct FromPartsNested {
+                value: f32,
+                name: String,
+            }
+
+            #[derive(Debug, PartialEq)]
+            struct FromPartsGeneric<T> {
+                value: T,
+            }
+        );
+
+        let nested = FromPartsNested {
+            value: 1.0,
+            name: "nested".to_string(),
+        };
+        assert_eq!(
+            FromPartsStruct::from_parts(nested, FromPartsGeneric { value: 2u8 }, true, 3),
+            FromPartsStruct {
+                enable: true,
+                n_value: 1.0,
+                n_name: "nested".to_string(),
+                other: 2,
+                extra: 3,
+            }
+        );
+        let from_parts_tuple = FromPartsTuple::from((FromPartsNested {
+            value: 0.0,
+            name: String::new(),
+        },));
+        assert_eq!(from_parts_tuple.value, 0.0);
+        let (nested,) = from_parts_tuple.into();
+        assert_eq!(FromPartsTuple::from((nested,)).value, 0.0);
+    }
+
+    #[test]
+    fn flatten_no_inspect() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten(no_inspect)]
+            struct NoInspectStruct {
+                enable: bool,
+                #[flatten]
+                nested: NoInspectNested,
+            }
+
+            #[allow(unused)]
+            struct NoInspectNested {
+                value: f32,
+            }
+
+            // Would clash with the inspection macro of `NoInspectStruct`
+            #[allow(unused)]
+            #[flatten_macro_name(NoInspectStruct)]
+            struct NoInspectOther {
+                value: f32,
+            }
+        );
+
+        let no_inspect_struct = NoInspectStruct {
+            enable: true,
+            value: 1.0,
+        };
+        assert_eq!(no_inspect_struct.field_names(), ["enable", "value"]);
+    }
+
+    #[test]
+    fn flatten_enum() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            enum EnumEvent {
+                Created {
+                    #[flatten]
+                    common: EnumCommon,
+                    name: String,
+                },
+                #[allow(unused)]
+                Updated {
+                    #[flatten(prefix = "old_")]
+                    old: EnumCommon,
+                    #[flatten]
+                    common: EnumCommon,
+                },
+                Tuple(u8, bool),
+                Unit,
+            }
+
+            #[allow(unused)]
+            struct EnumCommon {
+                pub id: u64,
+                ts: u64 = 1,
+            }
+        );
+
+        let created = EnumEvent::Created {
+            id: 0,
+            ts: 1,
+            name: "created".to_string(),
+        };
+        let EnumEvent::Created { id, ts, .. } = &created else {
+            unreachable!();
+        };
+        assert_eq!((*id, *ts), (0, 1));
+        assert_ne!(created, EnumEvent::Tuple(0, true));
+        assert_ne!(created, EnumEvent::Unit);
+        let updated = EnumEvent::Updated {
+            old_id: 0,
+            old_ts: 1,
+            id: 0,
+            ts: 2,
+        };
+        assert_ne!(created, updated);
+    }
+
+    #[test]
+    fn flatten_prefix_auto() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct PrefixAutoStruct {
+                id: u64,
+                #[flatten(prefix = auto)]
+                created: PrefixAutoNested,
+                #[flatten(prefix = auto, rename(by = updater))]
+                updated: PrefixAutoNested,
+            }
+
+            #[allow(unused)]
+            struct PrefixAutoNested {
+                id: u64,
+                ts: u64,
+                by: &'static str,
+            }
+        );
+
+        assert_eq!(
+            PrefixAutoStruct::FIELD_NAMES,
+            [
+                "id",
+                "created_id",
+                "ts",
+                "by",
+                "updated_id",
+                "updated_ts",
+                "updater"
+            ]
+        );
+    }
+
+    #[test]
+    fn flatten_derive_eq_ignoring() {
+        flatten_structs!(
+            #[allow(unused)]
+            #[flatten_derive(eq_ignoring(ts, cache))]
+            struct EqIgnoringStruct<T> {
+                id: u32,
+                #[flatten]
+                nested: EqIgnoringNested,
+                cache: std::sync::Mutex<T>,
+            }
+
+            #[allow(unused)]
+            struct EqIgnoringNested {
+                ts: u64,
+                value: f32,
+            }
+        );
+
+        let eq_ignoring_struct = EqIgnoringStruct {
+            id: 0,
+            ts: 1,
+            value: 2.0,
+            cache: std::sync::Mutex::new(3),
+        };
+        assert!(eq_ignoring_struct.eq_ignoring_volatile(&EqIgnoringStruct {
+            ts: 4,
+            cache: std::sync::Mutex::new(5),
+            ..eq_ignoring_struct
+        }));
+        assert!(!eq_ignoring_struct.eq_ignoring_volatile(&EqIgnoringStruct {
+            id: 1,
+            ts: 1,
+            value: 2.0,
+            cache: std::sync::Mutex::new(3),
+        }));
+    }
+
+    #[test]
+    fn flatten_derive_merge() {
+        flatten_structs!(
+            #[derive(Debug, Default, PartialEq)]
+            #[flatten_derive(merge)]
+            struct MergeConfig<T> {
+                name: String,
+                #[flatten]
+                limits: MergeLimits<T>,
+            }
+
+            #[derive(Debug, Default, PartialEq)]
+            #[flatten_derive(merge(skip(id)))]
+            struct MergeSkipConfig {
+                id: u32,
+                verbose: Option<bool>,
+            }
+
+            #[allow(unused)]
+            struct MergeLimits<T> {
+                retries: Option<u32>,
+                timeout: Option<T>,
+            }
+        );
+
+        let mut config = MergeConfig {
+            name: String::from("defaults"),
+            retries: Some(3),
+            timeout: Some(1.5),
+        };
+        config.merge(MergeConfig {
+            name: String::from("file"),
+            retries: None,
+            timeout: Some(2.5),
+        });
+        assert_eq!(
+            config,
+            MergeConfig {
+                name: String::from("file"),
+                retries: Some(3),
+                timeout: Some(2.5),
+            }
+        );
+
+        let mut skip_config = MergeSkipConfig {
+            id: 1,
+            verbose: Some(true),
+        };
+        skip_config.merge(MergeSkipConfig {
+            id: 2,
+            verbose: None,
+        });
+        assert_eq!(
+            skip_config,
+            MergeSkipConfig {
+                id: 1,
+                verbose: Some(true),
+            }
+        );
+    }
+
+    #[test]
+    fn flatten_derive_merge_many_groups() {
+        // `merge` and `patch` are generated for all 8 groups at once,
+        // without growing the recursion depth per group
+        flatten_structs!(
+            #[derive(Debug, Default, PartialEq)]
+            #[flatten_derive(merge(skip(id)), patch)]
+            struct MergeMixer {
+                id: u32,
+                #[flatten(prefix = "channel_0_")]
+                channel_0: MergeChannel,
+                #[flatten(prefix = "channel_1_")]
+                channel_1: MergeChannel,
+                #[flatten(prefix = "channel_2_")]
+                channel_2: MergeChannel,
+                #[flatten(prefix = "channel_3_")]
+                channel_3: MergeChannel,
+                #[flatten(prefix = "channel_4_")]
+                channel_4: MergeChannel,
+                #[flatten(prefix = "channel_5_")]
+                channel_5: MergeChannel,
+                #[flatten(prefix = "channel_6_")]
+                channel_6: MergeChannel,
+                #[flatten(prefix = "channel_7_")]
+                channel_7: MergeChannel,
+            }
+
+            #[allow(unused)]
+            group MergeChannel {
+                gain: Option<u8>,
+                muted: bool,
+            }
+        );
+
+        let mut mixer = MergeMixer {
+            id: 1,
+            channel_0_gain: Some(1),
+            channel_7_gain: Some(2),
+            ..MergeMixer::default()
+        };
+        mixer.merge(MergeMixer {
+            id: 2,
+            channel_7_gain: None,
+            channel_7_muted: true,
+            ..MergeMixer::default()
+        });
+        assert_eq!(mixer.id, 1);
+        assert_eq!(
+            (mixer.channel_0_gain, mixer.channel_7_gain),
+            (Some(1), Some(2))
+        );
+        assert!(mixer.channel_7_muted);
+        mixer.apply(MergeMixerPatch {
+            channel_7_muted: Some(false),
+            ..MergeMixerPatch::default()
+        });
+        assert!(!mixer.channel_7_muted);
+    }
+
+    #[test]
+    fn flatten_derive_const_new() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            #[flatten_derive(const_new)]
+            struct ConstNewStruct {
+                r#type: u8,
+                #[flatten(prefix = "limit_")]
+                limits: ConstNewLimits,
+                name: &'static str,
+            }
+
+            #[allow(unused)]
+            struct ConstNewLimits {
+                max: u32 = 10,
+                min: u32,
+            }
+        );
+
+        static TABLE: [ConstNewStruct; 2] = [
+            ConstNewStruct::new(0, 10, 1, "first"),
+            ConstNewStruct::new(1, 20, 2, "second"),
+        ];
+        assert_eq!(
+            TABLE[1],
+            ConstNewStruct {
+                r#type: 1,
+                limit_max: 20,
+                limit_min: 2,
+                name: "second",
+            }
+        );
+    }
+
+    #[test]
+    fn flatten_derive_metadata() {
+        flatten_structs!(
+            #[allow(unused)]
+            #[flatten_derive(metadata)]
+            struct MetadataStruct<T> {
+                r#type: u8,
+                #[flatten(prefix = "limit_")]
+                limits: MetadataLimits,
+                values: Vec<T>,
+            }
+
+            #[allow(unused)]
+            struct MetadataLimits {
+                max: Option<u32>,
+            }
+        );
+
+        let metadata: Vec<(&str, String)> = MetadataStruct::<f32>::field_metadata()
+            .iter()
+            .map(|(name, ty)| (*name, ty.split_whitespace().collect()))
+            .collect();
+        assert_eq!(
+            metadata,
+            [
+                ("type", "u8".to_owned()),
+                ("limit_max", "Option<u32>".to_owned()),
+                ("values", "Vec<T>".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn flatten_inner_attrs() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct InnerAttrStruct<T> {
+                //! Inner doc comments document the struct.
+                #![derive(Debug, Clone, PartialEq)]
+                #![flatten_derive(metadata)]
+                enable: bool,
                 #[flatten]
-                sn: SubNestedStruct,
+                common: InnerAttrGroup,
+                value: T,
+            }
+
+            group InnerAttrGroup {
+                #![flatten_as(InnerAttrCommon, derive(Debug, PartialEq))]
+                id: u32,
             }
+
+            struct InnerAttrTuple(#![derive(Debug, PartialEq)] u8, f32);
+        );
+
+        let inner_attr = InnerAttrStruct {
+            enable: true,
+            id: 1,
+            value: 2.0,
+        };
+        assert_eq!(inner_attr.clone(), inner_attr);
+        assert_eq!(
+            InnerAttrStruct::<f32>::field_metadata(),
+            [("enable", "bool"), ("id", "u32"), ("value", "T")]
         );
+        assert_eq!(InnerAttrCommon { id: 1 }, InnerAttrCommon { id: 1 });
+        assert_eq!(InnerAttrTuple(1, 2.0), InnerAttrTuple(1, 2.0));
+    }
+
+    #[test]
+    fn flatten_postprocess() {
+        macro_rules! postprocess_with_defaults {
+            (
+                attrs = { $(#[$attr:meta])* },
+                vis = { $vis:vis },
+                name = $name:ident,
+                generics = {},
+                where_clause = {},
+                fields = {$(
+                    $(#[$field_attr:meta])*
+                    $field_vis:vis $field_name:ident: $field_type:ty $(= $field_default:expr)?,
+                )*},
+            ) => {
+                $(#[$attr])*
+                $vis struct $name {$(
+                    $(#[$field_attr])*
+                    $field_vis $field_name: $field_type,
+                )*}
+                impl $name {
+                    const DEFAULTS: &'static [&'static str] = &[$($(
+                        ::core::stringify!($field_default),
+                    )?)*];
+                }
+            };
+        }
 
         flatten_structs!(
+            #[flatten(postprocess = postprocess_with_defaults)]
+            #[derive(Debug, PartialEq)]
+            struct PostprocessStruct {
+                enable: bool = true,
+                #[flatten]
+                nested: PostprocessNested,
+            }
+
             #[allow(unused)]
-            struct SubNestedStruct {
-                min: f32,
-                max: f32,
+            struct PostprocessNested {
+                value: f32 = 1.5,
+                count: u8,
             }
         );
 
-        let base_struct = BaseStruct {
+        assert_eq!(PostprocessStruct::DEFAULTS, ["true", "1.5"]);
+        assert_eq!(PostprocessStruct::FIELD_NAMES, ["enable", "value", "count"]);
+        assert_eq!(
+            PostprocessStruct::default(),
+            PostprocessStruct {
+                enable: true,
+                value: 1.5,
+                count: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn flatten_strict() {
+        flatten_structs!(
+            #[flatten(strict)]
+            #[derive(Debug, Default, PartialEq)]
+            struct StrictStruct<T> {
+                enable: bool,
+                #[flatten]
+                values: StrictValues<T>,
+                #[flatten]
+                limits: Option<Box<StrictLimits>>,
+            }
+
+            #[derive(Debug, Default, PartialEq)]
+            struct StrictValues<T> {
+                value: T,
+            }
+
+            #[derive(Debug, Default, PartialEq)]
+            struct StrictLimits {
+                max: u32,
+            }
+        );
+
+        let strict = StrictStruct {
             enable: true,
-            value: 0.0,
-            goal: 10.0,
-            min: 0.0,
-            max: 20.0,
+            value: 1.0,
+            max: Some(2),
         };
-        assert!(base_struct.enable);
+        assert_eq!(strict.value, 1.0);
+        assert_eq!(strict.max, Some(2));
     }
 
     #[test]
-    fn flatten_serde_struct() {
+    fn flatten_derive_doc_origins() {
+        // Declares the struct with its attributes and lists them, the docs of
+        // the struct are the expanded output of `doc_origins`
+        macro_rules! doc_origins_attrs {
+            (
+                attrs = { $(#[$attr:meta])* },
+                vis = { $vis:vis },
+                name = $name:ident,
+                generics = {},
+                where_clause = {},
+                fields = { $($fields:tt)* },
+            ) => {
+                $(#[$attr])*
+                #[allow(unused)]
+                $vis struct $name { $($fields)* }
+
+                const DOC_ORIGINS_ATTRS: &[&str] = &[$(::core::stringify!($attr)),*];
+            };
+        }
+
         flatten_structs!(
-            #[derive(Serialize, Deserialize)]
-            pub struct BaseSerdeStruct {
+            /// A struct with documented origins.
+            #[flatten_derive(doc_origins, default)]
+            #[flatten(postprocess = doc_origins_attrs)]
+            struct DocOriginsStruct {
                 enable: bool,
                 #[flatten]
-                nested: NestedSerdeStruct,
+                nested: DocOriginsNested,
+                #[flatten(prefix = "other_")]
+                other: Option<DocOriginsNested>,
+            }
+
+            #[allow(unused)]
+            #[flatten_derive(doc_origins)]
+            struct DocOriginsNested {
+                value: u8,
+            }
+        );
+
+        let doc_origins_struct = DocOriginsStruct::default();
+        assert_eq!(doc_origins_struct.other_value, None);
+        let attrs: Vec<String> = DOC_ORIGINS_ATTRS
+            .iter()
+            .map(|attr| attr.split_whitespace().collect())
+            .collect();
+        assert_eq!(
+            attrs,
+            [
+                "doc=r\"Astructwithdocumentedorigins.\"",
+                "doc=\"\"",
+                "doc=\"Flattenedfrom:\"",
+                "doc=::core::concat!(\"-`\",::core::stringify!(DocOriginsNested),\"`\")",
+                "doc=::core::concat!(\"-`\",::core::stringify!\
+                 (::core::option::Option<DocOriginsNested>),\"`\")",
+            ]
+        );
+    }
+
+    #[test]
+    fn flatten_order() {
+        flatten_structs!(
+            #[derive(Debug, Default, PartialEq)]
+            #[repr(C)]
+            #[flatten(order(value, goal, enable))]
+            struct OrderStruct {
+                enable: bool,
+                #[flatten]
+                nested: OrderNested,
+            }
+
+            #[allow(unused)]
+            struct OrderNested {
+                goal: f32,
+                value: u64,
+            }
+        );
+
+        assert_eq!(OrderStruct::FIELD_NAMES, ["value", "goal", "enable"]);
+        assert_eq!(std::mem::offset_of!(OrderStruct, value), 0);
+        assert_eq!(std::mem::offset_of!(OrderStruct, goal), 8);
+        assert_eq!(std::mem::offset_of!(OrderStruct, enable), 12);
+    }
+
+    #[test]
+    fn flatten_field_types() {
+        trait Output {
+            type Out;
+        }
+
+        impl Output for u8 {
+            type Out = u16;
+        }
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct TypesStruct<'a, T: Output> {
+                map: std::collections::HashMap<String, f32>,
+                nested_generic: Vec<Option<std::collections::BTreeMap<u8, Vec<T>>>>,
+                associated: <T as Output>::Out,
+                callback: fn(&str) -> Option<usize>,
+                tuple: (u8, [f32; 2]),
+                reference: &'a [u8],
+                boxed: Box<dyn Fn(u8) -> u8 + 'a>,
+                #[flatten]
+                nested: TypesNested<std::collections::HashMap<String, Vec<Option<T>>>, T>,
             }
         );
 
         flatten_structs!(
             #[allow(unused)]
-            #[derive(Serialize, Deserialize)]
-            struct NestedSerdeStruct {
-                min: Option<f32>,
-                #[serde(skip_serializing_if = "Option::is_none")]
-                max: Option<f32>,
+            struct TypesNested<M, T: Output> {
+                inner_map: M,
+                inner_associated: Option<<T as Output>::Out>,
+                inner_qualified: ::core::option::Option<T>,
             }
         );
 
-        let base_struct = BaseSerdeStruct {
-            enable: false,
-            min: Some(0.0),
-            max: None,
+        let types_struct = TypesStruct::<u8> {
+            map: std::collections::HashMap::new(),
+            nested_generic: vec![None],
+            associated: 1u16,
+            callback: |value| value.find('a'),
+            tuple: (2, [3.0, 4.0]),
+            reference: &[5],
+            boxed: Box::new(|value| value + 1),
+            inner_map: std::collections::HashMap::from([("six".to_owned(), vec![Some(6)])]),
+            inner_associated: Some(7u16),
+            inner_qualified: Some(8),
         };
-        let base_struct_json = serde_json::to_string_pretty(&base_struct).unwrap();
-        pretty_assertions::assert_eq!(
-            r#"{
-  "enable": false,
-  "min": 0.0
-}"#,
-            base_struct_json
+        assert_eq!((types_struct.callback)("bab"), Some(1));
+        assert_eq!((types_struct.boxed)(types_struct.tuple.0), 3);
+        assert_eq!(types_struct.inner_map["six"], [Some(6)]);
+        assert_eq!(types_struct.inner_associated, Some(7));
+    }
+
+    #[test]
+    fn flatten_box() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct BoxStruct {
+                enable: bool,
+                #[flatten(reconstruct)]
+                boxed: Box<BoxNested>,
+                #[flatten(prefix = "option_")]
+                option: Option<Box<BoxNested>>,
+            }
+        );
+
+        flatten_structs!(
+            #[derive(Clone, Debug, PartialEq)]
+            struct BoxNested {
+                value: f32,
+            }
+        );
+
+        let box_struct = BoxStruct {
+            enable: true,
+            value: 1.0,
+            option_value: None,
+        };
+        assert_eq!(box_struct.to_boxed(), BoxNested { value: 1.0 });
+    }
+
+    #[test]
+    fn flatten_inspect() {
+        flatten_structs!(
+            #[allow(unused)]
+            struct InspectStruct {
+                enable: bool,
+                #[flatten(prefix = "n_")]
+                nested: InspectNested,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct InspectNested {
+                value: f32 = 1.0,
+            }
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct InspectTuple(u8, #[flatten] InspectPair);
+        );
+
+        flatten_structs!(
+            #[allow(unused)]
+            struct InspectPair(u16, u32);
+        );
+
+        macro_rules! field_names {
+            (
+                prefix = $prefix:literal,
+                fields = {$(
+                    $(#[$attr:meta])*
+                    $vis:vis $name:ident: $ty:ty $(= $default:expr)?,
+                )*}
+            ) => {
+                [$(concat!($prefix, stringify!($name))),*]
+            };
+        }
+
+        macro_rules! field_count {
+            (tuple_fields = {$(
+                $(#[$attr:meta])*
+                $vis:vis $ty:ty,
+            )*}) => {
+                [$(stringify!($ty)),*].len()
+            };
+        }
+
+        assert_eq!(
+            inspect!(InspectStruct => field_names!(prefix = "inspect_",)),
+            ["inspect_enable", "inspect_n_value"]
+        );
+        assert_eq!(inspect!(InspectTuple => field_count!), 3);
+    }
+
+    #[test]
+    fn flatten_deprecated_fields() {
+        flatten_structs!(
+            #[derive(Clone, Debug, Default, PartialEq)]
+            #[flatten_derive(builder, accessors, display, patch)]
+            struct DeprecatedFieldStruct {
+                #[deprecated(note = "use `enable` instead")]
+                enabled: bool,
+                enable: bool,
+                #[flatten(reconstruct, into_nested, field_count)]
+                nested: DeprecatedFieldNested,
+            }
+        );
+
+        flatten_structs!(
+            #[derive(Clone, Debug, Default, PartialEq)]
+            struct DeprecatedFieldNested {
+                #[deprecated(note = "use `value` instead")]
+                old_value: f32,
+                value: f32,
+            }
+        );
+
+        macro_rules! deprecated_fields {
+            (fields = {$(
+                $(#[$($attr:tt)*])*
+                $vis:vis $name:ident: $ty:ty $(= $default:expr)?,
+            )*}) => {
+                [$(stringify!($(#[$($attr)*])* $name)),*]
+            };
+        }
+
+        let fields = inspect!(DeprecatedFieldStruct => deprecated_fields!);
+        assert!(fields[0].starts_with("#[deprecated"));
+        assert_eq!(fields[1], "enable");
+        assert!(fields[2].starts_with("#[deprecated"));
+        assert_eq!(fields[3], "value");
+
+        #[allow(deprecated)]
+        fn use_deprecated_fields() {
+            let deprecated_struct = DeprecatedFieldStruct {
+                enabled: true,
+                enable: true,
+                old_value: 1.0,
+                value: 1.0,
+            };
+            assert_eq!(deprecated_struct.to_nested().old_value, 1.0);
+        }
+        use_deprecated_fields();
+
+        flatten_structs!(
+            #[flatten_derive(from_parts)]
+            struct DeprecatedTypeStruct {
+                #[flatten]
+                nested: DeprecatedTypeNested,
+            }
+        );
+
+        flatten_structs!(
+            #[deprecated(note = "use `DeprecatedFieldNested` instead")]
+            struct DeprecatedTypeNested {
+                value: f32,
+            }
+        );
+
+        #[allow(deprecated)]
+        fn use_deprecated_type() {
+            let deprecated_struct =
+                DeprecatedTypeStruct::from((DeprecatedTypeNested { value: 1.0 },));
+            assert_eq!(deprecated_struct.value, 1.0);
+        }
+        use_deprecated_type();
+    }
+
+    #[test]
+    fn flatten_group() {
+        flatten_structs!(
+            #[derive(Debug, PartialEq)]
+            struct GroupStruct {
+                enable: bool,
+                #[flatten(prefix = "created_")]
+                created: GroupFields,
+                #[flatten(prefix = "updated_")]
+                updated: GroupFields,
+            }
+
+            #[flatten_as(GroupCopy, derive(Debug, Default, PartialEq))]
+            group GroupFields {
+                id: u64,
+                ts: u64 = 1,
+            }
+        );
+
+        let group_struct = GroupStruct {
+            enable: true,
+            created_id: 0,
+            created_ts: 1,
+            updated_id: 2,
+            updated_ts: 3,
+        };
+        assert_eq!(group_struct.updated_id, 2);
+        assert_eq!(
+            GroupStruct::FIELD_NAMES,
+            [
+                "enable",
+                "created_id",
+                "created_ts",
+                "updated_id",
+                "updated_ts"
+            ]
+        );
+        assert_eq!(GroupCopy::default(), GroupCopy { id: 0, ts: 0 });
+    }
+
+    #[test]
+    fn flatten_validate_attrs() {
+        // Groups don't declare a struct, so attributes of other derive
+        // macros can be checked without depending on them
+        flatten_structs!(
+            group ValidatedForm {
+                #[validate(length(min = 1))]
+                name: String,
+                #[flatten(prefix = "upload_")]
+                upload: ValidatedLimits,
+            }
+
+            group ValidatedLimits {
+                #[validate(range(min = 0, max = 10), message = "too many retries")]
+                retries: u32,
+                #[serde(default)]
+                #[validate(custom(function = "crate::validate_timeout"))]
+                timeout: u64 = 30,
+            }
+        );
+
+        assert_eq!(
+            dump_fields!(ValidatedForm),
+            "#[validate(length(min = 1))] name: String,\n\
+             #[validate(range(min = 0, max = 10), message = \"too many retries\")] upload_retries: u32,\n\
+             #[serde(default)] #[validate(custom(function = \"crate::validate_timeout\"))] upload_timeout: u64 = 30,\n"
+        );
+    }
+
+    #[test]
+    fn flatten_empty() {
+        flatten_structs!(
+            #[derive(Debug, Default, PartialEq)]
+            #[flatten_derive(builder, merge, patch, from_parts)]
+            struct EmptyContainer {
+                #[flatten(prefix = "empty_", reconstruct, view, field_count)]
+                empty: EmptyStruct,
+                value: u8,
+            }
+
+            #[derive(Clone, Debug, Default, PartialEq)]
+            #[flatten_derive(builder, split, eq_ignoring())]
+            struct EmptyStruct {}
+
+            #[derive(Debug, Default)]
+            struct OnlyEmpty {
+                #[flatten]
+                empty: EmptyStruct,
+                #[flatten]
+                placeholder: EmptyGroup,
+            }
+
+            group EmptyGroup {}
+        );
+
+        assert_eq!(EmptyContainer::FIELD_NAMES, ["value"]);
+        assert_eq!(EmptyContainer::EMPTY_FIELD_COUNT, 0);
+        assert_eq!(EmptyStruct::FIELD_COUNT, 0);
+        assert_eq!(OnlyEmpty::FIELD_NAMES, [] as [&str; 0]);
+        let container = EmptyContainer { value: 1 };
+        assert_eq!(container.to_empty(), EmptyStruct {});
+        let EmptyContainerEmptyRef { .. } = container.empty();
+        assert_eq!(EmptyContainer::from_parts(EmptyStruct {}, 1), container);
+        assert_eq!(dump_fields!(EmptyGroup), "");
+    }
+
+    #[test]
+    fn flatten_dump_fields() {
+        flatten_structs!(
+            #[allow(unused)]
+            pub struct DumpStruct {
+                #[doc = "Enabled"]
+                pub(crate) enable: bool,
+                #[flatten]
+                nested: DumpNested,
+            }
+
+            #[allow(unused)]
+            struct DumpNested {
+                value: Option<f32> = Some(1.0),
+            }
+
+            #[allow(unused)]
+            struct DumpTuple(pub u8, [f32; 2]);
+        );
+
+        assert_eq!(
+            dump_fields!(DumpStruct),
+            "#[doc = \"Enabled\"] pub(crate) enable: bool,\nvalue: Option < f32 > = Some(1.0),\n"
         );
+        assert_eq!(dump_fields!(DumpTuple), "pub u8,\n[f32; 2],\n");
     }
 }
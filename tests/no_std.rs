@@ -0,0 +1,48 @@
+//! Checks that the generated code only depends on `core`.
+#![no_std]
+
+use flatten_structs::{MissingField, flatten_structs};
+
+flatten_structs!(
+    #[derive(Debug, Clone, PartialEq)]
+    #[flatten_derive(builder, accessors, patch)]
+    struct Frame {
+        #[flatten(prefix = "header_", reconstruct, view)]
+        header: Header,
+        payload: [u8; 2],
+    }
+);
+
+flatten_structs!(
+    #[derive(Debug, Clone, PartialEq)]
+    struct Header {
+        id: u16,
+        len: u16 = 0,
+    }
+);
+
+#[test]
+fn flatten_no_std() {
+    let mut frame = Frame {
+        header_id: 1,
+        header_len: 2,
+        payload: [3, 4],
+    };
+    assert_eq!(Frame::FIELD_NAMES, ["header_id", "header_len", "payload"]);
+    assert_eq!(frame.to_header(), Header { id: 1, len: 2 });
+    assert_eq!(*frame.header().id, 1);
+
+    frame.set_header_id(5);
+    frame.apply(FramePatch {
+        payload: Some([0, 0]),
+        ..Default::default()
+    });
+    assert_eq!(*frame.header_id(), 5);
+    assert_eq!(frame.payload, [0, 0]);
+
+    assert_eq!(Header::default(), Header { id: 0, len: 0 });
+    assert_eq!(
+        FrameBuilder::default().with_header_id(1).build(),
+        Err(MissingField { field: "payload" })
+    );
+}